@@ -0,0 +1,36 @@
+#![no_main]
+
+use cag::{
+    context_finder::{builtin, InputType},
+    json::{JsonConfig, JsonLog},
+};
+use clap::ValueEnum;
+use libfuzzer_sys::fuzz_target;
+
+// Indexes arbitrary input with every input type, in two chunks split where
+// the first byte says, and looks up the context of every line.
+fuzz_target!(|data: &[u8]| {
+    let Some((&split, input)) = data.split_first() else {
+        return;
+    };
+    let lines: Vec<String> = String::from_utf8_lossy(input)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    let split = usize::from(split).min(lines.len());
+    let json = JsonLog::new(&JsonConfig {
+        group: Some("id".to_string()),
+        ..JsonConfig::default()
+    });
+    for input_type in InputType::value_variants() {
+        let mut finder = builtin(*input_type, Some(json.clone()));
+        finder.extend(&lines[..split].to_vec());
+        finder.extend(&lines);
+        for position in 0..=lines.len() {
+            let context = finder.get_context(&lines, position);
+            finder.identifier(&context);
+            finder.diff_location(&lines, position);
+        }
+        finder.discard(split);
+    }
+});
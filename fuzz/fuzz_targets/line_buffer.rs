@@ -0,0 +1,26 @@
+#![no_main]
+
+use cag::storage::{LineBuffer, Storage};
+use libfuzzer_sys::fuzz_target;
+
+// Spills arbitrary lines to a temporary file after the number of bytes the
+// first byte says, and checks they read back unchanged.
+fuzz_target!(|data: &[u8]| {
+    let Some((&spill_over, input)) = data.split_first() else {
+        return;
+    };
+    let lines: Vec<String> = String::from_utf8_lossy(input)
+        .split('\n')
+        .map(str::to_string)
+        .collect();
+    let mut buffer = LineBuffer::new(Some(usize::from(spill_over)));
+    for chunk in lines.chunks(7) {
+        buffer.extend(chunk.to_vec()).unwrap();
+    }
+    assert_eq!(buffer.len(), lines.len());
+    for (index, line) in lines.iter().enumerate().rev() {
+        assert_eq!(buffer.line(index), line.as_str());
+    }
+    buffer.drain_front(lines.len() / 2);
+    assert_eq!(buffer.lines(0..buffer.len()).as_ref(), &lines[lines.len() / 2..]);
+});
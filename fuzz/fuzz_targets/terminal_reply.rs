@@ -0,0 +1,9 @@
+#![no_main]
+
+use cag::background::parse_reply;
+use libfuzzer_sys::fuzz_target;
+
+// Whatever the terminal answers the background color query with.
+fuzz_target!(|data: &[u8]| {
+    parse_reply(&String::from_utf8_lossy(data));
+});
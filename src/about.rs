@@ -0,0 +1,87 @@
+use std::{env, process::Command};
+
+use serde_json::Value;
+
+use crate::error::Error;
+
+/// Where the latest release of cag is looked up.
+const CRATES_IO_URL: &str = "https://crates.io/api/v1/crates/cag";
+/// Seconds to wait for crates.io before giving up.
+const UPDATE_CHECK_TIMEOUT: &str = "10";
+
+/// Name and version of this build.
+pub fn version() -> String {
+    format!("cag {}", env!("CARGO_PKG_VERSION"))
+}
+
+/// What this build supports and how, one `name: how` line each, for bug
+/// reports and for `--version --verbose`.
+pub fn features() -> Vec<String> {
+    let platform = format!("{}-{}", env::consts::ARCH, env::consts::OS);
+    [
+        ("clipboard", "OSC 52, through the terminal"),
+        ("syntax highlighting", "diffs only, no syntect"),
+        ("watching files", "no, pipe from `tail -f` instead"),
+        (
+            "sharing over sockets",
+            if cfg!(unix) {
+                "--serve, --attach"
+            } else {
+                "no"
+            },
+        ),
+        (
+            "kitty keyboard protocol",
+            if cfg!(unix) { "yes" } else { "no" },
+        ),
+        ("platform", &platform),
+    ]
+    .iter()
+    .map(|(name, how)| format!("{name}: {how}"))
+    .collect()
+}
+
+/// Asks crates.io for the latest release with curl, only ever when asked to
+/// with `--check-update`.
+pub fn latest_release() -> Result<String, Error> {
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--max-time"])
+        .arg(UPDATE_CHECK_TIMEOUT)
+        // crates.io turns away requests without a user agent
+        .args(["--user-agent", &version().replace(' ', "/"), CRATES_IO_URL])
+        .output()
+        .map_err(|err| Error::UpdateCheck(err.to_string()))?;
+    if !output.status.success() {
+        return Err(Error::UpdateCheck(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    parse_latest_release(&output.stdout)
+        .ok_or_else(|| Error::UpdateCheck("Unexpected answer from crates.io".to_string()))
+}
+
+/// The latest stable version in a crates.io crate answer.
+fn parse_latest_release(answer: &[u8]) -> Option<String> {
+    let answer: Value = serde_json::from_slice(answer).ok()?;
+    let krate = answer.get("crate")?;
+    krate
+        .get("max_stable_version")
+        .filter(|version| !version.is_null())
+        .or_else(|| krate.get("max_version"))?
+        .as_str()
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_latest_release;
+
+    #[test]
+    fn parse_crates_io_answer() {
+        let answer = br#"{"crate":{"id":"cag","max_version":"0.1.0-rc.1","max_stable_version":"0.0.3"},"versions":[]}"#;
+        assert_eq!(parse_latest_release(answer), Some("0.0.3".to_string()));
+        let answer = br#"{"crate":{"max_version":"0.1.0-rc.1","max_stable_version":null}}"#;
+        assert_eq!(parse_latest_release(answer), Some("0.1.0-rc.1".to_string()));
+        assert_eq!(parse_latest_release(b"<html>"), None);
+    }
+}
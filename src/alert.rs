@@ -0,0 +1,49 @@
+use regex::RegexSet;
+
+use crate::error::Error;
+
+/// Patterns that raise an alert when a line matching them is read while
+/// following the input.
+#[derive(Debug)]
+pub struct Alerts {
+    patterns: RegexSet,
+}
+
+impl Alerts {
+    pub fn new(patterns: &[String]) -> Result<Self, Error> {
+        Ok(Alerts {
+            patterns: RegexSet::new(patterns)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Numbers of the lines in `lines` matching any of the patterns, counting
+    /// from `start`.
+    pub fn matching(&self, lines: &[String], start: usize) -> Vec<usize> {
+        lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| self.patterns.is_match(line))
+            .map(|(index, _)| start + index)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Alerts;
+
+    #[test]
+    fn find_alerting_lines() {
+        let alerts = Alerts::new(&["ERROR|panic".to_string(), "^FATAL".to_string()]).unwrap();
+        let lines: Vec<String> = ["ok", "ERROR: disk full", "thread panicked", "not FATAL"]
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        assert_eq!(alerts.matching(&lines, 10), vec![11, 12]);
+        assert!(Alerts::new(&[]).unwrap().matching(&lines, 0).is_empty());
+    }
+}
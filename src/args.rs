@@ -0,0 +1,182 @@
+use std::{ffi::OsString, num::NonZeroUsize, path::PathBuf};
+
+use clap::Parser;
+
+use crate::{context_finder::InputType, diff::ColorMode, error::Error};
+
+/// Environment variables with default flags, like `LESS` for less, of which
+/// the first one set is used.
+const ENVIRONMENT_VARIABLES_DEFAULTS: [&str; 2] = ["CAG", "PAGER_OPTS"];
+
+#[derive(Parser, Debug, Clone)]
+#[command(
+    version,
+    about,
+    disable_version_flag = true,
+    // Flags given override the defaults from the environment
+    args_override_self = true
+)]
+pub struct Args {
+    /// Print version
+    #[arg(short = 'V', long)]
+    pub version: bool,
+    /// With --version, also list what this build supports
+    #[arg(long, requires = "version")]
+    pub verbose: bool,
+    /// With --version, also check crates.io for a newer release
+    #[arg(long, requires = "version")]
+    pub check_update: bool,
+    /// Files to read instead of stdin, one at a time like `less a b c`,
+    /// switched between with :n and :p
+    pub input: Vec<PathBuf>,
+    /// Read the system clipboard instead of stdin, with wl-paste, xclip or
+    /// xsel (pbpaste on macOS)
+    #[arg(long, conflicts_with_all = ["input", "merge", "attach", "resume"])]
+    pub from_clipboard: bool,
+    /// Run a shell command and page its output instead of stdin, with r to
+    /// run it again, e.g. --exec "git log -p --all"
+    #[arg(
+        long,
+        value_name = "CMD",
+        conflicts_with_all = ["input", "merge", "attach", "resume", "from_clipboard"]
+    )]
+    pub exec: Option<String>,
+    /// Interleave several timestamped logs into one chronologically ordered view
+    #[arg(long, num_args = 2.., value_name = "FILE", conflicts_with = "input")]
+    pub merge: Vec<PathBuf>,
+    /// Kind of input, deciding what is shown as the context
+    #[arg(long = "type", value_enum, default_value_t)]
+    pub input_type: InputType,
+    /// When to color added and removed lines, hunk headers and commit
+    /// metadata of diffs
+    #[arg(long, value_enum, default_value_t)]
+    pub color: ColorMode,
+    /// Start at the first file of a diff whose path matches REGEX, or at the
+    /// first line matching it for other input types
+    #[arg(long, value_name = "REGEX")]
+    pub pattern: Option<String>,
+    /// Annotate lines in a gutter, e.g. with their commit or log level
+    #[arg(long)]
+    pub gutter: bool,
+    /// Show removed and added lines of diff hunks side by side
+    #[arg(long)]
+    pub side_by_side: bool,
+    /// Show the input piped through CMD, like `delta` or `diff-so-fancy`,
+    /// while still finding the context in the input itself
+    #[arg(long, value_name = "CMD", conflicts_with = "max_lines")]
+    pub preprocess: Option<String>,
+    /// Show at most LINES lines of context above the input, the rest can be
+    /// scrolled with Shift-Up and Shift-Down
+    #[arg(long, value_name = "LINES", default_value_t = 6)]
+    pub context_height: usize,
+    /// Wrap long lines onto the next rows instead of cutting them at the
+    /// edge of the screen
+    #[arg(long)]
+    pub wrap: bool,
+    /// Print the input and exit instead of paging it if it fits on one screen
+    #[arg(short = 'F', long, visible_alias = "no-alt-screen")]
+    pub quit_if_one_screen: bool,
+    /// Keep only the last N lines, dropping the oldest ones, to page
+    /// unbounded pipes in bounded memory
+    #[arg(long, value_name = "N")]
+    pub max_lines: Option<NonZeroUsize>,
+    /// Keep the lines in a temporary file instead of memory once they take
+    /// more than MB megabytes, for inputs of several gigabytes
+    #[arg(long, value_name = "MB")]
+    pub spill_over: Option<NonZeroUsize>,
+    /// Print the time spent on each commit on exit
+    #[arg(long)]
+    pub review_stats: bool,
+    /// Pick hunks of a diff with Space to stage them with `git apply
+    /// --cached` on quit, or to write them to FILE if given
+    #[arg(long, value_name = "FILE", num_args = 0..=1, require_equals = true)]
+    pub pick_hunks: Option<Option<PathBuf>>,
+    /// Config file to use instead of ~/.config/cag/config.toml
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Reopen a snapshot saved with the :save command instead of reading input
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["input", "merge", "attach"])]
+    pub resume: Option<PathBuf>,
+    /// Experimental: read the input and share it over a Unix socket with
+    /// pagers started with --attach instead of showing it
+    #[arg(long, value_name = "SOCKET", conflicts_with = "attach")]
+    pub serve: Option<PathBuf>,
+    /// Experimental: page the input shared by a cag started with --serve
+    #[arg(long, value_name = "SOCKET", conflicts_with_all = ["input", "merge"])]
+    pub attach: Option<PathBuf>,
+}
+
+/// `args` with the default flags of the environment, read with `var`, put
+/// right after the program name so that those given override them. This
+/// way `CAG='--gutter -F'` applies also when cag is started by git. The flags
+/// are split like a shell would, so `CAG="--pattern 'fn main'"` keeps the
+/// pattern whole.
+pub fn with_defaults(
+    args: impl IntoIterator<Item = OsString>,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<OsString>, Error> {
+    let defaults = match ENVIRONMENT_VARIABLES_DEFAULTS
+        .into_iter()
+        .find_map(|name| Some((name, var(name)?)))
+    {
+        Some((name, flags)) => {
+            shell_words::split(&flags).map_err(|err| Error::DefaultFlags(name, err))?
+        }
+        None => Vec::new(),
+    };
+    let mut args = args.into_iter();
+    Ok(args
+        .next()
+        .into_iter()
+        .chain(defaults.into_iter().map(OsString::from))
+        .chain(args)
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsString;
+
+    use clap::Parser;
+
+    use super::{with_defaults, Args};
+    use crate::{context_finder::InputType, error::Error};
+
+    fn parse(args: &[&str], cag: Option<&str>, pager_opts: Option<&str>) -> Args {
+        let args = with_defaults(args.iter().map(OsString::from), |name| match name {
+            "CAG" => cag.map(String::from),
+            "PAGER_OPTS" => pager_opts.map(String::from),
+            _ => None,
+        });
+        Args::try_parse_from(args.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn default_flags_from_environment() {
+        let args = parse(&["cag", "a.log"], Some(" --gutter  -F "), None);
+        assert!(args.gutter && args.quit_if_one_screen);
+        assert_eq!(args.input, ["a.log"].map(std::path::PathBuf::from));
+
+        // Flags given win, also when they are given again
+        let args = parse(
+            &["cag", "--type", "json", "--gutter"],
+            None,
+            Some("--type syslog --gutter"),
+        );
+        assert_eq!(args.input_type, InputType::Json);
+        assert!(args.gutter);
+
+        let args = parse(&["cag"], Some(""), Some("--gutter"));
+        assert!(!args.gutter);
+    }
+
+    #[test]
+    fn quoted_default_flags() {
+        let args = parse(&["cag"], Some(r#"--pattern "foo bar" -F"#), None);
+        assert_eq!(args.pattern.as_deref(), Some("foo bar"));
+        assert!(args.quit_if_one_screen);
+
+        let unterminated = with_defaults(["cag".into()], |_| Some("--pattern 'foo".to_string()));
+        assert!(matches!(unterminated, Err(Error::DefaultFlags("CAG", _))));
+    }
+}
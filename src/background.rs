@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+/// Brightness of the terminal background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Background {
+    Dark,
+    Light,
+}
+
+/// Asks the terminal for its background color with OSC 11.
+///
+/// Must be called in raw mode and while nothing else is reading terminal
+/// input. Returns `None` if the terminal does not answer within `timeout`.
+#[cfg(unix)]
+pub fn query(timeout: Duration) -> Option<Background> {
+    use std::{
+        fs::OpenOptions,
+        io::{Read, Write},
+        os::unix::io::AsRawFd,
+        time::Instant,
+    };
+
+    let mut tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+    tty.write_all(b"\x1b]11;?\x07").ok()?;
+    tty.flush().ok()?;
+
+    let deadline = Instant::now() + timeout;
+    let mut reply = Vec::new();
+    let mut buf = [0; 64];
+    loop {
+        let remaining = deadline.checked_duration_since(Instant::now())?;
+        let mut fds = libc::pollfd {
+            fd: tty.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: `fds` is a single valid pollfd for the duration of the call.
+        let ready = unsafe { libc::poll(&mut fds, 1, remaining.as_millis() as libc::c_int) };
+        if ready <= 0 {
+            return None;
+        }
+        let read = tty.read(&mut buf).ok()?;
+        if read == 0 {
+            return None;
+        }
+        reply.extend_from_slice(&buf[..read]);
+        // The reply is terminated by BEL or ST (ESC \)
+        if reply.ends_with(b"\x07") || reply.ends_with(b"\x1b\\") {
+            return parse_reply(&String::from_utf8_lossy(&reply));
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn query(_timeout: Duration) -> Option<Background> {
+    None
+}
+
+/// Parses an OSC 11 reply like `ESC ]11;rgb:1e1e/1e1e/2e2e BEL`.
+pub fn parse_reply(reply: &str) -> Option<Background> {
+    let start = reply.find("rgb:")? + "rgb:".len();
+    let end = reply[start..]
+        .find(|c: char| !(c.is_ascii_hexdigit() || c == '/'))
+        .map_or(reply.len(), |end| start + end);
+    let channels = reply[start..end]
+        .split('/')
+        .map(|channel| {
+            let value = u32::from_str_radix(channel, 16).ok()?;
+            let max = 16u32.checked_pow(channel.len() as u32)?.checked_sub(1)?;
+            Some(value as f64 / max as f64)
+        })
+        .collect::<Option<Vec<f64>>>()?;
+    let [red, green, blue] = channels[..] else {
+        return None;
+    };
+    let luminance = 0.2126 * red + 0.7152 * green + 0.0722 * blue;
+    Some(if luminance < 0.5 {
+        Background::Dark
+    } else {
+        Background::Light
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::{parse_reply, Background};
+
+    #[test]
+    fn parse_dark_and_light_replies() {
+        assert_eq!(
+            parse_reply("\x1b]11;rgb:1e1e/1e1e/2e2e\x07"),
+            Some(Background::Dark)
+        );
+        assert_eq!(
+            parse_reply("\x1b]11;rgb:ffff/fafa/f0f0\x1b\\"),
+            Some(Background::Light)
+        );
+        assert_eq!(
+            parse_reply("\x1b]11;rgb:f/f/f\x07"),
+            Some(Background::Light)
+        );
+    }
+
+    #[test]
+    fn reject_malformed_replies() {
+        assert_eq!(parse_reply("\x1b]11;?\x07"), None);
+        assert_eq!(parse_reply("\x1b]11;rgb:ffff/ffff\x07"), None);
+    }
+
+    proptest! {
+        #[test]
+        fn never_panic_on_any_reply(reply in "(\\x1b\\]11;)?(rgb:)?[0-9a-fA-F/]{0,20}.{0,5}") {
+            parse_reply(&reply);
+        }
+
+        #[test]
+        fn parse_any_color(red in 0..=0xffffu32, green in 0..=0xffffu32, blue in 0..=0xffffu32) {
+            let reply = format!("\x1b]11;rgb:{red:04x}/{green:04x}/{blue:04x}\x07");
+            prop_assert!(parse_reply(&reply).is_some());
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::Read,
+};
+
+use clap::Parser;
+use ratatui::{backend::Backend, Terminal};
+
+use crate::{
+    alert::Alerts,
+    args::Args,
+    config::Config,
+    context_finder::{ContextFinder, InputType},
+    error::Error,
+    extract::Extractor,
+    files::FileList,
+    input::{stream_reader, Chunk, Pause},
+    pager::{run_app, Input},
+    rewrite::Rewriter,
+};
+
+/// Builds a pager for a program embedding cag, like a git wrapper showing
+/// its output without spawning the `cag` command.
+///
+/// ```no_run
+/// # fn main() -> Result<(), cag::error::Error> {
+/// use cag::{context_finder::InputType, PagerBuilder};
+/// use ratatui::{backend::CrosstermBackend, Terminal};
+///
+/// let terminal = Terminal::new(CrosstermBackend::new(std::io::stdout()))?;
+/// let log = std::process::Command::new("git")
+///     .args(["log", "--patch"])
+///     .stdout(std::process::Stdio::piped())
+///     .spawn()?;
+/// PagerBuilder::new(terminal)
+///     .reader(log.stdout.unwrap())
+///     .input_type(InputType::Git)
+///     .bind('x', 'q')
+///     .build()
+///     .run()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct PagerBuilder<B: Backend> {
+    terminal: Terminal<B>,
+    reader: Option<Box<dyn Read + Send>>,
+    input_type: InputType,
+    finder: Option<Box<dyn ContextFinder>>,
+    keys: HashMap<char, char>,
+}
+
+impl<B: Backend> PagerBuilder<B> {
+    /// Starts a pager drawing on `terminal`, which the embedding program has
+    /// put into raw mode and restores after, reading stdin as a git log.
+    pub fn new(terminal: Terminal<B>) -> Self {
+        PagerBuilder {
+            terminal,
+            reader: None,
+            input_type: InputType::default(),
+            finder: None,
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Pages what `reader` reads instead of stdin.
+    pub fn reader(mut self, reader: impl Read + Send + 'static) -> Self {
+        self.reader = Some(Box::new(reader));
+        self
+    }
+
+    /// Finds the contexts of `input_type`, and shows it the way cag does.
+    pub fn input_type(mut self, input_type: InputType) -> Self {
+        self.input_type = input_type;
+        self
+    }
+
+    /// Finds the contexts with `finder` instead of the one of the input type.
+    pub fn context_finder(mut self, finder: impl ContextFinder + 'static) -> Self {
+        self.finder = Some(Box::new(finder));
+        self
+    }
+
+    /// Makes `key` act like `bound`, like the `keys` config does.
+    pub fn bind(mut self, key: char, bound: char) -> Self {
+        self.keys.insert(key, bound);
+        self
+    }
+
+    pub fn build(self) -> Pager<B> {
+        let mut args = Args::parse_from(["cag"]);
+        args.input_type = self.input_type;
+        let config = Config {
+            keys: self.keys,
+            ..Config::default()
+        };
+        Pager {
+            terminal: self.terminal,
+            reader: Some(self.reader.unwrap_or_else(|| Box::new(std::io::stdin()))),
+            finder: self.finder,
+            args,
+            config,
+        }
+    }
+}
+
+/// A pager built by [`PagerBuilder`], ready to run.
+pub struct Pager<B: Backend> {
+    terminal: Terminal<B>,
+    reader: Option<Box<dyn Read + Send>>,
+    finder: Option<Box<dyn ContextFinder>>,
+    args: Args,
+    config: Config,
+}
+
+impl<B: Backend> Pager<B> {
+    /// Pages the input until the user quits, returning the summary cag
+    /// prints on exit, like the time spent reviewing, if any. The input is
+    /// read once: running again pages nothing.
+    pub fn run(&mut self) -> Result<Option<String>, Error> {
+        let rewriter = Rewriter::new(&self.config.rewrite)?;
+        let extractor = Extractor::new(&self.config.extract)?;
+        let alerts = Alerts::new(&self.config.alerts)?;
+        let pause = Pause::default();
+        let reader = self
+            .reader
+            .take()
+            .unwrap_or_else(|| Box::new(std::io::empty()));
+        let (rx, _handle) = stream_reader(reader, pause.clone());
+        let mut files = FileList::default();
+        let input = Input {
+            rx,
+            pause,
+            source_names: Vec::new(),
+            buffered: Chunk::default(),
+            files: &mut files,
+            background: None,
+            finder: self.finder.take(),
+            exec: None,
+            keys: VecDeque::new(),
+        };
+        run_app(
+            &mut self.terminal,
+            self.args.clone(),
+            input,
+            &self.config,
+            &rewriter,
+            &extractor,
+            &alerts,
+        )
+    }
+
+    /// The terminal drawn on, to restore or reuse once the pager is done.
+    pub fn terminal(&mut self) -> &mut Terminal<B> {
+        &mut self.terminal
+    }
+}
@@ -0,0 +1,124 @@
+use std::env;
+
+use ratatui::{buffer::Buffer, layout::Rect, widgets::Widget};
+use serde::Deserialize;
+
+/// Variables deciding the character encoding of the locale, most specific
+/// first.
+const LOCALE_VARIABLES: [&str; 3] = ["LC_ALL", "LC_CTYPE", "LANG"];
+
+/// Characters borders, separators and sparklines are drawn with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Charset {
+    /// ASCII unless the locale is UTF-8.
+    #[default]
+    Auto,
+    Unicode,
+    Ascii,
+}
+
+impl Charset {
+    /// Whether to draw with ASCII only.
+    pub fn is_ascii(self) -> bool {
+        match self {
+            // Consoles on Windows don't set a locale but draw Unicode fine
+            Charset::Auto if cfg!(unix) => {
+                !is_utf8(LOCALE_VARIABLES.map(|variable| env::var(variable).ok()))
+            }
+            Charset::Auto | Charset::Unicode => false,
+            Charset::Ascii => true,
+        }
+    }
+}
+
+/// Whether the first locale set among `locales` uses UTF-8. No locale at all
+/// means the C locale, which is ASCII.
+fn is_utf8(locales: impl IntoIterator<Item = Option<String>>) -> bool {
+    locales
+        .into_iter()
+        .flatten()
+        .find(|locale| !locale.is_empty())
+        .is_some_and(|locale| {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
+
+/// ASCII stand-in for a box drawing or block character.
+fn ascii(symbol: &str) -> Option<&'static str> {
+    let replacement = match symbol {
+        "│" | "║" | "┃" => "|",
+        "─" | "━" => "-",
+        "═" => "=",
+        "┌" | "┐" | "└" | "┘" | "╔" | "╗" | "╚" | "╝" | "╭" | "╮" | "╰" | "╯" | "┏" | "┓" | "┗"
+        | "┛" => "+",
+        "▁" => "_",
+        "▂" => ".",
+        "▃" => ":",
+        "▄" => "-",
+        "▅" => "=",
+        "▆" => "+",
+        "▇" => "*",
+        "█" => "#",
+        _ => return None,
+    };
+    Some(replacement)
+}
+
+/// Replaces the box drawing and block characters already drawn in its area
+/// with ASCII ones, as ratatui has no ASCII borders of its own.
+pub struct Asciify;
+
+impl Widget for Asciify {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = buf.get_mut(x, y);
+                if let Some(replacement) = ascii(&cell.symbol) {
+                    cell.set_symbol(replacement);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::{
+        buffer::Buffer,
+        layout::Rect,
+        widgets::{Block, BorderType, Borders, Widget},
+    };
+
+    use super::{is_utf8, Asciify};
+
+    fn locales(locales: &[Option<&str>]) -> Vec<Option<String>> {
+        locales
+            .iter()
+            .map(|locale| locale.map(str::to_string))
+            .collect()
+    }
+
+    #[test]
+    fn detect_utf8_locale() {
+        assert!(is_utf8(locales(&[None, None, Some("en_US.UTF-8")])));
+        assert!(is_utf8(locales(&[Some(""), Some("fi_FI.utf8"), None])));
+        // LC_ALL overrides LANG
+        assert!(!is_utf8(locales(&[Some("C"), None, Some("en_US.UTF-8")])));
+        assert!(!is_utf8(locales(&[Some("POSIX"), None, None])));
+        assert!(!is_utf8(locales(&[None, None, None])));
+    }
+
+    #[test]
+    fn draw_ascii_borders() {
+        let area = Rect::new(0, 0, 4, 3);
+        let mut buf = Buffer::empty(area);
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Double)
+            .render(area, &mut buf);
+        Asciify.render(area, &mut buf);
+        assert_eq!(buf, Buffer::with_lines(vec!["+==+", "|  |", "+==+"]));
+    }
+}
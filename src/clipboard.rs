@@ -0,0 +1,104 @@
+use std::{
+    io::{self, ErrorKind, Write},
+    process::{ChildStdout, Command, Stdio},
+};
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Copies `text` to the system clipboard by asking the terminal to do it with
+/// OSC 52, which also works over SSH. Terminals that don't support it
+/// silently ignore the request.
+pub fn copy(output: &mut impl Write, text: &str) -> io::Result<()> {
+    write!(output, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    output.flush()
+}
+
+/// Reads the system clipboard with the first of the usual command line tools
+/// installed, returning what it prints.
+pub fn paste() -> io::Result<ChildStdout> {
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some_and(|display| !display.is_empty());
+    for (program, args) in paste_commands(wayland) {
+        let spawned = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        match spawned {
+            Ok(mut child) => return Ok(child.stdout.take().expect("stdout is piped")),
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            Err(err) => return Err(io::Error::new(err.kind(), format!("{program}: {err}"))),
+        }
+    }
+    Err(io::Error::new(
+        ErrorKind::NotFound,
+        "no clipboard tool found, install wl-clipboard, xclip or xsel",
+    ))
+}
+
+/// Commands printing the clipboard, in the order they are tried.
+fn paste_commands(wayland: bool) -> Vec<(&'static str, &'static [&'static str])> {
+    if cfg!(target_os = "macos") {
+        return vec![("pbpaste", &[])];
+    }
+    let mut commands: Vec<(&str, &[&str])> = vec![
+        ("xclip", &["-selection", "clipboard", "-out"]),
+        ("xsel", &["--clipboard", "--output"]),
+    ];
+    // XWayland may be running too, but its clipboard isn't the one in use
+    if wayland {
+        commands.insert(0, ("wl-paste", &["--no-newline"]));
+    }
+    commands
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let bits = group.iter().enumerate().fold(0u32, |bits, (i, byte)| {
+            bits | (*byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= group.len() {
+                encoded.push(BASE64[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+    use super::{base64, copy, paste_commands};
+
+    #[test]
+    fn encode_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn copy_with_osc_52() {
+        let mut output = Vec::new();
+        copy(&mut output, "b8e882d").unwrap();
+        assert_eq!(output, b"\x1b]52;c;YjhlODgyZA==\x07");
+    }
+
+    #[test]
+    #[cfg(not(target_os = "macos"))]
+    fn prefer_wayland_clipboard() {
+        let programs = |wayland| -> Vec<&str> {
+            paste_commands(wayland)
+                .into_iter()
+                .map(|(program, _)| program)
+                .collect()
+        };
+        assert_eq!(programs(true), ["wl-paste", "xclip", "xsel"]);
+        assert_eq!(programs(false), ["xclip", "xsel"]);
+    }
+}
@@ -0,0 +1,98 @@
+/// A cut-like selection of columns to display from each line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnSelection {
+    /// Zero-based column indices in display order.
+    columns: Vec<usize>,
+    /// Column separator, or `None` to split on runs of whitespace.
+    delimiter: Option<char>,
+}
+
+impl ColumnSelection {
+    /// Parses `[-d DELIM] LIST` where `LIST` is comma separated one-based
+    /// column numbers or ranges, like `1,3-5`.
+    pub fn parse(args: &str) -> Result<Self, String> {
+        let mut args = args.split_whitespace();
+        let mut delimiter = None;
+        let mut list = args.next();
+        if list == Some("-d") {
+            let mut chars = args.next().unwrap_or_default().chars();
+            delimiter = match (chars.next(), chars.next()) {
+                (Some(delimiter), None) => Some(delimiter),
+                _ => return Err("Delimiter must be a single character".to_string()),
+            };
+            list = args.next();
+        }
+        let list = list.ok_or_else(|| "Missing column list".to_string())?;
+        if let Some(extra) = args.next() {
+            return Err(format!("Unexpected argument: {extra}"));
+        }
+
+        let mut columns = Vec::new();
+        for item in list.split(',') {
+            let (start, end) = item.split_once('-').unwrap_or((item, item));
+            let start = parse_column(start)?;
+            let end = parse_column(end)?;
+            if end < start {
+                return Err(format!("Invalid column range: {item}"));
+            }
+            columns.extend(start..=end);
+        }
+        Ok(ColumnSelection { columns, delimiter })
+    }
+
+    /// The selected columns of `line`. Columns missing from the line are
+    /// skipped.
+    pub fn apply(&self, line: &str) -> String {
+        let fields: Vec<&str> = match self.delimiter {
+            Some(delimiter) => line.split(delimiter).collect(),
+            None => line.split_whitespace().collect(),
+        };
+        let separator = self.delimiter.map_or(" ".to_string(), String::from);
+        self.columns
+            .iter()
+            .filter_map(|column| fields.get(*column).copied())
+            .collect::<Vec<_>>()
+            .join(&separator)
+    }
+}
+
+fn parse_column(column: &str) -> Result<usize, String> {
+    match column.trim().parse::<usize>() {
+        Ok(column) if column > 0 => Ok(column - 1),
+        _ => Err(format!("Invalid column: {column}")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ColumnSelection;
+
+    #[test]
+    fn select_whitespace_columns() {
+        let selection = ColumnSelection::parse("1,3").unwrap();
+        assert_eq!(
+            selection.apply("2023-04-12  INFO   started  worker"),
+            "2023-04-12 started"
+        );
+    }
+
+    #[test]
+    fn select_delimited_column_range() {
+        let selection = ColumnSelection::parse("-d , 2-3,1").unwrap();
+        assert_eq!(selection.apply("a,b,,d"), "b,,a");
+    }
+
+    #[test]
+    fn skip_missing_columns() {
+        let selection = ColumnSelection::parse("2,5").unwrap();
+        assert_eq!(selection.apply("a b c"), "b");
+    }
+
+    #[test]
+    fn reject_invalid_lists() {
+        assert!(ColumnSelection::parse("").is_err());
+        assert!(ColumnSelection::parse("0").is_err());
+        assert!(ColumnSelection::parse("3-1").is_err());
+        assert!(ColumnSelection::parse("-d ab 1").is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use std::path::PathBuf;
+
+use crate::{columns::ColumnSelection, transform::Transform};
+
+/// Commands entered at the `:` prompt.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Show only the selected columns, or toggle the previous selection if
+    /// none is given.
+    Columns(Option<ColumnSelection>),
+    /// Save what has been read so far to be reopened with `--resume`.
+    Save(PathBuf),
+    /// Show the shown lines transformed in a new tab.
+    Transform(Transform),
+    /// Count the values of a pattern in the shown lines and show the most
+    /// frequent ones.
+    TopK { pattern: String, limit: usize },
+    /// Show the version and what this build supports.
+    About,
+    /// Show the size of the buffer and its indexes, and whether the input
+    /// has ended.
+    Stats,
+    /// Page the next of the files given.
+    NextFile,
+    /// Page the previous of the files given.
+    PreviousFile,
+}
+
+/// Number of values `:topk` shows unless told otherwise.
+const DEFAULT_TOP_K: usize = 10;
+
+impl Command {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        let (name, args) = input.split_once(' ').unwrap_or((input, ""));
+        match name {
+            "columns" | "cols" => {
+                if args.trim().is_empty() {
+                    Ok(Command::Columns(None))
+                } else {
+                    Ok(Command::Columns(Some(ColumnSelection::parse(args)?)))
+                }
+            }
+            "save" | "w" => match args.trim() {
+                "" => Err("Usage: save FILE".to_string()),
+                path => Ok(Command::Save(PathBuf::from(path))),
+            },
+            "sort" => match args.trim() {
+                "" => Ok(Command::Transform(Transform::Sort)),
+                other => Err(format!("Unexpected argument: {other}")),
+            },
+            "uniq" => Ok(Command::Transform(Transform::parse_uniq(args)?)),
+            "topk" => {
+                let args = args.trim();
+                let (limit, pattern) = match args.strip_prefix("-n ") {
+                    Some(rest) => {
+                        let rest = rest.trim_start();
+                        let (limit, pattern) = rest.split_once(' ').unwrap_or((rest, ""));
+                        let limit = limit
+                            .parse()
+                            .map_err(|_| format!("Invalid number of values: {limit}"))?;
+                        (limit, pattern.trim())
+                    }
+                    None => (DEFAULT_TOP_K, args),
+                };
+                // Allow quoting the pattern as in a shell
+                let pattern = pattern
+                    .strip_prefix('\'')
+                    .and_then(|pattern| pattern.strip_suffix('\''))
+                    .unwrap_or(pattern);
+                match pattern {
+                    "" => Err("Usage: topk [-n N] PATTERN".to_string()),
+                    pattern => Ok(Command::TopK {
+                        pattern: pattern.to_string(),
+                        limit,
+                    }),
+                }
+            }
+            "about" | "version" => Ok(Command::About),
+            "stats" => Ok(Command::Stats),
+            "n" | "next" => Ok(Command::NextFile),
+            "p" | "prev" => Ok(Command::PreviousFile),
+            "" => Err("No command given".to_string()),
+            _ => Err(format!("Unknown command: {name}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::Command;
+    use crate::{columns::ColumnSelection, transform::Transform};
+
+    #[test]
+    fn parse_columns() {
+        assert_eq!(Command::parse("columns"), Ok(Command::Columns(None)));
+        assert_eq!(
+            Command::parse(" cols 1,3 "),
+            Ok(Command::Columns(Some(
+                ColumnSelection::parse("1,3").unwrap()
+            )))
+        );
+    }
+
+    #[test]
+    fn parse_about() {
+        assert_eq!(Command::parse("about"), Ok(Command::About));
+        assert_eq!(Command::parse("version"), Ok(Command::About));
+        assert_eq!(Command::parse("stats"), Ok(Command::Stats));
+        assert_eq!(Command::parse("n"), Ok(Command::NextFile));
+        assert_eq!(Command::parse("p"), Ok(Command::PreviousFile));
+    }
+
+    #[test]
+    fn parse_save() {
+        assert_eq!(
+            Command::parse("w review.cagsnap"),
+            Ok(Command::Save(PathBuf::from("review.cagsnap")))
+        );
+        assert!(Command::parse("save ").is_err());
+    }
+
+    #[test]
+    fn parse_transforms() {
+        assert_eq!(
+            Command::parse("sort"),
+            Ok(Command::Transform(Transform::Sort))
+        );
+        assert_eq!(
+            Command::parse("uniq -c"),
+            Ok(Command::Transform(Transform::Uniq { count: true }))
+        );
+        assert!(Command::parse("sort -r").is_err());
+    }
+
+    #[test]
+    fn parse_topk() {
+        assert_eq!(
+            Command::parse(r"topk 'user=(\w+)'"),
+            Ok(Command::TopK {
+                pattern: r"user=(\w+)".to_string(),
+                limit: 10
+            })
+        );
+        assert_eq!(
+            Command::parse("topk -n 3 status=\\d+"),
+            Ok(Command::TopK {
+                pattern: r"status=\d+".to_string(),
+                limit: 3
+            })
+        );
+        assert!(Command::parse("topk").is_err());
+        assert!(Command::parse("topk -n x y").is_err());
+    }
+
+    #[test]
+    fn reject_unknown_command() {
+        assert!(Command::parse("frobnicate").is_err());
+    }
+}
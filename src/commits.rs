@@ -0,0 +1,123 @@
+use regex::Regex;
+
+use crate::storage::Storage;
+
+/// A commit header found in the input.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Commit {
+    /// Buffer line number of the `commit` line.
+    pub line: usize,
+    pub hash: String,
+    /// First line of the message, empty until it has been read.
+    pub subject: String,
+}
+
+/// Every commit of `git log` output read so far, in order, found as the
+/// input streams in for a table of contents.
+pub struct CommitList {
+    header: Regex,
+    commits: Vec<Commit>,
+    /// Number of buffer lines scanned so far.
+    scanned: usize,
+    /// Whether the last commit is still waiting for its subject.
+    pending: bool,
+}
+
+impl CommitList {
+    pub fn new() -> Self {
+        CommitList {
+            header: Regex::new(r"^commit ([0-9a-fA-F]{40})").unwrap(),
+            commits: Vec::new(),
+            scanned: 0,
+            pending: false,
+        }
+    }
+
+    pub fn commits(&self) -> &[Commit] {
+        &self.commits
+    }
+
+    /// Scans the lines read since the last call.
+    pub fn extend(&mut self, lines: &dyn Storage) {
+        for line_num in self.scanned..lines.len() {
+            let line = lines.line(line_num);
+            if let Some(captures) = self.header.captures(&line) {
+                self.commits.push(Commit {
+                    line: line_num,
+                    hash: captures[1].to_string(),
+                    subject: String::new(),
+                });
+                self.pending = true;
+            } else if self.pending && line.starts_with("    ") && !line.trim().is_empty() {
+                self.commits.last_mut().expect("pending commit").subject = line.trim().to_string();
+                self.pending = false;
+            }
+        }
+        self.scanned = lines.len();
+    }
+
+    /// Forgets the commits in the first `lines` lines of the buffer,
+    /// numbering the rest from zero again. Returns how many were forgotten.
+    pub fn discard(&mut self, lines: usize) -> usize {
+        let discarded = self.commits.partition_point(|commit| commit.line < lines);
+        self.commits.drain(..discarded);
+        for commit in &mut self.commits {
+            commit.line -= lines;
+        }
+        self.pending &= !self.commits.is_empty();
+        self.scanned = self.scanned.saturating_sub(lines);
+        discarded
+    }
+
+    /// Index of the commit `line` belongs to.
+    pub fn containing(&self, line: usize) -> Option<usize> {
+        self.commits
+            .partition_point(|commit| commit.line <= line)
+            .checked_sub(1)
+    }
+
+    /// How far through the commits `line` is, like `commit 37/210, 18%`,
+    /// which unlike a percentage of the lines doesn't jump over huge commits.
+    pub fn progress(&self, line: usize) -> Option<String> {
+        let current = self.containing(line)? + 1;
+        let total = self.commits.len();
+        let percent = (current * 100 + total / 2) / total;
+        Some(format!("commit {current}/{total}, {percent}%"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CommitList;
+
+    const GIT_LOG: &str = include_str!("../tests/data/git_patch");
+
+    #[test]
+    fn list_commits_as_they_stream_in() {
+        let lines: Vec<String> = GIT_LOG.lines().map(|line| line.to_string()).collect();
+        let mut list = CommitList::new();
+        // The second commit arrives without its message
+        list.extend(&lines[..181].to_vec());
+        assert_eq!(list.commits().len(), 2);
+        assert_eq!(list.commits()[0].subject, "Read stdin");
+        assert_eq!(list.commits()[1].subject, "");
+        list.extend(&lines);
+        let commits: Vec<(usize, &str, &str)> = list
+            .commits()
+            .iter()
+            .map(|commit| (commit.line, &commit.hash[..7], commit.subject.as_str()))
+            .collect();
+        assert_eq!(
+            commits[..2],
+            [(0, "b8e882d", "Read stdin"), (178, "96f57fb", "WIP")]
+        );
+        assert_eq!(commits[2].0, 306);
+        assert_eq!(list.containing(200), Some(1));
+        assert_eq!(list.containing(0), Some(0));
+        assert_eq!(list.progress(200).unwrap(), "commit 2/3, 67%");
+        assert_eq!(list.discard(100), 1);
+        assert_eq!(list.commits()[0].line, 78);
+        assert_eq!(list.containing(0), None);
+        assert_eq!(list.progress(0), None);
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use tracing::trace;
+
+use crate::{
+    charset::Charset,
+    context_finder::InputType,
+    error::Error,
+    filter::FilterBinding,
+    json::JsonConfig,
+    search::{CaseMode, Normalization},
+    theme::ThemeConfig,
+    yank::YankConfig,
+};
+
+const CONFIG_FILE: &str = "cag/config.toml";
+
+/// Settings read from `$XDG_CONFIG_HOME/cag/config.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Rules rewriting lines when they are displayed
+    pub rewrite: Vec<RewriteRule>,
+    /// Show the per-line annotation gutter on startup
+    pub gutter: bool,
+    /// Dim the pager while the terminal window is not focused
+    pub dim_unfocused: bool,
+    /// Patterns of what `o` lists for opening or copying, in addition to
+    /// URLs and paths
+    pub extract: Vec<String>,
+    /// Patterns of lines that ring the bell and are bookmarked when read
+    /// while following
+    pub alerts: Vec<String>,
+    /// Whether searching ignores case: `smart`, `insensitive` or `sensitive`
+    pub search_case: CaseMode,
+    /// Ignore the case of all letters when searching, not just ASCII ones
+    pub search_unicode: bool,
+    /// Unicode normalization of the term and the lines when searching:
+    /// `none`, `nfc` or `nfkc`
+    pub search_normalize: Normalization,
+    /// Ignore accents and other diacritics when searching
+    pub search_fold_diacritics: bool,
+    /// Milliseconds to wait after the last key typed into the search prompt
+    /// before searching
+    pub search_debounce: Option<u64>,
+    /// Keep the search terms recalled with Up in `~/.local/share/cag/history`
+    /// across sessions
+    pub save_search_history: bool,
+    /// External commands run with `!` and a key
+    pub filter: Vec<FilterBinding>,
+    /// Ask before running git commands changing the repository and before
+    /// overwriting files
+    pub confirm_actions: bool,
+    /// Keys that only act when pressed twice in a row, like `q`
+    pub double_press: Vec<char>,
+    /// Preset and styles to draw with
+    pub theme: ThemeConfig,
+    /// Characters to draw borders and separators with: `auto`, `unicode` or
+    /// `ascii`
+    pub charset: Charset,
+    /// Fields shown and grouped by for JSON Lines logs
+    pub json: JsonConfig,
+    /// Pattern of the line to start at for each input type, like `^@@ ` to
+    /// skip to the first hunk of a diff
+    pub start_at: HashMap<InputType, String>,
+    /// What `y` and `Y` copy of a commit
+    pub yank: YankConfig,
+    /// Keys acting like another one, like `x = 'q'` to quit with `x`
+    pub keys: HashMap<char, char>,
+}
+
+/// Replaces every match of `pattern` with `replacement`, which may refer to
+/// capture groups as `$1` or `${name}`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RewriteRule {
+    pub pattern: String,
+    pub replacement: String,
+}
+
+impl Config {
+    /// Loads the config from `path`, or from the default location if no path
+    /// is given. A missing default config is not an error.
+    pub fn load(path: Option<&Path>) -> Result<Self, Error> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+        trace!("Loading config from {path:?}");
+        match fs::read_to_string(&path) {
+            Ok(contents) => Self::parse(&contents),
+            Err(err) if err.kind() == ErrorKind::NotFound && !required => Ok(Config::default()),
+            Err(err) => Err(Error::ConfigRead(path, err)),
+        }
+    }
+
+    fn parse(contents: &str) -> Result<Self, Error> {
+        Ok(toml::from_str(contents)?)
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join(CONFIG_FILE))
+}
+
+#[cfg(test)]
+mod test {
+    use super::Config;
+    use crate::{filter::FilterInput, search::CaseMode};
+
+    #[test]
+    fn parse_empty_config() {
+        let config = Config::parse("").unwrap();
+        assert!(config.rewrite.is_empty());
+        assert!(!config.gutter);
+        assert_eq!(config.search_case, CaseMode::Smart);
+        assert!(!config.confirm_actions);
+        assert!(config.double_press.is_empty());
+    }
+
+    #[test]
+    fn parse_rewrite_rules() {
+        let config = Config::parse(
+            r#"
+            [[rewrite]]
+            pattern = '/home/(\w+)'
+            replacement = '~$1'
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.rewrite.len(), 1);
+        assert_eq!(config.rewrite[0].replacement, "~$1");
+    }
+
+    #[test]
+    fn parse_filters() {
+        let config = Config::parse(
+            r#"
+            [[filter]]
+            key = 'j'
+            command = 'jq .'
+            input = 'context'
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.filter[0].key, 'j');
+        assert_eq!(config.filter[0].input, FilterInput::Context);
+        assert!(Config::parse("[[filter]]\nkey = 'jq'\ncommand = 'jq .'").is_err());
+    }
+
+    #[test]
+    fn parse_key_bindings() {
+        let config = Config::parse("[keys]\nx = 'q'\n'.' = 'n'").unwrap();
+        assert_eq!(config.keys.get(&'x'), Some(&'q'));
+        assert_eq!(config.keys.get(&'.'), Some(&'n'));
+        assert!(Config::parse("[keys]\nxy = 'q'").is_err());
+    }
+
+    #[test]
+    fn reject_unknown_keys() {
+        assert!(Config::parse("colour = 'red'").is_err());
+    }
+}
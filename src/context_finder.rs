@@ -1,103 +1,1183 @@
-use std::ops::Range;
+use std::{borrow::Cow, collections::HashMap, ops::Range, path::Path};
 
+use clap::ValueEnum;
 use regex::Regex;
+use serde::Deserialize;
 use tracing::trace;
 
-use crate::error::Error;
+use crate::{json::JsonLog, storage::Storage};
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum InputType {
+    /// `git log --patch` and `git diff` output
+    #[default]
     Git,
+    /// `journalctl -o short` output
+    Journald,
+    /// RFC 3164 and RFC 5424 syslog files, like `/var/log/syslog`
+    Syslog,
+    /// JSON Lines logs, like `kubectl logs --output json` or Bunyan output
+    Json,
+    /// `strace -f` output
+    Strace,
+    /// `pytest` and `python -m unittest` output
+    Pytest,
+    /// GCC and Clang diagnostics
+    Gcc,
+    /// Stack traces of uncaught JVM exceptions
+    #[serde(rename = "java-stacktrace")]
+    JavaStacktrace,
+    /// mbox files, like a mailing list archive
+    #[value(alias = "mbox")]
+    #[serde(alias = "mbox")]
+    Mailbox,
+    /// `cargo build` and `cargo test` output
+    Cargo,
+    /// Markdown documents
+    Markdown,
+    /// Indentation structured text like YAML or pretty-printed JSON
+    Outline,
 }
 
-pub struct ContextFinder {
+impl InputType {
+    /// Input type of a file with the extension of `path`, for those that
+    /// tell.
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        Some(match extension.as_str() {
+            "patch" | "diff" => InputType::Git,
+            "jsonl" | "ndjson" => InputType::Json,
+            "mbox" => InputType::Mailbox,
+            "md" | "markdown" => InputType::Markdown,
+            "yaml" | "yml" | "json" => InputType::Outline,
+            _ => return None,
+        })
+    }
+}
+
+/// Finds the context of the lines of the buffer. The lines are passed to
+/// `extend` in order as they are read, so an implementation can parse them as
+/// they stream in and keep any state it needs. Only `find_range` has to be
+/// implemented: by default the first line of the range found is the context.
+pub trait ContextFinder {
+    /// Lines from the start of the outermost context at or above `position`,
+    /// like the current commit, up to where the next one starts, or to the
+    /// end of the buffer if it hasn't yet.
+    fn find_range(&self, lines: &dyn Storage, position: usize) -> Option<Range<usize>>;
+
+    /// Indexes the lines read since the last call.
+    fn extend(&mut self, _lines: &dyn Storage) {}
+
+    /// Forgets the first `lines` lines of the buffer, numbering the rest from
+    /// zero again.
+    fn discard(&mut self, _lines: usize) {}
+
+    /// Line starting the outermost context shown for `position`, and the
+    /// line ending it if known.
+    fn boundaries(&self, lines: &dyn Storage, position: usize) -> Option<(usize, Option<usize>)> {
+        let range = self.find_range(lines, position.checked_sub(1)?)?;
+        Some((range.start, None))
+    }
+
+    /// Number of contexts indexed, and a rough estimate of the bytes their
+    /// index takes.
+    fn index_size(&self) -> (usize, usize) {
+        (0, 0)
+    }
+
+    /// The lines of each context level enclosing `position`, outermost first.
+    fn get_context<'a>(&self, lines: &'a dyn Storage, position: usize) -> Vec<Cow<'a, [String]>> {
+        self.boundaries(lines, position)
+            .filter(|(start, _)| *start < lines.len())
+            .map(|(start, _)| lines.lines(start..start + 1))
+            .into_iter()
+            .collect()
+    }
+
+    /// Short identifier of the outermost context, like the hash of the current
+    /// commit, if there is one.
+    fn identifier<'a>(&self, _context: &'a [Cow<'a, [String]>]) -> Option<&'a str> {
+        None
+    }
+
+    /// Path and line number in the new version of the file shown at
+    /// `position`, for patches.
+    fn diff_location(&self, _lines: &dyn Storage, _position: usize) -> Option<(String, usize)> {
+        None
+    }
+}
+
+/// The context finder of a built-in input type, mostly matching lines with
+/// regular expressions. JSON Lines logs are grouped by a field of `json`.
+pub fn builtin(input_type: InputType, json: Option<JsonLog>) -> Box<dyn ContextFinder> {
+    match input_type {
+        InputType::Git => Box::new(GitFinder::new()),
+        InputType::Journald => Box::new(PatternFinder::journald()),
+        InputType::Syslog => Box::new(SyslogFinder::new()),
+        InputType::Json => Box::new(JsonFinder::new(json)),
+        InputType::Strace => Box::new(StraceFinder::new()),
+        InputType::Pytest => Box::new(PatternFinder::pytest()),
+        InputType::Gcc => Box::new(DiagnosticFinder::gcc()),
+        InputType::JavaStacktrace => Box::new(DiagnosticFinder::java_stacktrace()),
+        InputType::Mailbox => Box::new(MailboxFinder::new()),
+        InputType::Cargo => Box::new(PatternFinder::cargo()),
+        InputType::Markdown => Box::new(MarkdownFinder::new()),
+        InputType::Outline => Box::new(OutlineFinder::new()),
+    }
+}
+
+/// One level of context, like the commit, file or hunk of a patch.
+#[derive(Clone, Default)]
+struct Level {
+    /// Lines starting a context with the first line after them ending it,
+    /// once read, in order.
+    ranges: Vec<(usize, Option<usize>)>,
+    /// Number of entries at the end of `ranges` still waiting for their end.
+    open: usize,
+}
+
+impl Level {
+    /// Indexes line `line_num`, which `ends` the open ranges and `starts` a
+    /// new one.
+    fn mark(&mut self, line_num: usize, ends: bool, starts: bool) {
+        if self.open > 0 && ends {
+            let open = self.ranges.len() - self.open;
+            for (_, end) in &mut self.ranges[open..] {
+                *end = Some(line_num);
+            }
+            self.open = 0;
+        }
+        if starts {
+            self.ranges.push((line_num, None));
+            self.open += 1;
+        }
+    }
+
+    /// Forgets the first `lines` lines, numbering the rest from zero again.
+    fn discard(&mut self, lines: usize) {
+        let discarded = self.ranges.partition_point(|(start, _)| *start < lines);
+        self.ranges.drain(..discarded);
+        self.open = std::cmp::min(self.open, self.ranges.len());
+        for (start, end) in &mut self.ranges {
+            *start -= lines;
+            *end = end.map(|end| end - lines);
+        }
+    }
+
+    /// Finds the context above `current_position`, starting no earlier than
+    /// `bound`. It ends before the line ending it, or right above
+    /// `current_position` if there is none in between.
+    fn find_range(&self, bound: usize, current_position: usize) -> Option<Range<usize>> {
+        let before = self
+            .ranges
+            .partition_point(|(start, _)| *start < current_position);
+        let (start, end) = *self.ranges[..before].last()?;
+        if start < bound {
+            return None;
+        }
+        let end = end
+            .filter(|end| *end < current_position)
+            .unwrap_or(current_position);
+        Some(start..end - 1)
+    }
+
+    /// Lines from the start of the context at or above `position` up to
+    /// where the next one starts.
+    fn enclosing(&self, position: usize) -> Option<Range<usize>> {
+        let next = self.ranges.partition_point(|(start, _)| *start <= position);
+        let (start, _) = *self.ranges[..next].last()?;
+        let end = self.ranges.get(next).map_or(usize::MAX, |(next, _)| *next);
+        Some(start..end)
+    }
+
+    /// Line starting the context shown for `position`, and the line ending
+    /// it if read yet.
+    fn boundaries(&self, position: usize) -> Option<(usize, Option<usize>)> {
+        let before = self.ranges.partition_point(|(start, _)| *start < position);
+        self.ranges[..before].last().copied()
+    }
+}
+
+/// Ranges of the context `levels` found above `current_position`, outermost
+/// first. Each level is only searched for below the start of the level
+/// enclosing it, so a hunk from a previous commit is never shown.
+fn find_ranges<'a>(
+    levels: impl IntoIterator<Item = &'a Level>,
+    current_position: usize,
+) -> Vec<Range<usize>> {
+    let mut bound = 0;
+    let mut ranges = Vec::new();
+    for level in levels {
+        if let Some(range) = level.find_range(bound, current_position) {
+            bound = range.start + 1;
+            ranges.push(range);
+        }
+    }
+    ranges
+}
+
+/// Number of contexts indexed in `levels`, and the bytes their index takes.
+fn levels_size<'a>(levels: impl IntoIterator<Item = &'a Level>) -> (usize, usize) {
+    let ranges: usize = levels.into_iter().map(|level| level.ranges.len()).sum();
+    (
+        ranges,
+        ranges * std::mem::size_of::<(usize, Option<usize>)>(),
+    )
+}
+
+/// A context level starting at the lines matching `start`, and ending at the
+/// first line after them matching `end`.
+#[derive(Clone)]
+struct Pattern {
     start: Regex,
     end: Regex,
+    level: Level,
+}
+
+impl Pattern {
+    fn new(start: &str, end: &str) -> Self {
+        Pattern {
+            start: Regex::new(start).unwrap(),
+            end: Regex::new(end).unwrap(),
+            level: Level::default(),
+        }
+    }
+
+    /// Indexes line `line_num` reading `line`, the one after the last indexed.
+    fn extend(&mut self, line_num: usize, line: &str) {
+        let (ends, starts) = (self.end.is_match(line), self.start.is_match(line));
+        self.level.mark(line_num, ends, starts);
+    }
+}
+
+/// Nested context levels found by their patterns alone, like the boots of
+/// journald and the units started in them.
+#[derive(Clone)]
+struct PatternFinder {
+    /// Outermost first.
+    patterns: Vec<Pattern>,
+    /// Number of lines indexed so far.
+    scanned: usize,
 }
 
-impl ContextFinder {
-    pub fn new(input_type: InputType) -> Result<Self, Error> {
-        match input_type {
-            InputType::Git => {
-                trace!("Creating GIT context finder");
-                let start = Regex::new(r"^commit [0-9a-fA-F]{40}").unwrap();
-                let end = Regex::new(r"^(commit [0-9a-fA-F]{40}|diff --git)").unwrap();
-                Ok(ContextFinder { start, end })
+impl PatternFinder {
+    fn new(patterns: Vec<Pattern>) -> Self {
+        PatternFinder {
+            patterns,
+            scanned: 0,
+        }
+    }
+
+    fn journald() -> Self {
+        trace!("Creating journald context finder");
+        // The boot header and the line where the current unit started
+        Self::new(vec![
+            Pattern::new(r"^-- (Boot [0-9a-f]+|Reboot) --", r"^"),
+            Pattern::new(r"^\S+ +\d+ [0-9:]+ \S+ systemd\[1\]: Starting ", r"^"),
+        ])
+    }
+
+    fn pytest() -> Self {
+        trace!("Creating pytest context finder");
+        // The section, like the failures or the summary, the failing test and
+        // the output captured while it ran
+        Self::new(vec![
+            Pattern::new(r"^={3,} .+ ={3,}$", r"^"),
+            Pattern::new(r"^(_{3,} .+ _{3,}|(FAIL|ERROR): .+|FAILED \S+.*)$", r"^"),
+            Pattern::new(r"^-{3,} .+ -{3,}$", r"^"),
+        ])
+    }
+
+    fn cargo() -> Self {
+        trace!("Creating cargo context finder");
+        // Compiler diagnostics, test run headers and failing test output
+        Self::new(vec![Pattern::new(
+            r"^(error(\[E\d+\])?: |warning: |running \d+ tests?$|---- .+ ----$)",
+            r"^(\s*$|error(\[E\d+\])?: |warning: |running \d+ tests?$|---- )",
+        )])
+    }
+
+    fn levels(&self) -> impl Iterator<Item = &Level> {
+        self.patterns.iter().map(|pattern| &pattern.level)
+    }
+
+    fn find_ranges(&self, current_position: usize) -> Vec<Range<usize>> {
+        find_ranges(self.levels(), current_position)
+    }
+}
+
+impl ContextFinder for PatternFinder {
+    /// Indexes the lines read since the last call, so finding the context
+    /// of a line is a binary search instead of a scan back through the lines
+    /// above it on every frame.
+    fn extend(&mut self, lines: &dyn Storage) {
+        for line_num in self.scanned..lines.len() {
+            let line = lines.line(line_num);
+            for pattern in &mut self.patterns {
+                pattern.extend(line_num, &line);
             }
         }
+        self.scanned = lines.len();
+    }
+
+    fn discard(&mut self, lines: usize) {
+        for pattern in &mut self.patterns {
+            pattern.level.discard(lines);
+        }
+        self.scanned = self.scanned.saturating_sub(lines);
     }
 
-    pub fn get_context<'a>(
+    fn find_range(&self, _lines: &dyn Storage, position: usize) -> Option<Range<usize>> {
+        self.patterns.first()?.level.enclosing(position)
+    }
+
+    fn boundaries(&self, _lines: &dyn Storage, position: usize) -> Option<(usize, Option<usize>)> {
+        self.patterns.first()?.level.boundaries(position)
+    }
+
+    fn index_size(&self) -> (usize, usize) {
+        levels_size(self.levels())
+    }
+
+    fn get_context<'a>(
         &self,
-        all_lines: &'a [String],
+        all_lines: &'a dyn Storage,
         position: usize,
-    ) -> Option<&'a [String]> {
+    ) -> Vec<Cow<'a, [String]>> {
         trace!("Finding context");
-        let context_lines = self.find_range(all_lines, position);
-        if let Some(lines) = context_lines {
-            all_lines.get(lines.start..(lines.end + 1))
+        self.find_ranges(position)
+            .into_iter()
+            .filter(|lines| lines.end < all_lines.len())
+            .map(|lines| all_lines.lines(lines.start..(lines.end + 1)))
+            .collect()
+    }
+}
+
+/// The commit, file and hunk of `git log --patch` and `git diff` output.
+#[derive(Clone)]
+struct GitFinder {
+    patterns: PatternFinder,
+}
+
+impl GitFinder {
+    fn new() -> Self {
+        trace!("Creating GIT context finder");
+        GitFinder {
+            patterns: PatternFinder::new(vec![
+                Pattern::new(
+                    r"^commit [0-9a-fA-F]{40}",
+                    r"^(commit [0-9a-fA-F]{40}|diff --git)",
+                ),
+                // The file and the hunk are one line each
+                Pattern::new(r"^diff --git ", r"^"),
+                Pattern::new(r"^@@ ", r"^"),
+            ]),
+        }
+    }
+
+    fn find_ranges(&self, current_position: usize) -> Vec<Range<usize>> {
+        self.patterns.find_ranges(current_position)
+    }
+}
+
+impl ContextFinder for GitFinder {
+    fn extend(&mut self, lines: &dyn Storage) {
+        self.patterns.extend(lines);
+    }
+
+    fn discard(&mut self, lines: usize) {
+        self.patterns.discard(lines);
+    }
+
+    fn find_range(&self, lines: &dyn Storage, position: usize) -> Option<Range<usize>> {
+        self.patterns.find_range(lines, position)
+    }
+
+    /// Like the header of the current commit, and the first file of the
+    /// commit ending it.
+    fn boundaries(&self, lines: &dyn Storage, position: usize) -> Option<(usize, Option<usize>)> {
+        self.patterns.boundaries(lines, position)
+    }
+
+    fn index_size(&self) -> (usize, usize) {
+        self.patterns.index_size()
+    }
+
+    fn get_context<'a>(&self, lines: &'a dyn Storage, position: usize) -> Vec<Cow<'a, [String]>> {
+        self.patterns.get_context(lines, position)
+    }
+
+    fn identifier<'a>(&self, context: &'a [Cow<'a, [String]>]) -> Option<&'a str> {
+        context
+            .first()?
+            .first()?
+            .strip_prefix("commit ")?
+            .split_whitespace()
+            .next()
+    }
+
+    /// Path and line number in the new version of the file shown at
+    /// `position` of a patch. Removed lines map to the line that replaced
+    /// them.
+    fn diff_location(&self, lines: &dyn Storage, position: usize) -> Option<(String, usize)> {
+        // Include `position` itself in case it is the header
+        let ranges = self.find_ranges(std::cmp::min(position + 1, lines.len()));
+        let header = ranges
+            .iter()
+            .map(|range| lines.line(range.start))
+            .find(|line| line.starts_with("diff --git "))?;
+        let path = diff_path(&header)?;
+        let Some(hunk) = ranges
+            .iter()
+            .map(|range| range.start)
+            .find(|start| lines.line(*start).starts_with("@@ "))
+        else {
+            return Some((path.to_string(), 1));
+        };
+        // `@@ -3,22 +3,16 @@` starts at line 3 of the new file
+        let start: usize = lines
+            .line(hunk)
+            .split_whitespace()
+            .nth(2)?
+            .trim_start_matches('+')
+            .split(',')
+            .next()?
+            .parse()
+            .ok()?;
+        let offset = if hunk < position && position <= lines.len() {
+            lines
+                .iter(hunk + 1..position)
+                .filter(|line| !line.starts_with('-'))
+                .count()
         } else {
-            None
+            0
+        };
+        Some((path.to_string(), start.saturating_add(offset)))
+    }
+}
+
+/// The first message of the day, and the first of the messages in a row from
+/// the same host and program, found by comparing each message to the one
+/// before.
+#[derive(Clone)]
+struct SyslogFinder {
+    message: Regex,
+    days: Level,
+    sources: Level,
+    /// Day, and host and program, of the last message indexed.
+    last_message: Option<(String, String)>,
+    /// Number of lines indexed so far.
+    scanned: usize,
+}
+
+impl SyslogFinder {
+    fn new() -> Self {
+        trace!("Creating syslog context finder");
+        SyslogFinder {
+            message: Regex::new(r"^(?:<\d+>(?:1 )?)?(?P<day>[A-Z][a-z]{2} [ \d]\d|\d{4}-\d\d-\d\d)[ T][0-9:.+\-Z]+ (?P<host>\S+) (?P<program>[^:\[\s]+)").unwrap(),
+            days: Level::default(),
+            sources: Level::default(),
+            last_message: None,
+            scanned: 0,
+        }
+    }
+
+    /// Starts the day or the host and program contexts at `line` if it is a
+    /// message from a different one than the message before. Lines that
+    /// aren't messages, like continuations, don't change either.
+    fn index_message(&mut self, line_num: usize, line: &str) {
+        let message = self.message.captures(line).map(|captures| {
+            (
+                captures["day"].to_string(),
+                format!("{} {}", &captures["host"], &captures["program"]),
+            )
+        });
+        let last = self.last_message.as_ref();
+        let new_day = message
+            .as_ref()
+            .is_some_and(|(day, _)| last.is_none_or(|(last_day, _)| day != last_day));
+        let new_source = message
+            .as_ref()
+            .is_some_and(|(_, source)| last.is_none_or(|(_, last_source)| source != last_source));
+        self.days.mark(line_num, true, new_day);
+        self.sources.mark(line_num, true, new_source);
+        if message.is_some() {
+            self.last_message = message;
+        }
+    }
+}
+
+impl ContextFinder for SyslogFinder {
+    fn extend(&mut self, lines: &dyn Storage) {
+        for line_num in self.scanned..lines.len() {
+            self.index_message(line_num, &lines.line(line_num));
+        }
+        self.scanned = lines.len();
+    }
+
+    fn discard(&mut self, lines: usize) {
+        self.days.discard(lines);
+        self.sources.discard(lines);
+        self.scanned = self.scanned.saturating_sub(lines);
+    }
+
+    fn find_range(&self, _lines: &dyn Storage, position: usize) -> Option<Range<usize>> {
+        self.days.enclosing(position)
+    }
+
+    fn boundaries(&self, _lines: &dyn Storage, position: usize) -> Option<(usize, Option<usize>)> {
+        self.days.boundaries(position)
+    }
+
+    fn index_size(&self) -> (usize, usize) {
+        levels_size([&self.days, &self.sources])
+    }
+
+    fn get_context<'a>(
+        &self,
+        all_lines: &'a dyn Storage,
+        position: usize,
+    ) -> Vec<Cow<'a, [String]>> {
+        find_ranges([&self.days, &self.sources], position)
+            .into_iter()
+            .filter(|lines| lines.end < all_lines.len())
+            .map(|lines| all_lines.lines(lines.start..(lines.end + 1)))
+            .collect()
+    }
+}
+
+/// The first line of the current group of JSON Lines logs, found by comparing
+/// the group field of each line to the one before.
+#[derive(Clone)]
+struct JsonFinder {
+    json: Option<JsonLog>,
+    groups: Level,
+    /// Value of the group field of the last line indexed.
+    last_group: Option<String>,
+    /// Number of lines indexed so far.
+    scanned: usize,
+}
+
+impl JsonFinder {
+    fn new(json: Option<JsonLog>) -> Self {
+        trace!("Creating JSON context finder");
+        JsonFinder {
+            json,
+            groups: Level::default(),
+            last_group: None,
+            scanned: 0,
         }
     }
 
-    fn find_range(&self, lines: &[String], current_position: usize) -> Option<Range<usize>> {
-        if let Some(context_start_position) = self.start_line_num(lines, current_position) {
-            if let Some(context_end_delta) =
-                self.end_line_num(lines, current_position, context_start_position)
-            {
-                Some(Range {
-                    start: context_start_position,
-                    end: context_start_position + context_end_delta,
-                })
-            } else {
-                Some(Range {
-                    start: context_start_position,
-                    end: current_position - 1,
-                })
+    /// Starts a group at `line` if its group field differs from the one of
+    /// the line before. Lines without the field stay in the group.
+    fn index_group(&mut self, line_num: usize, line: &str) {
+        let group = self.json.as_ref().and_then(|json| json.group(line));
+        let starts = group.is_some() && group != self.last_group;
+        self.groups.mark(line_num, true, starts);
+        if group.is_some() {
+            self.last_group = group;
+        }
+    }
+}
+
+impl ContextFinder for JsonFinder {
+    fn extend(&mut self, lines: &dyn Storage) {
+        for line_num in self.scanned..lines.len() {
+            self.index_group(line_num, &lines.line(line_num));
+        }
+        self.scanned = lines.len();
+    }
+
+    fn discard(&mut self, lines: usize) {
+        self.groups.discard(lines);
+        self.scanned = self.scanned.saturating_sub(lines);
+    }
+
+    fn find_range(&self, _lines: &dyn Storage, position: usize) -> Option<Range<usize>> {
+        self.groups.enclosing(position)
+    }
+
+    fn boundaries(&self, _lines: &dyn Storage, position: usize) -> Option<(usize, Option<usize>)> {
+        self.groups.boundaries(position)
+    }
+
+    fn index_size(&self) -> (usize, usize) {
+        levels_size([&self.groups])
+    }
+
+    fn get_context<'a>(
+        &self,
+        all_lines: &'a dyn Storage,
+        position: usize,
+    ) -> Vec<Cow<'a, [String]>> {
+        let Some(json) = &self.json else {
+            return Vec::new();
+        };
+        find_ranges([&self.groups], position)
+            .into_iter()
+            .filter_map(|range| json.heading(&all_lines.line(range.start)))
+            .map(|heading| Cow::Owned(vec![heading]))
+            .collect()
+    }
+}
+
+/// The process of each line of `strace -f` output, and the program it ran
+/// last.
+#[derive(Clone)]
+struct StraceFinder {
+    /// The pid strace prefixes the lines of the other processes with.
+    prefix: Regex,
+    execve: Regex,
+    /// Lines of the `execve` calls of each traced process, by pid. `None` is
+    /// the first process, whose lines strace doesn't prefix with its pid
+    /// unless writing to a file.
+    execs: HashMap<Option<u32>, Vec<usize>>,
+    /// Number of lines indexed so far.
+    scanned: usize,
+}
+
+impl StraceFinder {
+    fn new() -> Self {
+        trace!("Creating strace context finder");
+        let prefix = r"^(?:\[pid +(\d+)\] |(\d+) +)";
+        StraceFinder {
+            prefix: Regex::new(prefix).unwrap(),
+            execve: Regex::new(&format!(r"{prefix}?(?:[0-9:.]+ +)?execve\(")).unwrap(),
+            execs: HashMap::new(),
+            scanned: 0,
+        }
+    }
+
+    /// Pid strace prefixed `line` with.
+    fn pid(&self, line: &str) -> Option<u32> {
+        let captures = self.prefix.captures(line)?;
+        captures.get(1).or(captures.get(2))?.as_str().parse().ok()
+    }
+}
+
+impl ContextFinder for StraceFinder {
+    fn extend(&mut self, lines: &dyn Storage) {
+        for line_num in self.scanned..lines.len() {
+            let line = lines.line(line_num);
+            if self.execve.is_match(&line) {
+                let pid = self.pid(&line);
+                self.execs.entry(pid).or_default().push(line_num);
             }
-        } else {
-            None
         }
+        self.scanned = lines.len();
     }
 
-    fn start_line_num(&self, lines: &[String], start_position: usize) -> Option<usize> {
-        trace!("Looking for start line");
-        let pos = lines.get(0..start_position).map(|lines| {
-            lines
-                .iter()
-                .enumerate()
-                .rev()
-                .find(|(_line_num, line)| self.start.is_match(line))
+    fn discard(&mut self, lines: usize) {
+        for execs in self.execs.values_mut() {
+            execs.retain(|line| *line >= lines);
+            for line in execs {
+                *line -= lines;
+            }
+        }
+        self.scanned = self.scanned.saturating_sub(lines);
+    }
+
+    /// The lines of the processes interleave, there is no range to find.
+    fn find_range(&self, _lines: &dyn Storage, _position: usize) -> Option<Range<usize>> {
+        None
+    }
+
+    fn index_size(&self) -> (usize, usize) {
+        let execs: usize = self.execs.values().map(Vec::len).sum();
+        (execs, execs * std::mem::size_of::<usize>())
+    }
+
+    /// The process of the line above `position`, and the program it ran
+    /// last.
+    fn get_context<'a>(&self, lines: &'a dyn Storage, position: usize) -> Vec<Cow<'a, [String]>> {
+        let Some(line) = position.checked_sub(1).filter(|line| *line < lines.len()) else {
+            return Vec::new();
+        };
+        let pid = self.pid(&lines.line(line));
+        let process = match pid {
+            Some(pid) => format!("pid {pid}"),
+            None => "first process".to_string(),
+        };
+        let exec = self.execs.get(&pid).and_then(|execs| {
+            let before = execs.partition_point(|exec| *exec < position);
+            let exec = *execs[..before].last()?;
+            Some(lines.lines(exec..exec + 1))
         });
-        pos.unwrap_or(None).map(|(num, _line)| num)
+        [Some(Cow::Owned(vec![process])), exec]
+            .into_iter()
+            .flatten()
+            .collect()
     }
+}
 
-    fn end_line_num(
+/// Diagnostics kept through the lines of their body, like the notes of a
+/// compiler error or the frames of a stack trace. `end` matches these lines
+/// rather than what ends the diagnostic, which also ends where the next one
+/// starts.
+#[derive(Clone)]
+struct DiagnosticFinder {
+    /// Outermost first.
+    patterns: Vec<Pattern>,
+    /// Number of lines indexed so far.
+    scanned: usize,
+}
+
+impl DiagnosticFinder {
+    fn gcc() -> Self {
+        trace!("Creating GCC context finder");
+        // An error or warning, kept through the notes, source lines, carets
+        // and template instantiations following it
+        DiagnosticFinder {
+            patterns: vec![Pattern::new(
+                r"^\S.*?:\d+:(\d+:)? (fatal error|error|warning): ",
+                r"^(\s|In file included from |\S.*?:\d+:(\d+:)? +(note: |required |recursively required |in ))",
+            )],
+            scanned: 0,
+        }
+    }
+
+    fn java_stacktrace() -> Self {
+        trace!("Creating Java stack trace context finder");
+        // The uncaught exception and the cause being read, kept through the
+        // stack frames
+        let trace = r"^(\s+(at |\.\.\. \d+ more|\.\.\. \d+ common frames omitted)|\s*(Caused by|Suppressed): )";
+        DiagnosticFinder {
+            patterns: vec![
+                Pattern::new(r#"Exception in thread ""#, trace),
+                Pattern::new(r"^\s*Caused by: ", trace),
+            ],
+            scanned: 0,
+        }
+    }
+
+    fn levels(&self) -> impl Iterator<Item = &Level> {
+        self.patterns.iter().map(|pattern| &pattern.level)
+    }
+}
+
+impl ContextFinder for DiagnosticFinder {
+    fn extend(&mut self, lines: &dyn Storage) {
+        for line_num in self.scanned..lines.len() {
+            let line = lines.line(line_num);
+            for pattern in &mut self.patterns {
+                let (body, starts) = (pattern.end.is_match(&line), pattern.start.is_match(&line));
+                pattern.level.mark(line_num, !body || starts, starts);
+            }
+        }
+        self.scanned = lines.len();
+    }
+
+    fn discard(&mut self, lines: usize) {
+        for pattern in &mut self.patterns {
+            pattern.level.discard(lines);
+        }
+        self.scanned = self.scanned.saturating_sub(lines);
+    }
+
+    fn find_range(&self, _lines: &dyn Storage, position: usize) -> Option<Range<usize>> {
+        self.patterns.first()?.level.enclosing(position)
+    }
+
+    fn boundaries(&self, _lines: &dyn Storage, position: usize) -> Option<(usize, Option<usize>)> {
+        self.patterns.first()?.level.boundaries(position)
+    }
+
+    fn index_size(&self) -> (usize, usize) {
+        levels_size(self.levels())
+    }
+
+    /// Only the diagnostic or exception itself, while its body is on screen.
+    fn get_context<'a>(
         &self,
-        lines: &[String],
-        start_position: usize,
-        start_line_num: usize,
-    ) -> Option<usize> {
-        trace!("Looking for end line");
-        let pos = lines
-            .get((start_line_num + 1)..start_position)
-            .map(|lines| {
-                lines
-                    .iter()
-                    .enumerate()
-                    .find(|(_line_num, line)| self.end.is_match(line))
-            });
-        pos.unwrap_or(None).map(|(num, _line)| num)
+        all_lines: &'a dyn Storage,
+        position: usize,
+    ) -> Vec<Cow<'a, [String]>> {
+        find_ranges(self.levels(), position)
+            .into_iter()
+            .filter(|lines| lines.end + 1 >= position && lines.start < all_lines.len())
+            .map(|lines| all_lines.lines(lines.start..lines.start + 1))
+            .collect()
     }
 }
 
+/// The `From ` line separating the messages of an mbox file, and the subject
+/// and date among the headers following it. Headers quoted in the body don't
+/// count.
+#[derive(Clone)]
+struct MailboxFinder {
+    separator: Regex,
+    header: Regex,
+    messages: Level,
+    headers: Level,
+    /// Whether the last line indexed is in the headers of a message.
+    in_headers: bool,
+    /// Number of lines indexed so far.
+    scanned: usize,
+}
+
+impl MailboxFinder {
+    fn new() -> Self {
+        trace!("Creating mailbox context finder");
+        MailboxFinder {
+            separator: Regex::new(r"^From \S+ ").unwrap(),
+            header: Regex::new(r"^(?i:subject|date):").unwrap(),
+            messages: Level::default(),
+            headers: Level::default(),
+            in_headers: false,
+            scanned: 0,
+        }
+    }
+
+    /// Starts a message at `line` if it is a `From ` line, and remembers the
+    /// subject and date among the headers following it, which end at the
+    /// first blank line.
+    fn index_mail_header(&mut self, line_num: usize, line: &str) {
+        if self.separator.is_match(line) {
+            self.messages.mark(line_num, false, true);
+            self.in_headers = true;
+        } else if line.is_empty() {
+            self.in_headers = false;
+        } else if self.in_headers && self.header.is_match(line) {
+            self.headers.mark(line_num, false, true);
+        }
+    }
+
+    /// The `From ` line of the message at `position`, followed by its subject
+    /// and date headers.
+    fn message<'a>(&self, lines: &'a dyn Storage, position: usize) -> Option<Cow<'a, [String]>> {
+        let (separators, headers) = (&self.messages.ranges, &self.headers.ranges);
+        let current = separators.partition_point(|(start, _)| *start < position);
+        let (start, _) = *separators[..current].last()?;
+        let next = separators
+            .get(current)
+            .map_or(lines.len(), |(next, _)| std::cmp::min(*next, lines.len()));
+        let first = headers.partition_point(|(line, _)| *line < start);
+        let last = headers.partition_point(|(line, _)| *line < next);
+        let message = std::iter::once(start)
+            .chain(headers[first..last].iter().map(|(line, _)| *line))
+            .filter(|line| *line < lines.len())
+            .map(|line| lines.line(line).into_owned())
+            .collect();
+        Some(Cow::Owned(message))
+    }
+}
+
+impl ContextFinder for MailboxFinder {
+    fn extend(&mut self, lines: &dyn Storage) {
+        for line_num in self.scanned..lines.len() {
+            self.index_mail_header(line_num, &lines.line(line_num));
+        }
+        self.scanned = lines.len();
+    }
+
+    fn discard(&mut self, lines: usize) {
+        self.messages.discard(lines);
+        self.headers.discard(lines);
+        self.scanned = self.scanned.saturating_sub(lines);
+    }
+
+    fn find_range(&self, _lines: &dyn Storage, position: usize) -> Option<Range<usize>> {
+        self.messages.enclosing(position)
+    }
+
+    fn boundaries(&self, _lines: &dyn Storage, position: usize) -> Option<(usize, Option<usize>)> {
+        self.messages.boundaries(position)
+    }
+
+    fn index_size(&self) -> (usize, usize) {
+        levels_size([&self.messages, &self.headers])
+    }
+
+    fn get_context<'a>(&self, lines: &'a dyn Storage, position: usize) -> Vec<Cow<'a, [String]>> {
+        self.message(lines, position).into_iter().collect()
+    }
+}
+
+/// The headings of a Markdown document enclosing a line.
+#[derive(Clone)]
+struct MarkdownFinder {
+    heading: Regex,
+    /// Code fences, whose contents are never headings.
+    fence: Regex,
+    /// Line numbers and depths of the headings outside code fences.
+    headings: Vec<(usize, usize)>,
+    /// Whether the last line indexed is inside a code fence.
+    in_fence: bool,
+    /// Number of lines indexed so far.
+    scanned: usize,
+}
+
+impl MarkdownFinder {
+    fn new() -> Self {
+        trace!("Creating markdown context finder");
+        MarkdownFinder {
+            heading: Regex::new(r"^(#{1,6})\s+\S").unwrap(),
+            fence: Regex::new(r"^(```|~~~)").unwrap(),
+            headings: Vec::new(),
+            in_fence: false,
+            scanned: 0,
+        }
+    }
+
+    /// Remembers `line` if it is a heading outside code fences.
+    fn index_heading(&mut self, line_num: usize, line: &str) {
+        if self.fence.is_match(line) {
+            self.in_fence = !self.in_fence;
+        } else if !self.in_fence {
+            if let Some(captures) = self.heading.captures(line) {
+                self.headings.push((line_num, captures[1].len()));
+            }
+        }
+    }
+
+    /// Breadcrumb of the headings enclosing `position`, like
+    /// `# Chapter > ## Section`: the last heading above it, the last one
+    /// less deep above that one, and so on.
+    fn heading_path(&self, lines: &dyn Storage, position: usize) -> Option<String> {
+        let before = self.headings.partition_point(|(line, _)| *line < position);
+        let mut path = Vec::new();
+        let mut depth = usize::MAX;
+        for (line, heading_depth) in self.headings[..before].iter().rev() {
+            if *heading_depth < depth {
+                path.push(lines.line(*line).trim_end().to_string());
+                depth = *heading_depth;
+            }
+        }
+        if path.is_empty() {
+            return None;
+        }
+        path.reverse();
+        Some(path.join(" > "))
+    }
+}
+
+impl ContextFinder for MarkdownFinder {
+    fn extend(&mut self, lines: &dyn Storage) {
+        for line_num in self.scanned..lines.len() {
+            self.index_heading(line_num, &lines.line(line_num));
+        }
+        self.scanned = lines.len();
+    }
+
+    fn discard(&mut self, lines: usize) {
+        let discarded = self.headings.partition_point(|(line, _)| *line < lines);
+        self.headings.drain(..discarded);
+        for (line, _) in &mut self.headings {
+            *line -= lines;
+        }
+        self.scanned = self.scanned.saturating_sub(lines);
+    }
+
+    /// Sections nest by depth rather than following each other, there is no
+    /// range to find.
+    fn find_range(&self, _lines: &dyn Storage, _position: usize) -> Option<Range<usize>> {
+        None
+    }
+
+    fn index_size(&self) -> (usize, usize) {
+        (
+            self.headings.len(),
+            self.headings.len() * std::mem::size_of::<(usize, usize)>(),
+        )
+    }
+
+    fn get_context<'a>(&self, lines: &'a dyn Storage, position: usize) -> Vec<Cow<'a, [String]>> {
+        self.heading_path(lines, position)
+            .map(|path| Cow::Owned(vec![path]))
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Consecutive lines of the same indentation, not counting blank lines.
+#[derive(Clone, Copy)]
+struct Run {
+    start: usize,
+    /// Last line of the run that isn't blank.
+    end: usize,
+    indent: usize,
+    /// Index of the run with the last less indented line above this one.
+    parent: Option<usize>,
+}
+
+/// Indentation structured text like YAML or pretty-printed JSON. The levels
+/// follow the indentation, indexed as runs of lines indented alike, so the
+/// lines enclosing one are found by following the runs' parents.
+#[derive(Clone, Default)]
+struct OutlineFinder {
+    runs: Vec<Run>,
+    /// Indexes of the runs that may still be the parent of one, least
+    /// indented first.
+    open: Vec<usize>,
+    /// Number of lines indexed so far.
+    scanned: usize,
+}
+
+impl OutlineFinder {
+    fn new() -> Self {
+        trace!("Creating outline context finder");
+        OutlineFinder::default()
+    }
+
+    /// Indexes line `line_num` reading `line`, the one after the last indexed.
+    /// Blank lines are skipped as they don't have a meaningful indentation.
+    fn index_line(&mut self, line_num: usize, line: &str) {
+        let content = line.trim_start();
+        if content.is_empty() {
+            return;
+        }
+        let indent = line.len() - content.len();
+        if let Some(run) = self.runs.last_mut().filter(|run| run.indent == indent) {
+            run.end = line_num;
+            return;
+        }
+        while let Some(&last) = self.open.last() {
+            if self.runs[last].indent < indent {
+                break;
+            }
+            self.open.pop();
+        }
+        self.runs.push(Run {
+            start: line_num,
+            end: line_num,
+            indent,
+            parent: self.open.last().copied(),
+        });
+        self.open.push(self.runs.len() - 1);
+    }
+
+    /// Line numbers of the less indented lines enclosing `position`, outermost
+    /// first, like the headers of folded code. A blank line takes the
+    /// indentation of the line after it.
+    fn outline(&self, position: usize) -> Vec<usize> {
+        let after = self.runs.partition_point(|run| run.start <= position);
+        let run = match after.checked_sub(1) {
+            Some(before) if position <= self.runs[before].end => before,
+            _ if after < self.runs.len() => after,
+            _ => return Vec::new(),
+        };
+        let mut ancestors = Vec::new();
+        let mut parent = self.runs[run].parent;
+        while let Some(run) = parent {
+            ancestors.push(self.runs[run].end);
+            parent = self.runs[run].parent;
+        }
+        ancestors.reverse();
+        ancestors
+    }
+}
+
+impl ContextFinder for OutlineFinder {
+    fn extend(&mut self, lines: &dyn Storage) {
+        for line_num in self.scanned..lines.len() {
+            self.index_line(line_num, &lines.line(line_num));
+        }
+        self.scanned = lines.len();
+    }
+
+    fn discard(&mut self, lines: usize) {
+        let discarded = self.runs.partition_point(|run| run.end < lines);
+        self.runs.drain(..discarded);
+        for run in &mut self.runs {
+            run.start = run.start.saturating_sub(lines);
+            run.end -= lines;
+            run.parent = run.parent.and_then(|parent| parent.checked_sub(discarded));
+        }
+        self.open.retain(|run| *run >= discarded);
+        for run in &mut self.open {
+            *run -= discarded;
+        }
+        self.scanned = self.scanned.saturating_sub(lines);
+    }
+
+    fn find_range(&self, _lines: &dyn Storage, _position: usize) -> Option<Range<usize>> {
+        None
+    }
+
+    fn index_size(&self) -> (usize, usize) {
+        (
+            self.runs.len(),
+            self.runs.len() * std::mem::size_of::<Run>(),
+        )
+    }
+
+    fn get_context<'a>(&self, lines: &'a dyn Storage, position: usize) -> Vec<Cow<'a, [String]>> {
+        self.outline(position)
+            .into_iter()
+            .map(|line| lines.lines(line..line + 1))
+            .collect()
+    }
+}
+
+/// Path of the new file of a `diff --git` header, whatever its prefixes: the
+/// default `a/` and `b/`, `i/` and `w/` of `diff.mnemonicPrefix`, ones set
+/// with `--src-prefix` and `--dst-prefix`, or none with `diff.noPrefix`.
+/// Paths may have spaces, so the header is split where both sides name the
+/// same path, falling back to the default prefix for renames.
+pub fn diff_path(header: &str) -> Option<&str> {
+    let paths = header.strip_prefix("diff --git ")?;
+    paths
+        .match_indices(' ')
+        .find_map(|(space, _)| common_path(&paths[..space], &paths[space + 1..]))
+        .or_else(|| Some(paths.rsplit_once(" b/")?.1))
+}
+
+/// The path both `old` and `new` end with, after prefixes of one directory
+/// each, if any.
+fn common_path<'a>(old: &str, new: &'a str) -> Option<&'a str> {
+    if old == new {
+        return Some(new);
+    }
+    let common = old
+        .bytes()
+        .rev()
+        .zip(new.bytes().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    // The prefixes may end alike, like `xa/` and `ya/`, so the path starts
+    // after the first slash of what the sides have in common
+    let slash = new.as_bytes()[new.len() - common..]
+        .iter()
+        .position(|byte| *byte == b'/')?;
+    let start = new.len() - common + slash + 1;
+    let path = &new[start..];
+    let prefix_of_one = |prefix: &str| prefix.find('/') == Some(prefix.len() - 1);
+    (!path.is_empty()
+        && prefix_of_one(&old[..old.len() - path.len()])
+        && prefix_of_one(&new[..start]))
+    .then_some(path)
+}
+
 #[cfg(test)]
 mod test {
-    use std::io::BufRead;
+    use std::{borrow::Cow, io::BufRead, ops::Range, path::Path};
 
-    use crate::{context_finder::ContextFinder, error::Error};
+    use clap::ValueEnum;
+    use proptest::{collection::vec, prelude::*};
+
+    use crate::{
+        context_finder::{
+            builtin, diff_path, ContextFinder, GitFinder, InputType, OutlineFinder, PatternFinder,
+        },
+        error::Error,
+        json::{JsonConfig, JsonLog},
+        storage::Storage,
+    };
 
     pub const GIT_LOG: &str = include_str!("../tests/data/git_patch");
+    pub const JOURNALD_LOG: &str = include_str!("../tests/data/journald");
+    pub const SYSLOG: &str = include_str!("../tests/data/syslog");
+    pub const JSON_LINES: &str = include_str!("../tests/data/json_lines");
+    pub const STRACE: &str = include_str!("../tests/data/strace");
+    pub const PYTEST: &str = include_str!("../tests/data/pytest");
+    pub const GCC: &str = include_str!("../tests/data/gcc");
+    pub const JAVA_STACKTRACE: &str = include_str!("../tests/data/java_stacktrace");
+    pub const MBOX: &str = include_str!("../tests/data/mbox");
+    pub const CARGO_LOG: &str = include_str!("../tests/data/cargo_test");
+    pub const MARKDOWN: &str = include_str!("../tests/data/markdown");
+    pub const OUTLINE: &str = include_str!("../tests/data/outline");
 
     fn read_input<R: BufRead>(mut reader: R) -> Result<String, Error> {
         let mut buf: Vec<u8> = Vec::new();
@@ -106,6 +1186,15 @@ mod test {
         Ok(result.to_string())
     }
 
+    fn finder(input_type: InputType, input: &[String]) -> Box<dyn ContextFinder> {
+        indexed(builtin(input_type, None), input)
+    }
+
+    fn indexed<F: ContextFinder + ?Sized>(mut cf: Box<F>, input: &[String]) -> Box<F> {
+        cf.extend(&input.to_vec());
+        cf
+    }
+
     #[test]
     fn read_file() {
         let input = GIT_LOG.repeat(10);
@@ -117,17 +1206,17 @@ mod test {
     fn find_commit_from_start() {
         let lines = GIT_LOG.lines();
         let input: Vec<String> = lines.map(|l| l.to_string()).collect();
-        let cf = ContextFinder::new(crate::context_finder::InputType::Git).unwrap();
-        let commit_pos = cf.find_range(&input, 0);
-        assert!(commit_pos.is_none());
+        let cf = indexed(Box::new(GitFinder::new()), &input);
+        let commit_pos = cf.find_ranges(0);
+        assert!(commit_pos.is_empty());
     }
 
     #[test]
     fn find_commit_from_end() {
         let lines = GIT_LOG.lines();
         let input: Vec<String> = lines.map(|l| l.to_string()).collect();
-        let cf = ContextFinder::new(crate::context_finder::InputType::Git).unwrap();
-        let range = cf.find_range(&input, input.len() - 1).unwrap();
+        let cf = indexed(Box::new(GitFinder::new()), &input);
+        let range = cf.find_ranges(input.len() - 1)[0].clone();
         assert_eq!(range.start, 306);
         assert_eq!(range.end, 311);
         assert!(input[range.start].contains("commit"));
@@ -138,17 +1227,17 @@ mod test {
     fn find_commit_patch_from_start() {
         let lines = GIT_LOG.lines();
         let input: Vec<String> = lines.map(|l| l.to_string()).collect();
-        let cf = ContextFinder::new(crate::context_finder::InputType::Git).unwrap();
-        let range = cf.find_range(&input, 0);
-        assert!(range.is_none());
+        let cf = indexed(Box::new(GitFinder::new()), &input);
+        let range = cf.find_ranges(0);
+        assert!(range.is_empty());
     }
 
     #[test]
     fn find_commit_patch_first() {
         let lines = GIT_LOG.lines();
         let input: Vec<String> = lines.map(|l| l.to_string()).collect();
-        let cf = ContextFinder::new(crate::context_finder::InputType::Git).unwrap();
-        let range = cf.find_range(&input, 10).unwrap();
+        let cf = indexed(Box::new(GitFinder::new()), &input);
+        let range = cf.find_ranges(10)[0].clone();
         assert_eq!(range.start, 0);
         assert_eq!(range.end, 5);
         assert!(input[range.start].contains("commit"));
@@ -159,11 +1248,573 @@ mod test {
     fn find_commit_patch() {
         let lines = GIT_LOG.lines();
         let input: Vec<String> = lines.map(|l| l.to_string()).collect();
-        let cf = ContextFinder::new(crate::context_finder::InputType::Git).unwrap();
-        let range = cf.find_range(&input, input.len() - 1).unwrap();
+        let cf = indexed(Box::new(GitFinder::new()), &input);
+        let range = cf.find_ranges(input.len() - 1)[0].clone();
         assert_eq!(range.start, 306);
         assert_eq!(range.end, 311);
         assert!(input[range.start].contains("commit"));
         assert!(input[range.start + 1].contains("Mr. Example"));
     }
+
+    #[test]
+    fn find_journald_boot() {
+        let lines = JOURNALD_LOG.lines();
+        let input: Vec<String> = lines.map(|l| l.to_string()).collect();
+        let cf = indexed(Box::new(PatternFinder::journald()), &input);
+        let range = cf.find_ranges(5)[0].clone();
+        assert_eq!(range.start, 3);
+        assert_eq!(range.end, 3);
+        assert!(input[range.start].starts_with("-- Boot"));
+    }
+
+    #[test]
+    fn find_journald_unit_start() {
+        let lines = JOURNALD_LOG.lines();
+        let input: Vec<String> = lines.map(|l| l.to_string()).collect();
+        let cf = indexed(Box::new(PatternFinder::journald()), &input);
+        let range = cf.find_ranges(input.len() - 1).pop().unwrap();
+        assert_eq!(range.start, 9);
+        assert_eq!(range.end, 9);
+        assert!(input[range.start].contains("Starting PostgreSQL"));
+    }
+
+    #[test]
+    fn find_cargo_failing_test() {
+        let lines = CARGO_LOG.lines();
+        let input: Vec<String> = lines.map(|l| l.to_string()).collect();
+        let cf = indexed(Box::new(PatternFinder::cargo()), &input);
+        let range = cf.find_ranges(29)[0].clone();
+        assert_eq!(range.start, 19);
+        assert_eq!(range.end, 28);
+        let range = cf.find_ranges(35)[0].clone();
+        assert_eq!(range.start, 19);
+        assert_eq!(range.end, 30);
+        assert!(input[range.start].contains("tests::parses"));
+    }
+
+    #[test]
+    fn find_cargo_test_run_and_error() {
+        let lines = CARGO_LOG.lines();
+        let input: Vec<String> = lines.map(|l| l.to_string()).collect();
+        let cf = indexed(Box::new(PatternFinder::cargo()), &input);
+        let range = cf.find_ranges(15)[0].clone();
+        assert_eq!(range.start, 12);
+        assert!(input[range.start].starts_with("running 3 tests"));
+        let range = cf.find_ranges(41)[0].clone();
+        assert_eq!(range.start, 38);
+        assert!(input[range.start].starts_with("error[E0308]"));
+    }
+
+    #[test]
+    fn find_markdown_heading_path() {
+        let lines = MARKDOWN.lines();
+        let input: Vec<String> = lines.map(|l| l.to_string()).collect();
+        let cf = finder(InputType::Markdown, &input);
+        assert!(cf.get_context(&input, 0).is_empty());
+        let context = cf.get_context(&input, 12);
+        assert_eq!(context[0][0], "# Guide > ## Installing > ### From source");
+        let context = cf.get_context(&input, input.len() - 1);
+        assert_eq!(context[0][0], "# Guide > ## Usage");
+    }
+
+    #[test]
+    fn find_nested_git_levels() {
+        let lines = GIT_LOG.lines();
+        let input: Vec<String> = lines.map(|l| l.to_string()).collect();
+        let cf = indexed(Box::new(GitFinder::new()), &input);
+        let ranges = cf.find_ranges(25);
+        assert_eq!(ranges.len(), 3);
+        assert_eq!(ranges[0], 0..5);
+        assert!(input[ranges[1].start].starts_with("diff --git a/src/main.rs"));
+        assert!(input[ranges[2].start].starts_with("@@ "));
+        assert_eq!(ranges[2].start, ranges[2].end);
+
+        // The files of the previous commit are not shown in the next header
+        let ranges = cf.find_ranges(180);
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].start, 178);
+    }
+
+    #[test]
+    fn find_nested_journald_levels() {
+        let lines = JOURNALD_LOG.lines();
+        let input: Vec<String> = lines.map(|l| l.to_string()).collect();
+        let cf = finder(InputType::Journald, &input);
+        let context = cf.get_context(&input, input.len() - 1);
+        assert_eq!(context.len(), 2);
+        assert!(context[0][0].starts_with("-- Boot"));
+        assert!(context[1][0].contains("Starting PostgreSQL"));
+    }
+
+    #[test]
+    fn find_syslog_day_and_program() {
+        let lines = SYSLOG.lines();
+        let input: Vec<String> = lines.map(|l| l.to_string()).collect();
+        let cf = finder(InputType::Syslog, &input);
+        assert!(cf.get_context(&input, 0).is_empty());
+        let context = cf.get_context(&input, 2);
+        assert_eq!(context.len(), 2);
+        assert!(context[0][0].starts_with("Apr 11 23:58:01 web1 CRON"));
+        assert!(context[1][0].starts_with("Apr 11 23:59:14 web1 sshd"));
+        // The first message of a day is also the first of its program
+        let context = cf.get_context(&input, 4);
+        assert_eq!(context.len(), 1);
+        assert!(context[0][0].starts_with("Apr 12 00:00:01"));
+        let context = cf.get_context(&input, 6);
+        assert!(context[1][0].starts_with("Apr 12 00:00:03 web1 nginx"));
+        // Continuation lines stay with the message above them
+        let context = cf.get_context(&input, 9);
+        assert!(context[1][0].contains("kernel"));
+        // Days written differently aren't compared, the RFC 5424 messages
+        // start a new one
+        let context = cf.get_context(&input, input.len() - 1);
+        assert_eq!(context.len(), 1);
+        assert!(context[0][0].contains("2023-04-12T00:01:12.003Z db1 postgres"));
+    }
+
+    #[test]
+    fn find_json_group() {
+        let lines = JSON_LINES.lines();
+        let input: Vec<String> = lines.map(|l| l.to_string()).collect();
+        let json = JsonLog::new(&JsonConfig {
+            group: Some("request_id".to_string()),
+            ..JsonConfig::default()
+        });
+        let mut cf = builtin(InputType::Json, Some(json));
+        cf.extend(&input);
+        assert!(cf.get_context(&input, 1).is_empty());
+        let context = cf.get_context(&input, 3);
+        assert_eq!(
+            context[0][0],
+            "request_id=r-101: 2023-04-12T17:49:21.120Z info GET /orders"
+        );
+        // The stack trace printed in between stays in its request
+        let context = cf.get_context(&input, input.len() - 1);
+        assert_eq!(
+            context[0][0],
+            "request_id=r-102: 2023-04-12T17:49:22.500Z info POST /orders"
+        );
+    }
+
+    #[test]
+    fn find_strace_process() {
+        let lines = STRACE.lines();
+        let input: Vec<String> = lines.map(|l| l.to_string()).collect();
+        let cf = finder(InputType::Strace, &input);
+        assert!(cf.get_context(&input, 0).is_empty());
+        let context = cf.get_context(&input, 3);
+        assert_eq!(context[0][0], "first process");
+        assert!(context[1][0].starts_with(r#"execve("/usr/bin/make""#));
+        // The other processes in between don't change the one of the line
+        let context = cf.get_context(&input, 14);
+        assert_eq!(context[0][0], "pid 4313");
+        assert!(context[1][0].contains(r#"execve("/usr/bin/ls""#));
+        let context = cf.get_context(&input, 19);
+        assert_eq!(context[0][0], "pid 4312");
+        assert!(context[1][0].contains(r#"execve("/bin/sh""#));
+        let context = cf.get_context(&input, input.len());
+        assert!(context[1][0].starts_with(r#"execve("/usr/bin/make""#));
+    }
+
+    #[test]
+    fn find_failing_pytest() {
+        let lines = PYTEST.lines();
+        let input: Vec<String> = lines.map(|l| l.to_string()).collect();
+        let cf = finder(InputType::Pytest, &input);
+        // Deep in the traceback of the first failure
+        let context = cf.get_context(&input, 21);
+        assert_eq!(context.len(), 2);
+        assert!(context[0][0].contains(" FAILURES "));
+        assert!(context[1][0].contains(" test_divide "));
+        let context = cf.get_context(&input, 33);
+        assert_eq!(context.len(), 3);
+        assert!(context[1][0].contains(" test_round "));
+        assert!(context[2][0].contains(" Captured stdout call "));
+        // Tests of the failures section are not shown in the summary
+        let context = cf.get_context(&input, 36);
+        assert_eq!(context.len(), 2);
+        assert!(context[0][0].contains(" short test summary info "));
+        assert!(context[1][0].starts_with("FAILED tests/test_calc.py::test_divide"));
+    }
+
+    #[test]
+    fn find_gcc_diagnostic() {
+        let lines = GCC.lines();
+        let input: Vec<String> = lines.map(|l| l.to_string()).collect();
+        let cf = finder(InputType::Gcc, &input);
+        assert!(cf.get_context(&input, 2).is_empty());
+        // Through the notes of the first error and the lines including them
+        let context = cf.get_context(&input, 12);
+        assert_eq!(context.len(), 1);
+        assert!(context[0][0].starts_with("main.cpp:12:15: error: no matching function"));
+        let context = cf.get_context(&input, 17);
+        assert!(context[0][0].contains("stl_algo.h:1950:50: error: no match"));
+        // Gone once the diagnostic is over
+        assert!(cf.get_context(&input, 19).is_empty());
+        let context = cf.get_context(&input, 22);
+        assert!(context[0][0].starts_with("util.cpp:3:10: warning:"));
+        assert!(cf.get_context(&input, input.len()).is_empty());
+    }
+
+    #[test]
+    fn find_java_exception_and_cause() {
+        let lines = JAVA_STACKTRACE.lines();
+        let input: Vec<String> = lines.map(|l| l.to_string()).collect();
+        let cf = finder(InputType::JavaStacktrace, &input);
+        assert!(cf.get_context(&input, 2).is_empty());
+        let context = cf.get_context(&input, 5);
+        assert_eq!(context.len(), 1);
+        assert!(context[0][0].starts_with("Exception in thread \"main\""));
+        // The root exception stays while the causes follow each other
+        let context = cf.get_context(&input, 12);
+        assert_eq!(context.len(), 2);
+        assert!(context[0][0].starts_with("Exception in thread \"main\""));
+        assert!(context[1][0].starts_with("Caused by: java.io.UncheckedIOException"));
+        let context = cf.get_context(&input, 16);
+        assert!(context[1][0].starts_with("Caused by: java.sql.SQLException"));
+        // Gone once the trace is over
+        assert!(cf.get_context(&input, 18).is_empty());
+        let context = cf.get_context(&input, 22);
+        assert_eq!(context.len(), 1);
+        assert!(context[0][0].contains("java.lang.OutOfMemoryError"));
+        assert!(cf.get_context(&input, input.len()).is_empty());
+    }
+
+    #[test]
+    fn find_mail_subject() {
+        let input: Vec<String> = MBOX.lines().map(|l| l.to_string()).collect();
+        let cf = finder(InputType::Mailbox, &input);
+        assert!(cf.get_context(&input, 0).is_empty());
+        let first = [
+            "From alice@example.com Mon Apr 10 09:12:44 2023",
+            "Date: Mon, 10 Apr 2023 09:12:40 +0000",
+            "Subject: [PATCH] Read stdin when no file is given",
+        ];
+        assert_eq!(
+            cf.get_context(&input, 12),
+            [first.map(String::from).to_vec()]
+        );
+        // Headers quoted in the body are not the subject
+        assert_eq!(
+            cf.get_context(&input, 19),
+            [first.map(String::from).to_vec()]
+        );
+        let context = cf.get_context(&input, 25);
+        assert_eq!(context[0].len(), 3);
+        assert_eq!(
+            context[0][1],
+            "Subject: Re: [PATCH] Read stdin when no file is given"
+        );
+    }
+
+    #[test]
+    fn find_enclosing_commit() {
+        let input: Vec<String> = GIT_LOG.lines().map(|l| l.to_string()).collect();
+        let cf = finder(InputType::Git, &input);
+        assert_eq!(cf.find_range(&input, 0), Some(0..178));
+        assert_eq!(cf.find_range(&input, 200), Some(178..306));
+        // The last commit may still be streaming in
+        assert_eq!(
+            cf.find_range(&input, input.len() - 1),
+            Some(306..usize::MAX)
+        );
+        assert_eq!(
+            finder(InputType::Outline, &input).find_range(&input, 10),
+            None
+        );
+    }
+
+    #[test]
+    fn find_context_boundaries() {
+        let input: Vec<String> = GIT_LOG.lines().map(|l| l.to_string()).collect();
+        let cf = finder(InputType::Git, &input);
+        let (start, end) = cf.boundaries(&input, 200).unwrap();
+        assert_eq!(start, 178);
+        assert!(input[end.unwrap()].starts_with("diff --git "));
+        // The header at the top is not a context yet
+        assert_eq!(cf.boundaries(&input, 0), None);
+        assert_eq!(
+            finder(InputType::Outline, &input).boundaries(&input, 10),
+            None
+        );
+    }
+
+    /// Sections of lines like `== Name ==`, indexed as they are read.
+    #[derive(Default)]
+    struct Sections {
+        starts: Vec<usize>,
+        scanned: usize,
+    }
+
+    impl ContextFinder for Sections {
+        fn find_range(&self, _lines: &dyn Storage, position: usize) -> Option<Range<usize>> {
+            let next = self.starts.partition_point(|start| *start <= position);
+            let start = *self.starts[..next].last()?;
+            Some(start..self.starts.get(next).copied().unwrap_or(usize::MAX))
+        }
+
+        fn extend(&mut self, lines: &dyn Storage) {
+            for line in self.scanned..lines.len() {
+                if lines.line(line).starts_with("== ") {
+                    self.starts.push(line);
+                }
+            }
+            self.scanned = lines.len();
+        }
+    }
+
+    #[test]
+    fn find_custom_context() {
+        let input: Vec<String> = ["== One ==", "a", "b", "== Two ==", "c"]
+            .map(String::from)
+            .to_vec();
+        let mut cf = Sections::default();
+        cf.extend(&input[..2].to_vec());
+        cf.extend(&input);
+        assert_eq!(cf.find_range(&input, 2), Some(0..3));
+        assert_eq!(cf.get_context(&input, 2), [Cow::Borrowed(&input[..1])]);
+        assert_eq!(cf.boundaries(&input, 4), Some((3, None)));
+        // The section header at the top is not a context yet
+        assert!(cf.get_context(&input, 0).is_empty());
+        assert_eq!(cf.identifier(&cf.get_context(&input, 4)), None);
+    }
+
+    #[test]
+    fn count_indexed_contexts() {
+        let input: Vec<String> = GIT_LOG.lines().map(|l| l.to_string()).collect();
+        let cf = finder(InputType::Git, &input);
+        let (contexts, bytes) = cf.index_size();
+        let headers = input
+            .iter()
+            .filter(|line| line.starts_with("commit ") || line.starts_with("diff --git "))
+            .count();
+        let hunks = input.iter().filter(|line| line.starts_with("@@ ")).count();
+        assert_eq!(contexts, headers + hunks);
+        assert!(bytes >= contexts * 2 * std::mem::size_of::<usize>());
+        assert_eq!(finder(InputType::Git, &[]).index_size(), (0, 0));
+    }
+
+    #[test]
+    fn find_commit_hash() {
+        let lines = GIT_LOG.lines();
+        let input: Vec<String> = lines.map(|l| l.to_string()).collect();
+        let cf = finder(InputType::Git, &input);
+        let context = cf.get_context(&input, 10);
+        assert_eq!(
+            cf.identifier(&context),
+            Some(input[0].trim_start_matches("commit "))
+        );
+        assert_eq!(cf.identifier(&[]), None);
+    }
+
+    #[test]
+    fn find_diff_location() {
+        let lines = GIT_LOG.lines();
+        let input: Vec<String> = lines.map(|l| l.to_string()).collect();
+        let cf = finder(InputType::Git, &input);
+        assert_eq!(cf.diff_location(&input, 3), None);
+        assert_eq!(
+            cf.diff_location(&input, 18),
+            Some(("src/main.rs".to_string(), 1))
+        );
+        assert_eq!(
+            cf.diff_location(&input, 22),
+            Some(("src/main.rs".to_string(), 3))
+        );
+        assert_eq!(
+            cf.diff_location(&input, 24),
+            Some(("src/main.rs".to_string(), 4))
+        );
+        // Removed lines point at the line replacing them
+        assert_eq!(
+            cf.diff_location(&input, 26),
+            Some(("src/main.rs".to_string(), 6))
+        );
+        assert_eq!(
+            cf.diff_location(&input, 29),
+            Some(("src/main.rs".to_string(), 6))
+        );
+    }
+
+    #[test]
+    fn input_type_of_file() {
+        assert_eq!(
+            InputType::for_path(Path::new("fix.patch")),
+            Some(InputType::Git)
+        );
+        assert_eq!(
+            InputType::for_path(Path::new("docs/README.MD")),
+            Some(InputType::Markdown)
+        );
+        assert_eq!(
+            InputType::for_path(Path::new("app.jsonl")),
+            Some(InputType::Json)
+        );
+        assert_eq!(InputType::for_path(Path::new("syslog")), None);
+        assert_eq!(InputType::for_path(Path::new("build.log")), None);
+    }
+
+    #[test]
+    fn parse_diff_paths() {
+        let path = |header: &str| diff_path(header).map(str::to_string);
+        let main = Some("src/main.rs".to_string());
+        assert_eq!(path("diff --git a/src/main.rs b/src/main.rs"), main);
+        assert_eq!(path("diff --git i/src/main.rs w/src/main.rs"), main);
+        assert_eq!(path("diff --git c/src/main.rs o/src/main.rs"), main);
+        assert_eq!(
+            path("diff --git before/src/main.rs after/src/main.rs"),
+            main
+        );
+        assert_eq!(path("diff --git src/main.rs src/main.rs"), main);
+        assert_eq!(path("diff --git xa/src/main.rs ya/src/main.rs"), main);
+        assert_eq!(
+            path("diff --git a/my notes.md b/my notes.md").as_deref(),
+            Some("my notes.md")
+        );
+        assert_eq!(
+            path("diff --git a/src/old.rs b/src/new.rs").as_deref(),
+            Some("src/new.rs")
+        );
+        // Renames differing in a multibyte character
+        assert_eq!(path("diff --git a/ä b/ö").as_deref(), Some("ö"));
+        assert_eq!(path("index a30a4b0..2175237 100644"), None);
+    }
+
+    #[test]
+    fn find_diff_location_with_mnemonic_prefix() {
+        let input: Vec<String> = GIT_LOG
+            .lines()
+            .map(|line| match line.strip_prefix("diff --git ") {
+                Some(_) => line.replace(" a/", " i/").replace(" b/", " w/"),
+                None => line.to_string(),
+            })
+            .collect();
+        let cf = finder(InputType::Git, &input);
+        assert_eq!(
+            cf.diff_location(&input, 22),
+            Some(("src/main.rs".to_string(), 3))
+        );
+    }
+
+    #[test]
+    fn find_outline_ancestors() {
+        let lines = OUTLINE.lines();
+        let input: Vec<String> = lines.map(|l| l.to_string()).collect();
+        let cf = finder(InputType::Outline, &input);
+        assert!(cf.get_context(&input, 0).is_empty());
+        // The blank line takes the indentation of the line after it
+        let context = cf.get_context(&input, 5);
+        assert_eq!(context.len(), 3);
+        assert_eq!(context[0][0], "services:");
+        assert_eq!(context[1][0], "  web:");
+        assert_eq!(context[2][0], "    ports:");
+        let context = cf.get_context(&input, 11);
+        assert_eq!(context.len(), 3);
+        assert_eq!(context[1][0], "  db:");
+        assert!(cf.get_context(&input, 12).is_empty());
+
+        // Dropping the top of the file leaves the rest of `db` under nothing
+        let mut cf = indexed(Box::new(OutlineFinder::new()), &input);
+        cf.discard(8);
+        let rest = input[8..].to_vec();
+        let context = cf.get_context(&rest, 3);
+        assert_eq!(context.len(), 1);
+        assert_eq!(context[0][0], "    environment:");
+        let context = cf.get_context(&rest, 5);
+        assert_eq!(context.len(), 1);
+        assert_eq!(context[0][0], "volumes:");
+    }
+
+    #[test]
+    fn index_lines_as_they_arrive() {
+        let input: Vec<String> = GIT_LOG.lines().map(|l| l.to_string()).collect();
+        let whole = indexed(Box::new(GitFinder::new()), &input);
+        let mut streamed = GitFinder::new();
+        for end in (0..input.len()).step_by(7).chain([input.len()]) {
+            streamed.extend(&input[..end].to_vec());
+        }
+        for position in [0, 10, 25, 180, input.len() - 1] {
+            assert_eq!(streamed.find_ranges(position), whole.find_ranges(position));
+        }
+
+        // Dropping the first commit leaves the second one found from the top
+        streamed.discard(178);
+        let rest = input[178..].to_vec();
+        assert_eq!(streamed.find_ranges(10)[0], 0..5);
+        assert_eq!(
+            streamed.find_ranges(10),
+            indexed(Box::new(GitFinder::new()), &rest).find_ranges(10)
+        );
+
+        let input: Vec<String> = MARKDOWN.lines().map(|l| l.to_string()).collect();
+        let mut streamed = builtin(InputType::Markdown, None);
+        streamed.extend(&input[..5].to_vec());
+        streamed.extend(&input);
+        assert_eq!(
+            streamed.get_context(&input, 12)[0][0],
+            "# Guide > ## Installing > ### From source"
+        );
+    }
+
+    /// Lines made of the pieces the context finders look for, so that their
+    /// levels actually nest, mixed with anything at all.
+    fn line() -> impl Strategy<Value = String> {
+        prop_oneof![
+            Just("commit 96f57fbcd5a12a748b62521a446e5b18531704d6".to_string()),
+            "diff --git a/[a-z]{1,3} b/[a-z]{0,3}",
+            "@@ -[0-9]{1,2},[0-9] \\+[0-9]{1,20}(,[0-9])? @@.{0,5}",
+            "[ +-].{0,10}",
+            "#{1,7} ?.{0,5}",
+            Just("```".to_string()),
+            " {0,8}[a-z]{0,3}:?",
+            "(\\[pid +[0-9]{1,3}\\] )?(execve\\(|<\\.\\.\\. ).{0,5}",
+            "Apr 1[12] 0[0-9]:00:00 [a-z]{1,2} [a-z]{1,2}\\[[0-9]\\]: .{0,5}",
+            "([=_-]{3} [a-z ]{1,5} [=_-]{3}|FAILED .{0,3})",
+            "\\{\"id\":[0-9],\"msg\":\"[a-z]{0,3}\"\\}",
+            "(error|warning|running 1 test|---- a ----).{0,5}",
+            "[a-z]\\.c:[0-9]:([0-9]:)? +(error|note|required from): .{0,5}",
+            "(From a@b |Subject: |Date: )?.{0,5}",
+            "(Exception in thread \"a\" |\tat |\t?Caused by: |\t\\.\\.\\. 1 more).{0,5}",
+            ".{0,20}",
+        ]
+    }
+
+    /// A finder of each input type with nothing indexed yet.
+    fn finders() -> Vec<Box<dyn ContextFinder>> {
+        let json = JsonLog::new(&JsonConfig {
+            group: Some("id".to_string()),
+            ..JsonConfig::default()
+        });
+        InputType::value_variants()
+            .iter()
+            .map(|input_type| builtin(*input_type, Some(json.clone())))
+            .collect()
+    }
+
+    proptest! {
+        // Each case runs every input type, fuzzing goes further
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn never_panic_on_any_input(
+            lines in vec(line(), 0..40),
+            split in 0..40usize,
+            position in 0..45usize,
+        ) {
+            for (mut whole, mut streamed) in finders().into_iter().zip(finders()) {
+                whole.extend(&lines);
+                // Lines arriving in two chunks are indexed the same
+                streamed.extend(&lines[..split.min(lines.len())].to_vec());
+                streamed.extend(&lines);
+                let position = position.min(lines.len());
+                let context = streamed.get_context(&lines, position);
+                prop_assert_eq!(&context, &whole.get_context(&lines, position));
+                streamed.identifier(&context);
+                streamed.diff_location(&lines, position);
+                streamed.discard(split.min(lines.len()));
+                let rest = lines[split.min(lines.len())..].to_vec();
+                streamed.get_context(&rest, position.min(rest.len()));
+            }
+        }
+    }
 }
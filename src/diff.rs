@@ -0,0 +1,114 @@
+use clap::ValueEnum;
+
+/// When to color diffs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorMode {
+    /// Color `git` input unless it already has colors of its own
+    #[default]
+    Auto,
+    /// Color lines looking like a diff whatever the input type
+    Always,
+    /// Show lines as they are
+    Never,
+}
+
+/// Kinds of lines in `git log --patch` and `git diff` output, colored
+/// differently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLine {
+    /// `commit <hash>`
+    Commit,
+    /// Commit metadata like `Author:` and `Date:`
+    Metadata,
+    /// `diff --git`, `index` and the `---` and `+++` file names
+    FileHeader,
+    /// `@@ -1,2 +1,3 @@`
+    Hunk,
+    Added,
+    Removed,
+}
+
+impl DiffLine {
+    /// Kind of `line`, if it is part of a diff. File headers are checked
+    /// before added and removed lines, as they start with the same markers.
+    pub fn of(line: &str) -> Option<Self> {
+        const FILE_HEADERS: [&str; 9] = [
+            "diff --git ",
+            "index ",
+            "--- ",
+            "+++ ",
+            "new file mode ",
+            "deleted file mode ",
+            "similarity index ",
+            "rename from ",
+            "rename to ",
+        ];
+        const METADATA: [&str; 6] = [
+            "Author:",
+            "AuthorDate:",
+            "Commit:",
+            "CommitDate:",
+            "Date:",
+            "Merge:",
+        ];
+        if line.starts_with("commit ") {
+            Some(DiffLine::Commit)
+        } else if METADATA.iter().any(|prefix| line.starts_with(prefix)) {
+            Some(DiffLine::Metadata)
+        } else if FILE_HEADERS.iter().any(|prefix| line.starts_with(prefix)) {
+            Some(DiffLine::FileHeader)
+        } else if line.starts_with("@@") {
+            Some(DiffLine::Hunk)
+        } else if line.starts_with('+') {
+            Some(DiffLine::Added)
+        } else if line.starts_with('-') {
+            Some(DiffLine::Removed)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether `line` has ANSI color codes, like the output of `git log
+/// --color`.
+pub fn has_colors(line: &str) -> bool {
+    line.contains("\x1b[")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{has_colors, DiffLine};
+
+    const GIT_LOG: &str = include_str!("../tests/data/git_patch");
+
+    #[test]
+    fn classify_diff_lines() {
+        let kinds: Vec<Option<DiffLine>> = GIT_LOG.lines().take(19).map(DiffLine::of).collect();
+        assert_eq!(
+            kinds,
+            [
+                Some(DiffLine::Commit),
+                Some(DiffLine::Metadata),
+                Some(DiffLine::Metadata),
+                None,
+                None,
+                None,
+                Some(DiffLine::FileHeader),
+                Some(DiffLine::FileHeader),
+                Some(DiffLine::FileHeader),
+                Some(DiffLine::FileHeader),
+                Some(DiffLine::Hunk),
+                None,
+                None,
+                None,
+                Some(DiffLine::Added),
+                Some(DiffLine::Added),
+                None,
+                Some(DiffLine::Removed),
+                Some(DiffLine::FileHeader),
+            ]
+        );
+        assert!(has_colors("\x1b[33mcommit b8e882d\x1b[m"));
+        assert!(!has_colors("commit b8e882d"));
+    }
+}
@@ -1,4 +1,5 @@
 use std::io;
+use std::path::PathBuf;
 use std::sync::mpsc;
 
 use thiserror::Error;
@@ -9,8 +10,28 @@ pub enum Error {
     Io(#[from] io::Error),
     #[error("Could not read input")]
     StreamingReceive(#[from] mpsc::RecvError),
-    #[error("Could not send input to terminal")]
-    StreamingSend,
-    #[error("Timeout while waiting for input stream")]
-    StreamingTimeout(#[from] std::sync::mpsc::RecvTimeoutError),
+    #[error("Could not read input: {0}")]
+    InputRead(io::Error),
+    #[error("Could not read config file {0:?}: {1}")]
+    ConfigRead(PathBuf, io::Error),
+    #[error("Invalid config file: {0}")]
+    ConfigParse(#[from] toml::de::Error),
+    #[error("Could not read snapshot {0:?}: {1}")]
+    SnapshotRead(PathBuf, io::Error),
+    #[error("Not a cag snapshot: {0:?}")]
+    InvalidSnapshot(PathBuf),
+    #[error("Could not save snapshot {0:?}: {1}")]
+    SnapshotWrite(PathBuf, io::Error),
+    #[error("Could not write to a temporary file: {0}")]
+    Spill(io::Error),
+    #[error("Invalid regular expression: {0}")]
+    InvalidPattern(#[from] regex::Error),
+    #[error("Could not run {0}: {1}")]
+    FilterRun(String, io::Error),
+    #[error("{0} failed: {1}")]
+    FilterFailed(String, String),
+    #[error("Invalid default flags in {0}: {1}")]
+    DefaultFlags(&'static str, shell_words::ParseError),
+    #[error("Could not check crates.io for a newer release: {0}")]
+    UpdateCheck(String),
 }
@@ -0,0 +1,125 @@
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{self, Event};
+use tracing::trace;
+
+use crate::{
+    error::Error,
+    input::{Chunk, InputReceiver},
+};
+
+/// What the pager was woken up by.
+pub enum Wake {
+    Event(Event),
+    /// A chunk of the input, or `None` once it has ended.
+    Input(Option<Result<Chunk, Error>>),
+    Timeout,
+}
+
+/// Waits on the terminal and the input together, so that lines show up as
+/// soon as they are read and keys are handled as soon as they are typed.
+pub struct Waiter {
+    #[cfg(unix)]
+    tty: Option<unix::Tty>,
+}
+
+impl Waiter {
+    pub fn new() -> Self {
+        Waiter {
+            #[cfg(unix)]
+            tty: unix::Tty::open()
+                .map_err(|err| trace!("Not waiting on the terminal: {err}"))
+                .ok(),
+        }
+    }
+
+    /// Waits until `timeout` for a terminal event or, while `streaming`, for
+    /// input, which isn't taken before `input_after` so that a fast producer
+    /// doesn't have every chunk drawn.
+    pub fn wait(
+        &mut self,
+        rx: &mut InputReceiver,
+        streaming: bool,
+        input_after: Instant,
+        timeout: Duration,
+    ) -> io::Result<Wake> {
+        #[cfg(unix)]
+        if let Some(tty) = &mut self.tty {
+            return tty.wait(rx, streaming, input_after, Instant::now() + timeout);
+        }
+        // Without a terminal to wait on, input is taken between polls
+        let _ = (rx, streaming, input_after);
+        Ok(match event::poll(timeout)? {
+            true => Wake::Event(event::read()?),
+            false => Wake::Timeout,
+        })
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::{
+        fs::File,
+        io,
+        time::{Duration, Instant},
+    };
+
+    use crossterm::event;
+    use tokio::{
+        io::unix::AsyncFd,
+        signal::unix::{signal, Signal, SignalKind},
+        time::sleep_until,
+    };
+
+    use super::Wake;
+    use crate::input::{runtime, InputReceiver};
+
+    /// The terminal crossterm reads events from, opened again to be told
+    /// when there is something to read, without reading it.
+    pub struct Tty {
+        fd: AsyncFd<File>,
+        /// Resizes, which crossterm learns of from the signal, not the
+        /// terminal.
+        resize: Signal,
+    }
+
+    impl Tty {
+        pub fn open() -> io::Result<Self> {
+            let _runtime = runtime().enter();
+            Ok(Tty {
+                fd: AsyncFd::new(File::open("/dev/tty")?)?,
+                resize: signal(SignalKind::window_change())?,
+            })
+        }
+
+        pub fn wait(
+            &mut self,
+            rx: &mut InputReceiver,
+            streaming: bool,
+            input_after: Instant,
+            deadline: Instant,
+        ) -> io::Result<Wake> {
+            let Tty { fd, resize } = self;
+            runtime().block_on(async {
+                loop {
+                    // Including events crossterm has already read
+                    if event::poll(Duration::ZERO)? {
+                        return Ok(Wake::Event(event::read()?));
+                    }
+                    tokio::select! {
+                        ready = fd.readable() => ready?.clear_ready(),
+                        _ = resize.recv() => {}
+                        chunk = async {
+                            sleep_until(input_after.into()).await;
+                            rx.recv().await
+                        }, if streaming => return Ok(Wake::Input(chunk)),
+                        () = sleep_until(deadline.into()) => return Ok(Wake::Timeout),
+                    }
+                }
+            })
+        }
+    }
+}
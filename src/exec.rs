@@ -0,0 +1,105 @@
+use std::{
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use tokio::{sync::mpsc::unbounded_channel, task::JoinHandle};
+
+use crate::{
+    error::Error,
+    input::{runtime, stream_reader, InputReceiver, Pause},
+};
+
+/// A command run with `--exec`, whose output is paged. What it writes to
+/// stderr is kept for a panel of its own, and it is killed once paging is
+/// done with it, like when it is run again.
+pub struct Exec {
+    child: Child,
+    stderr: Arc<Mutex<Vec<String>>>,
+}
+
+impl Exec {
+    /// Runs `command` with the shell, reading its output like
+    /// `stream_input` reads a file. Failing to start it is reported the same
+    /// way as failing to open a file.
+    pub fn spawn(command: &str, pause: Pause) -> (InputReceiver, JoinHandle<()>, Option<Exec>) {
+        let spawned = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(err) => {
+                let (tx, rx) = unbounded_channel();
+                let _ = tx.send(Err(Error::InputRead(err)));
+                return (rx, runtime().spawn(async {}), None);
+            }
+        };
+        let stderr = Arc::new(Mutex::new(Vec::new()));
+        if let Some(pipe) = child.stderr.take() {
+            let stderr = stderr.clone();
+            thread::spawn(move || {
+                for line in BufReader::new(pipe).lines() {
+                    let Ok(line) = line else {
+                        return;
+                    };
+                    stderr.lock().unwrap().push(line);
+                }
+            });
+        }
+        let (rx, handle) = match child.stdout.take() {
+            Some(stdout) => stream_reader(Box::new(stdout), pause),
+            None => stream_reader(Box::new(std::io::empty()), pause),
+        };
+        (rx, handle, Some(Exec { child, stderr }))
+    }
+
+    /// The last `count` lines written to stderr so far, and how many there
+    /// are in all.
+    pub fn stderr(&self, count: usize) -> (Vec<String>, usize) {
+        let stderr = self.stderr.lock().unwrap();
+        let start = stderr.len().saturating_sub(count);
+        (stderr[start..].to_vec(), stderr.len())
+    }
+}
+
+impl Drop for Exec {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::Exec;
+    use crate::input::{runtime, Pause};
+
+    #[test]
+    fn page_output_and_keep_stderr() {
+        let (mut rx, handle, exec) =
+            Exec::spawn("echo out; echo err >&2; echo more", Pause::default());
+        let lines: Vec<String> = std::iter::from_fn(|| rx.blocking_recv())
+            .flat_map(|chunk| chunk.unwrap().lines)
+            .collect();
+        runtime().block_on(handle).unwrap();
+        assert_eq!(lines, ["out", "more"]);
+        let exec = exec.unwrap();
+        // Stderr is read on a thread of its own
+        for _ in 0..100 {
+            if exec.stderr(5).1 > 0 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(exec.stderr(5), (vec!["err".to_string()], 1));
+        assert_eq!(exec.stderr(0), (Vec::new(), 1));
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashSet;
+
+use regex::Regex;
+
+use crate::error::Error;
+
+/// URLs up to whitespace or a closing quote or bracket.
+const URL: &str = r#"https?://[^\s<>"'`)\]]+"#;
+/// Relative or absolute paths with at least one directory, like
+/// `src/main.rs` or `~/.config/cag`.
+const PATH: &str = r#"(?:^|[\s'"`(=])((?:~|\.{1,2})?/?(?:[\w.-]+/)+[\w.-]+)"#;
+
+/// Finds URLs, paths and anything else matching the configured patterns in
+/// lines, to be listed for opening or copying.
+#[derive(Debug)]
+pub struct Extractor {
+    patterns: Vec<Regex>,
+}
+
+impl Extractor {
+    /// Uses the built-in URL and path patterns and `extra`. Patterns with a
+    /// capture group extract the first group instead of the whole match.
+    pub fn new(extra: &[String]) -> Result<Self, Error> {
+        let patterns = [URL, PATH]
+            .into_iter()
+            .chain(extra.iter().map(String::as_str))
+            .map(Regex::new)
+            .collect::<Result<_, _>>()?;
+        Ok(Extractor { patterns })
+    }
+
+    /// Everything found in `lines`, in order and without duplicates.
+    pub fn extract(&self, lines: impl IntoIterator<Item = impl AsRef<str>>) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut found = Vec::new();
+        for line in lines {
+            for pattern in &self.patterns {
+                for captures in pattern.captures_iter(line.as_ref()) {
+                    let Some(item) = captures.get(1).or_else(|| captures.get(0)) else {
+                        continue;
+                    };
+                    // Punctuation ending a sentence is rarely part of it
+                    let item = item.as_str().trim_end_matches(['.', ',', ';', ':']);
+                    if !item.is_empty() && seen.insert(item.to_string()) {
+                        found.push(item.to_string());
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Extractor;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn extract_urls_and_paths() {
+        let extractor = Extractor::new(&[]).unwrap();
+        let found = extractor.extract(lines(&[
+            "See https://github.com/iisulop/cag/issues/1.",
+            "    Fix panic in src/main.rs (see ./docs/notes.md)",
+            "Edited src/main.rs and ~/.config/cag/config.toml",
+        ]));
+        assert_eq!(
+            found,
+            vec![
+                "https://github.com/iisulop/cag/issues/1",
+                "src/main.rs",
+                "./docs/notes.md",
+                "~/.config/cag/config.toml",
+            ]
+        );
+    }
+
+    #[test]
+    fn extract_configured_patterns() {
+        let extractor = Extractor::new(&[r"\b(JIRA-\d+)\b".to_string()]).unwrap();
+        assert_eq!(
+            extractor.extract(lines(&["Fixes JIRA-123"])),
+            vec!["JIRA-123"]
+        );
+        assert!(Extractor::new(&["(".to_string()]).is_err());
+    }
+}
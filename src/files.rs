@@ -0,0 +1,174 @@
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use crate::storage::Storage;
+
+/// Where a file was left when switching away from it, to come back to.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Place {
+    /// Buffer line at the top of the screen.
+    pub line: usize,
+    /// Term of the search, if any.
+    pub search: Option<String>,
+    /// First line of the outermost context of the top line, like `commit
+    /// 1a2b3c…`, and how far below it the top line is. Finds the same place in
+    /// input read again even if lines were added before it.
+    pub anchor: Option<(String, usize)>,
+}
+
+impl Place {
+    /// Buffer line of the place in `lines`, if the start of its context is
+    /// one of the lines in `range`.
+    pub fn find_anchor(&self, lines: &dyn Storage, range: Range<usize>) -> Option<usize> {
+        let (start, offset) = self.anchor.as_ref()?;
+        let line = range.into_iter().find(|line| lines.line(*line) == *start)?;
+        Some(line + offset)
+    }
+}
+
+/// Files given on the command line, paged one at a time like `less a b c`
+/// and switched between with `:n` and `:p`, each coming back where it was
+/// left.
+#[derive(Debug, Default)]
+pub struct FileList {
+    files: Vec<(PathBuf, Option<Place>)>,
+    current: usize,
+    /// Whether the pager was closed to page another file rather than quit.
+    switching: bool,
+    /// Where the input was left when it was closed to read it again.
+    again: Option<Place>,
+}
+
+impl FileList {
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        FileList {
+            files: paths.into_iter().map(|path| (path, None)).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// The file paged, if reading files.
+    pub fn current(&self) -> Option<&Path> {
+        self.files.get(self.current).map(|(path, _)| path.as_path())
+    }
+
+    /// Where the file paged was left, if it was paged before.
+    pub fn place(&self) -> Option<&Place> {
+        match &self.again {
+            Some(place) => Some(place),
+            None => self.files.get(self.current)?.1.as_ref(),
+        }
+    }
+
+    /// Leaves the input at `place` to read it again, like the output of
+    /// `--exec` run again, whether or not it is a file.
+    pub fn reload(&mut self, place: Place) {
+        self.again = Some(place);
+        self.switching = true;
+    }
+
+    /// Leaves the file paged at `place` for the next one, returning whether
+    /// there is one.
+    pub fn next(&mut self, place: Place) -> bool {
+        self.switch(self.current + 1, place)
+    }
+
+    /// Leaves the file paged at `place` for the previous one, returning
+    /// whether there is one.
+    pub fn previous(&mut self, place: Place) -> bool {
+        match self.current.checked_sub(1) {
+            Some(index) => self.switch(index, place),
+            None => false,
+        }
+    }
+
+    fn switch(&mut self, index: usize, place: Place) -> bool {
+        if index >= self.files.len() {
+            return false;
+        }
+        self.files[self.current].1 = Some(place);
+        self.current = index;
+        self.again = None;
+        self.switching = true;
+        true
+    }
+
+    /// Whether the pager was closed to page another file, resetting it for
+    /// the pager of that file.
+    pub fn take_switch(&mut self) -> bool {
+        std::mem::take(&mut self.switching)
+    }
+
+    /// Which file is paged, like `file 2/3: b.log`, if there are several.
+    pub fn status(&self) -> Option<String> {
+        let path = self.current().filter(|_| self.files.len() > 1)?;
+        Some(format!(
+            "file {}/{}: {}",
+            self.current + 1,
+            self.files.len(),
+            path.display()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::{FileList, Place};
+    use crate::storage::{LineBuffer, Storage};
+
+    #[test]
+    fn switch_between_files() {
+        let mut files = FileList::new(["a.log", "b.log"].map(PathBuf::from).to_vec());
+        assert_eq!(files.current(), Some(Path::new("a.log")));
+        assert_eq!(files.status().as_deref(), Some("file 1/2: a.log"));
+        assert!(!files.previous(Place::default()));
+        assert!(!files.take_switch());
+
+        let left = Place {
+            line: 42,
+            search: Some("error".to_string()),
+            anchor: None,
+        };
+        assert!(files.next(left.clone()));
+        assert!(files.take_switch());
+        assert!(!files.take_switch());
+        assert_eq!(files.current(), Some(Path::new("b.log")));
+        assert_eq!(files.place(), None);
+        assert!(!files.next(Place::default()));
+
+        assert!(files.previous(Place::default()));
+        assert_eq!(files.place(), Some(&left));
+        assert_eq!(files.place(), Some(&left));
+
+        // Back where it was left, and not in the file switched to next
+        files.reload(left.clone());
+        assert!(files.take_switch());
+        assert_eq!(files.current(), Some(Path::new("a.log")));
+        assert_eq!(files.place(), Some(&left));
+        assert!(files.next(Place::default()));
+        assert_eq!(files.place(), Some(&Place::default()));
+
+        // Standard input and single files aren't numbered
+        assert_eq!(FileList::new(Vec::new()).status(), None);
+        assert_eq!(FileList::new(vec![PathBuf::from("a")]).status(), None);
+    }
+
+    #[test]
+    fn find_anchor_in_lines_read_again() {
+        let place = Place {
+            line: 1,
+            search: None,
+            anchor: Some(("commit 1a2b".to_string(), 2)),
+        };
+        let mut lines = LineBuffer::new(None);
+        let read = ["commit 3c4d", "new", "commit 1a2b", "Author", ""];
+        lines.extend(read.map(String::from).to_vec()).unwrap();
+        assert_eq!(place.find_anchor(&lines, 0..lines.len()), Some(4));
+        assert_eq!(place.find_anchor(&lines, 0..2), None);
+        assert_eq!(Place::default().find_anchor(&lines, 0..lines.len()), None);
+    }
+}
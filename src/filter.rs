@@ -0,0 +1,96 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+    thread,
+};
+
+use serde::Deserialize;
+use tracing::trace;
+
+use crate::error::Error;
+
+/// An external command bound to `!` and a key, showing what it makes of
+/// some of the lines in a new tab.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FilterBinding {
+    pub key: char,
+    /// Shell command reading the lines from stdin, like `jq .`.
+    pub command: String,
+    #[serde(default)]
+    pub input: FilterInput,
+}
+
+/// Which lines a filter is given.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterInput {
+    /// The lines on screen.
+    #[default]
+    Screen,
+    /// The context shown at the top of the screen.
+    Context,
+}
+
+/// Runs `command` with the shell, feeding it `lines`, and returns the lines
+/// it prints.
+pub fn run(
+    command: &str,
+    lines: impl IntoIterator<Item = impl AsRef<str>>,
+) -> Result<Vec<String>, Error> {
+    trace!("Running filter {command}");
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| Error::FilterRun(command.to_string(), err))?;
+    let mut input = String::new();
+    for line in lines {
+        input.push_str(line.as_ref());
+        input.push('\n');
+    }
+    // Write on another thread so a filter printing a lot before reading all
+    // of its input can't block both ends
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = thread::spawn(move || {
+        // A filter is free to stop reading early
+        let _ = stdin.write_all(input.as_bytes());
+    });
+    let output = child
+        .wait_with_output()
+        .map_err(|err| Error::FilterRun(command.to_string(), err))?;
+    let _ = writer.join();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr
+            .lines()
+            .next()
+            .map_or_else(|| output.status.to_string(), str::to_string);
+        return Err(Error::FilterFailed(command.to_string(), reason));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::run;
+    use crate::error::Error;
+
+    #[test]
+    fn filter_lines() {
+        assert_eq!(
+            run("tr a-z A-Z | sort", ["b", "a"]).unwrap(),
+            vec!["A".to_string(), "B".to_string()]
+        );
+        assert!(matches!(
+            run("echo broken >&2; exit 3", [""; 0]),
+            Err(Error::FilterFailed(_, reason)) if reason == "broken"
+        ));
+    }
+}
@@ -0,0 +1,306 @@
+use regex::Regex;
+
+use crate::{context_finder::InputType, storage::Storage};
+
+/// Extension point for input types to annotate each line with a short label,
+/// like its log level or the commit it belongs to, shown in a gutter to the
+/// left of the line.
+pub trait Gutter {
+    /// Width of the gutter in columns, excluding the separating space.
+    fn width(&self) -> usize;
+
+    /// Annotations for the given buffer line numbers, one per line.
+    fn annotate(&self, lines: &dyn Storage, visible: &[usize]) -> Vec<Option<String>>;
+}
+
+/// The gutter used for an input type, if it has one.
+pub fn for_input_type(input_type: InputType) -> Option<Box<dyn Gutter>> {
+    match input_type {
+        InputType::Git => Some(Box::new(CommitHash::new())),
+        InputType::Journald => Some(Box::new(RegexLabel::new(
+            r"^\S+ +\d+ [0-9:]+ \S+ [^\[:]+\[(\d+)\]:",
+            7,
+        ))),
+        InputType::Syslog
+        | InputType::Json
+        | InputType::Strace
+        | InputType::Pytest
+        | InputType::Gcc
+        | InputType::JavaStacktrace
+        | InputType::Mailbox
+        | InputType::Cargo
+        | InputType::Markdown
+        | InputType::Outline => None,
+    }
+}
+
+/// Log level gutter for inputs without a dedicated one, like merged logs.
+pub fn log_level() -> Box<dyn Gutter> {
+    Box::new(RegexLabel::new(
+        r"\b(TRACE|DEBUG|INFO|WARN|ERROR|FATAL)\b",
+        5,
+    ))
+}
+
+/// Labels each line with the first capture group of a regex.
+struct RegexLabel {
+    pattern: Regex,
+    width: usize,
+}
+
+impl RegexLabel {
+    fn new(pattern: &str, width: usize) -> Self {
+        RegexLabel {
+            pattern: Regex::new(pattern).unwrap(),
+            width,
+        }
+    }
+}
+
+impl Gutter for RegexLabel {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn annotate(&self, lines: &dyn Storage, visible: &[usize]) -> Vec<Option<String>> {
+        visible
+            .iter()
+            .map(|&line| {
+                self.pattern
+                    .captures(&lines.line(line))
+                    .map(|captures| captures[1].to_string())
+            })
+            .collect()
+    }
+}
+
+/// Labels each line with the short hash of the commit it belongs to.
+struct CommitHash {
+    commit: Regex,
+}
+
+impl CommitHash {
+    const SHORT_HASH: usize = 7;
+
+    fn new() -> Self {
+        CommitHash {
+            commit: Regex::new(r"^commit ([0-9a-fA-F]{40})").unwrap(),
+        }
+    }
+
+    fn hash(&self, line: &str) -> Option<String> {
+        self.commit
+            .captures(line)
+            .and_then(|captures| captures.get(1))
+            .map(|hash| hash.as_str()[..Self::SHORT_HASH].to_string())
+    }
+}
+
+impl Gutter for CommitHash {
+    fn width(&self) -> usize {
+        Self::SHORT_HASH
+    }
+
+    fn annotate(&self, lines: &dyn Storage, visible: &[usize]) -> Vec<Option<String>> {
+        let mut previous: Option<usize> = None;
+        let mut current: Option<String> = None;
+        visible
+            .iter()
+            .map(|&line| {
+                current = match previous {
+                    // Walking forward, only a new header changes the commit
+                    Some(previous) if previous + 1 == line => {
+                        self.hash(&lines.line(line)).or(current.take())
+                    }
+                    _ => lines
+                        .iter(0..line + 1)
+                        .rev()
+                        .find_map(|line| self.hash(&line)),
+                };
+                previous = Some(line);
+                current.clone()
+            })
+            .collect()
+    }
+}
+
+/// Days before the first of each month in a non-leap year.
+const MONTH_STARTS: [u32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+/// How far back to look for the previous timestamped line.
+const MAX_LOOKBACK: usize = 1000;
+
+/// Labels each timestamped line with the time elapsed since the previous
+/// timestamped line, or since an anchor line if one is chosen.
+pub struct TimeDelta {
+    iso: Regex,
+    syslog: Regex,
+    /// Line to measure from instead of the previous one.
+    pub anchor: Option<usize>,
+}
+
+impl TimeDelta {
+    pub fn new() -> Self {
+        TimeDelta {
+            iso: Regex::new(
+                r"^\[?(\d{4})-(\d{2})-(\d{2})[T ](?P<time>\d{2}:\d{2}:\d{2}(?:[.,]\d+)?)",
+            )
+            .unwrap(),
+            syslog: Regex::new(r"^([A-Z][a-z]{2}) +(\d{1,2}) (?P<time>\d{2}:\d{2}:\d{2})").unwrap(),
+            anchor: None,
+        }
+    }
+
+    /// Seconds since some fixed point for the timestamp at the start of
+    /// `line`. Syslog timestamps have no year, so deltas across New Year are
+    /// wrong.
+    pub fn seconds(&self, line: &str) -> Option<f64> {
+        let (days, time) = if let Some(captures) = self.iso.captures(line) {
+            let year: i64 = captures[1].parse().ok()?;
+            let month: u32 = captures[2].parse().ok()?;
+            let day: u32 = captures[3].parse().ok()?;
+            (days_from_civil(year, month, day), captures)
+        } else {
+            let captures = self.syslog.captures(line)?;
+            let month = MONTHS.iter().position(|month| *month == &captures[1])?;
+            let day: u32 = captures[2].parse().ok()?;
+            ((MONTH_STARTS[month] + day) as i64, captures)
+        };
+        let time = time["time"].replace(',', ".");
+        let mut parts = time.split(':').map(|part| part.parse::<f64>().ok());
+        let (hours, minutes, seconds) = (parts.next()??, parts.next()??, parts.next()??);
+        Some(days as f64 * 86400.0 + hours * 3600.0 + minutes * 60.0 + seconds)
+    }
+}
+
+impl Gutter for TimeDelta {
+    fn width(&self) -> usize {
+        8
+    }
+
+    fn annotate(&self, lines: &dyn Storage, visible: &[usize]) -> Vec<Option<String>> {
+        let anchor = self
+            .anchor
+            .and_then(|anchor| self.seconds(&lines.line(anchor)));
+        visible
+            .iter()
+            .map(|&line| {
+                let time = self.seconds(&lines.line(line))?;
+                let since = match (self.anchor, anchor) {
+                    (Some(_), since) => since?,
+                    (None, _) => lines
+                        .iter(line.saturating_sub(MAX_LOOKBACK)..line)
+                        .rev()
+                        .find_map(|line| self.seconds(&line))?,
+                };
+                Some(format_delta(time - since))
+            })
+            .collect()
+    }
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Short signed duration like `+0.250s`, `+3m04s` or `-2h05m`.
+fn format_delta(seconds: f64) -> String {
+    let sign = if seconds < 0.0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    let whole = seconds as u64;
+    match whole {
+        0..=59 => format!("{sign}{seconds:.3}s"),
+        60..=3599 => format!("{sign}{}m{:02}s", whole / 60, whole % 60),
+        _ => format!("{sign}{}h{:02}m", whole / 3600, whole / 60 % 60),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{days_from_civil, for_input_type, format_delta, log_level, Gutter, TimeDelta};
+    use crate::context_finder::InputType;
+
+    pub const GIT_LOG: &str = include_str!("../tests/data/git_patch");
+    pub const JOURNALD_LOG: &str = include_str!("../tests/data/journald");
+
+    fn lines(input: &str) -> Vec<String> {
+        input.lines().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn annotate_commit_hashes() {
+        let input = lines(GIT_LOG);
+        let gutter = for_input_type(InputType::Git).unwrap();
+        let annotations = gutter.annotate(&input, &[0, 10, 177, 178, 179, 306]);
+        let annotations: Vec<&str> = annotations.iter().flatten().map(|a| a.as_str()).collect();
+        assert_eq!(
+            annotations,
+            vec!["b8e882d", "b8e882d", "b8e882d", "96f57fb", "96f57fb", "2db54fe"]
+        );
+    }
+
+    #[test]
+    fn annotate_journald_pids() {
+        let input = lines(JOURNALD_LOG);
+        let gutter = for_input_type(InputType::Journald).unwrap();
+        let annotations = gutter.annotate(&input, &[1, 5, 10]);
+        assert_eq!(
+            annotations,
+            vec![None, Some("1".to_string()), Some("401".to_string())]
+        );
+    }
+
+    #[test]
+    fn annotate_log_levels() {
+        let input = lines("2023-01-01 10:00:00 INFO start\ncontinued\n[ERROR] failed");
+        let annotations = log_level().annotate(&input, &[0, 1, 2]);
+        assert_eq!(
+            annotations,
+            vec![Some("INFO".to_string()), None, Some("ERROR".to_string())]
+        );
+    }
+
+    #[test]
+    fn annotate_time_deltas() {
+        let input = lines(
+            "2023-02-28T23:59:59.5 start\n  at main.rs:10\n2023-03-01 00:00:00,0 next\n\
+             2023-03-01 00:05:00 later",
+        );
+        let mut gutter = TimeDelta::new();
+        assert_eq!(
+            gutter.annotate(&input, &[0, 1, 2, 3]),
+            vec![
+                None,
+                None,
+                Some("+0.500s".to_string()),
+                Some("+5m00s".to_string())
+            ]
+        );
+        gutter.anchor = Some(3);
+        assert_eq!(
+            gutter.annotate(&input, &[0, 3]),
+            vec![Some("-5m00s".to_string()), Some("+0.000s".to_string())]
+        );
+        let syslog = lines("Apr 30 23:00:00 host a: x\nMay  1 01:30:00 host a: y");
+        assert_eq!(
+            TimeDelta::new().annotate(&syslog, &[1]),
+            vec![Some("+2h30m".to_string())]
+        );
+    }
+
+    #[test]
+    fn civil_days() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(format_delta(-0.25), "-0.250s");
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{context_finder::diff_path, extract::Extractor};
+
+/// Something in the context panel that can be focused with Tab and acted on
+/// with Enter, as a discoverable alternative to keys like `y` and `e`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderItem {
+    /// Hash of the commit, copied.
+    Hash(String),
+    /// Author of the commit, copied.
+    Author(String),
+    /// Path of a changed file, opened in the editor.
+    Path(String),
+    /// URL or path found by the extractor, opened.
+    Link(String),
+    /// Indicator of the context lines cut to fit the panel, shown whole in
+    /// an overlay.
+    More(String),
+}
+
+/// Indicator shown in place of the `count` context lines that didn't fit.
+pub fn more_lines(count: usize) -> String {
+    format!("(+{count} more lines)")
+}
+
+impl HeaderItem {
+    /// The text of the item as it appears in the context.
+    pub fn text(&self) -> &str {
+        match self {
+            HeaderItem::Hash(text)
+            | HeaderItem::Author(text)
+            | HeaderItem::Path(text)
+            | HeaderItem::Link(text)
+            | HeaderItem::More(text) => text,
+        }
+    }
+
+    /// What Enter does with the item.
+    pub fn action(&self) -> &'static str {
+        match self {
+            HeaderItem::Hash(_) | HeaderItem::Author(_) => "copy",
+            HeaderItem::Path(_) => "edit",
+            HeaderItem::Link(_) => "open",
+            HeaderItem::More(_) => "show",
+        }
+    }
+}
+
+/// The items in the context `lines`, in the order they appear.
+pub fn items<'a>(
+    lines: impl IntoIterator<Item = &'a String>,
+    extractor: &Extractor,
+) -> Vec<HeaderItem> {
+    let lines: Vec<&String> = lines.into_iter().collect();
+    let mut items: Vec<HeaderItem> = Vec::new();
+    for line in &lines {
+        let item = if let Some(hash) = line.strip_prefix("commit ") {
+            hash.split_whitespace()
+                .next()
+                .map(|hash| HeaderItem::Hash(hash.to_string()))
+        } else if let Some(author) = line.strip_prefix("Author:") {
+            Some(HeaderItem::Author(author.trim().to_string()))
+        } else if line.starts_with("diff --git ") {
+            diff_path(line).map(|path| HeaderItem::Path(path.to_string()))
+        } else {
+            None
+        };
+        items.extend(item.filter(|item| !items.contains(item)));
+    }
+    for link in extractor.extract(lines) {
+        // The extractor also finds the paths of a diff header, with their
+        // `a/` and `b/` or other prefixes
+        let is_path = |item: &HeaderItem| {
+            matches!(item, HeaderItem::Path(_))
+                && link.strip_suffix(item.text()).is_some_and(|prefix| {
+                    prefix.is_empty() || prefix.find('/') == Some(prefix.len() - 1)
+                })
+        };
+        if !items
+            .iter()
+            .any(|item| item.text() == link || is_path(item))
+        {
+            items.push(HeaderItem::Link(link));
+        }
+    }
+    items
+}
+
+#[cfg(test)]
+mod test {
+    use super::{items, HeaderItem};
+    use crate::extract::Extractor;
+
+    #[test]
+    fn find_items_in_context() {
+        let context: Vec<String> = [
+            "commit b8e882d50a8e2f184e8803a18818da18dbbd1469",
+            "Author: Mr. Example <mr@example.com>",
+            "Date:   Wed Apr 12 17:49:27 2023 +0300",
+            "",
+            "    Read stdin, see https://example.com/issue/1",
+            "diff --git a/src/main.rs b/src/main.rs",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+        let extractor = Extractor::new(&[]).unwrap();
+        assert_eq!(
+            items(&context, &extractor),
+            vec![
+                HeaderItem::Hash("b8e882d50a8e2f184e8803a18818da18dbbd1469".to_string()),
+                HeaderItem::Author("Mr. Example <mr@example.com>".to_string()),
+                HeaderItem::Path("src/main.rs".to_string()),
+                HeaderItem::Link("https://example.com/issue/1".to_string()),
+            ]
+        );
+        assert!(items(&[], &extractor).is_empty());
+        // Whatever the prefixes of the paths
+        let header = ["diff --git i/src/main.rs w/src/main.rs".to_string()];
+        assert_eq!(
+            items(&header, &extractor),
+            vec![HeaderItem::Path("src/main.rs".to_string())]
+        );
+    }
+}
@@ -0,0 +1,54 @@
+/// Bytes shown per row of a hex dump.
+const ROW_BYTES: usize = 16;
+
+/// Formats `bytes` like `hexdump -C`: the offset, the bytes in hex and the
+/// printable ones as ASCII, with a dot for everything else.
+pub fn dump(bytes: &[u8]) -> Vec<String> {
+    if bytes.is_empty() {
+        return vec!["0000".to_string()];
+    }
+    bytes
+        .chunks(ROW_BYTES)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let hex = chunk
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let ascii: String = chunk
+                .iter()
+                .map(|&byte| {
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!(
+                "{:04x}  {hex:width$}  |{ascii}|",
+                row * ROW_BYTES,
+                width = ROW_BYTES * 3 - 1
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::dump;
+
+    #[test]
+    fn dump_rows() {
+        let rows = dump(b"binary\xff\x00 garbage here!");
+        assert_eq!(
+            rows,
+            vec![
+                "0000  62 69 6e 61 72 79 ff 00 20 67 61 72 62 61 67 65  |binary.. garbage|",
+                "0010  20 68 65 72 65 21                                | here!|",
+            ]
+        );
+        assert_eq!(dump(b""), vec!["0000"]);
+    }
+}
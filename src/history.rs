@@ -0,0 +1,172 @@
+use std::{
+    collections::VecDeque,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use tracing::trace;
+
+/// Number of searches and commands remembered, one for each of `Alt-1` to
+/// `Alt-9`.
+const MAX_RECENT: usize = 9;
+/// Number of search terms recalled with Up in the search prompt.
+const MAX_SEARCH_TERMS: usize = 200;
+/// Name of the file keeping search terms, in the data directory.
+const SEARCH_HISTORY_FILE: &str = "cag/history";
+
+/// Searches, `:` commands and `&` filters run recently, most recent first,
+/// listed while typing a new one and re-run with `Alt` and their number.
+#[derive(Debug, Default)]
+pub struct History {
+    /// Entries as typed, with their prompt, like `/error` or `:sort`.
+    entries: VecDeque<String>,
+}
+
+impl History {
+    /// Remembers `entry` as the most recent, moving it up if it was run
+    /// before.
+    pub fn push(&mut self, entry: String) {
+        self.entries.retain(|recent| *recent != entry);
+        self.entries.push_front(entry);
+        self.entries.truncate(MAX_RECENT);
+    }
+
+    /// The entry bound to `Alt` and `number`, counting from 1.
+    pub fn get(&self, number: usize) -> Option<&str> {
+        self.entries.get(number.checked_sub(1)?).map(String::as_str)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(String::as_str)
+    }
+}
+
+/// Search terms typed, most recent first, recalled with Up and Down in the
+/// search prompt like less does. Kept in a file across sessions if loaded
+/// from one.
+#[derive(Debug, Default)]
+pub struct SearchHistory {
+    terms: VecDeque<String>,
+    path: Option<PathBuf>,
+}
+
+impl SearchHistory {
+    /// Reads the terms kept in `path`, one per line and the most recent
+    /// last. A missing file is an empty history.
+    pub fn load(path: PathBuf) -> io::Result<Self> {
+        trace!("Loading search history from {path:?}");
+        let terms = match fs::read_to_string(&path) {
+            Ok(contents) => contents.lines().rev().map(str::to_string).collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => VecDeque::new(),
+            Err(err) => return Err(err),
+        };
+        Ok(SearchHistory {
+            terms,
+            path: Some(path),
+        })
+    }
+
+    /// Remembers `term` as the most recent, moving it up if it was searched
+    /// before, and saves the history if it is kept in a file.
+    pub fn push(&mut self, term: &str) -> io::Result<()> {
+        self.terms.retain(|recent| recent != term);
+        self.terms.push_front(term.to_string());
+        self.terms.truncate(MAX_SEARCH_TERMS);
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut contents = String::new();
+        for term in self.terms.iter().rev() {
+            contents.push_str(term);
+            contents.push('\n');
+        }
+        fs::write(path, contents)
+    }
+
+    /// The term searched `back` searches ago, 0 being the last one.
+    pub fn recall(&self, back: usize) -> Option<&str> {
+        self.terms.get(back).map(String::as_str)
+    }
+}
+
+/// Where the search history is kept when configured to be:
+/// `$XDG_DATA_HOME/cag/history`, or `~/.local/share/cag/history`.
+pub fn search_history_path() -> Option<PathBuf> {
+    let data_dir = env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(data_dir.join(SEARCH_HISTORY_FILE))
+}
+
+/// Key presses typing `entry` at the pager and running it.
+pub fn keys(entry: &str) -> impl Iterator<Item = KeyEvent> + '_ {
+    entry
+        .chars()
+        .map(KeyCode::Char)
+        .chain([KeyCode::Enter])
+        .map(KeyEvent::from)
+}
+
+#[cfg(test)]
+mod test {
+    use crossterm::event::{KeyCode, KeyEvent};
+
+    use super::{keys, History, SearchHistory, MAX_RECENT};
+
+    #[test]
+    fn most_recent_first() {
+        let mut history = History::default();
+        for entry in ["/error", ":sort", "&warn", "/error"] {
+            history.push(entry.to_string());
+        }
+        assert_eq!(
+            history.entries().collect::<Vec<_>>(),
+            ["/error", "&warn", ":sort"]
+        );
+        assert_eq!(history.get(2), Some("&warn"));
+        assert_eq!(history.get(0), None);
+        for number in 0..20 {
+            history.push(format!("/{number}"));
+        }
+        assert_eq!(history.entries().count(), MAX_RECENT);
+        assert_eq!(history.get(1), Some("/19"));
+    }
+
+    #[test]
+    fn keep_search_terms() {
+        let path = std::env::temp_dir().join(format!("cag-history-{}/history", std::process::id()));
+        let mut history = SearchHistory::load(path.clone()).unwrap();
+        assert_eq!(history.recall(0), None);
+        for term in ["error", "warn", "error", "panic"] {
+            history.push(term).unwrap();
+        }
+        assert_eq!(history.recall(0), Some("panic"));
+        assert_eq!(history.recall(1), Some("error"));
+        assert_eq!(history.recall(3), None);
+        // Read back in the next session
+        let history = SearchHistory::load(path.clone()).unwrap();
+        assert_eq!(history.recall(2), Some("warn"));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn replay_keys() {
+        assert_eq!(
+            keys(":uniq").map(|key| key.code).collect::<Vec<_>>(),
+            [':', 'u', 'n', 'i', 'q']
+                .into_iter()
+                .map(KeyCode::Char)
+                .chain([KeyCode::Enter])
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            keys("").collect::<Vec<_>>(),
+            [KeyEvent::from(KeyCode::Enter)]
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use crate::{gutter::Gutter, storage::Storage};
+
+/// Hunks of a diff picked with Space, to be staged or written out on quit
+/// like with `git add -p`.
+#[derive(Debug, Default)]
+pub struct HunkPicker {
+    /// Buffer lines of the picked hunks, from their `@@` line, by start,
+    /// with the first line of the header of their file.
+    picked: BTreeMap<usize, (usize, usize)>,
+}
+
+impl HunkPicker {
+    /// Picks the hunk containing `line`, or the first one after it, or
+    /// unpicks it if it was picked. Returns the hunk and whether it is now
+    /// picked, or `None` if there is no hunk there.
+    pub fn toggle(&mut self, lines: &dyn Storage, line: usize) -> Option<(Range<usize>, bool)> {
+        let hunk = hunk(lines, line).or_else(|| {
+            let next = (line..lines.len()).find(|line| lines.line(*line).starts_with("@@ "))?;
+            hunk(lines, next)
+        })?;
+        let picked = self.picked.remove(&hunk.start).is_none();
+        if picked {
+            let header = file_header(lines, hunk.start).map_or(hunk.start, |header| header.start);
+            self.picked.insert(hunk.start, (hunk.end, header));
+        }
+        Some((hunk, picked))
+    }
+
+    pub fn len(&self) -> usize {
+        self.picked.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.picked.is_empty()
+    }
+
+    /// Numbers the lines from zero again after the first `lines` are
+    /// dropped, unpicking the hunks whose file header was dropped as they
+    /// can no longer be applied.
+    pub fn discard(&mut self, lines: usize) {
+        self.picked = std::mem::take(&mut self.picked)
+            .into_iter()
+            .filter(|(_, (_, header))| *header >= lines)
+            .map(|(start, (end, header))| (start - lines, (end - lines, header - lines)))
+            .collect();
+    }
+
+    fn is_picked(&self, line: usize) -> bool {
+        self.picked
+            .range(..=line)
+            .next_back()
+            .is_some_and(|(_, (end, _))| line < *end)
+    }
+
+    /// The picked hunks as a patch for `git apply`, each after the header of
+    /// its file, which is given once for the hunks of the same file.
+    pub fn patch(&self, lines: &dyn Storage) -> Vec<String> {
+        let mut patch = Vec::new();
+        let mut last_header = None;
+        for (start, (end, _)) in &self.picked {
+            if let Some(header) = file_header(lines, *start) {
+                if last_header.as_ref() != Some(&header) {
+                    patch.extend(lines.iter(header.clone()).map(|line| line.into_owned()));
+                    last_header = Some(header);
+                }
+            }
+            patch.extend(lines.iter(*start..*end).map(|line| line.into_owned()));
+        }
+        patch
+    }
+}
+
+impl Gutter for HunkPicker {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn annotate(&self, _lines: &dyn Storage, visible: &[usize]) -> Vec<Option<String>> {
+        visible
+            .iter()
+            .map(|line| self.is_picked(*line).then(|| "*".to_string()))
+            .collect()
+    }
+}
+
+/// Numbers of old and new lines of a hunk from its header, like
+/// `@@ -3,22 +3,16 @@`, where a missing count means one line.
+fn hunk_counts(header: &str) -> Option<(usize, usize)> {
+    let ranges = header.strip_prefix("@@ -")?;
+    let (old, rest) = ranges.split_once(" +")?;
+    let (new, _) = rest.split_once(" @@")?;
+    let count = |range: &str| match range.split_once(',') {
+        Some((_, count)) => count.parse().ok(),
+        None => Some(1),
+    };
+    Some((count(old)?, count(new)?))
+}
+
+/// Buffer lines of the hunk containing `line`, from its `@@` line to its last
+/// line as counted in the header.
+pub fn hunk(lines: &dyn Storage, line: usize) -> Option<Range<usize>> {
+    if line >= lines.len() {
+        return None;
+    }
+    // Only lines of a hunk can be passed on the way up to its header
+    let start = (0..=line).rev().find(|index| {
+        let line = lines.line(*index);
+        line.starts_with("@@ ") || !line.starts_with([' ', '+', '-', '\\'])
+    })?;
+    let (mut old, mut new) = hunk_counts(&lines.line(start))?;
+    let mut end = start + 1;
+    while end < lines.len() {
+        let body = lines.line(end);
+        match body.chars().next() {
+            Some(' ') if old > 0 && new > 0 => {
+                old -= 1;
+                new -= 1;
+            }
+            Some('-') if old > 0 => old -= 1,
+            Some('+') if new > 0 => new -= 1,
+            // `\ No newline at end of file`
+            Some('\\') => (),
+            _ => break,
+        }
+        end += 1;
+    }
+    (line < end).then_some(start..end)
+}
+
+/// Buffer lines of the header of the file of the hunk starting at `start`,
+/// from its `diff` line, or its `---` line without one, to its `+++` line.
+fn file_header(lines: &dyn Storage, start: usize) -> Option<Range<usize>> {
+    let end = (1..start).rev().find(|index| {
+        lines.line(*index).starts_with("+++ ")
+            && lines.line(index - 1).starts_with("--- ")
+            && lines.line(index + 1).starts_with("@@ ")
+    })?;
+    let is_header = |line: &str| {
+        [
+            "diff ",
+            "index ",
+            "old mode ",
+            "new mode ",
+            "similarity index ",
+        ]
+        .iter()
+        .chain(&[
+            "rename from ",
+            "rename to ",
+            "new file mode ",
+            "deleted file mode ",
+        ])
+        .any(|prefix| line.starts_with(prefix))
+    };
+    let mut first = end - 1;
+    while first > 0 && is_header(&lines.line(first - 1)) {
+        first -= 1;
+    }
+    Some(first..end + 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{hunk, HunkPicker};
+    use crate::gutter::Gutter;
+
+    const GIT_LOG: &str = include_str!("../tests/data/git_patch");
+
+    #[test]
+    fn find_hunks() {
+        let input: Vec<String> = GIT_LOG.lines().map(String::from).collect();
+        assert_eq!(hunk(&input, 10), Some(10..18));
+        assert_eq!(hunk(&input, 17), Some(10..18));
+        // Counted from `@@ -3,22 +3,16 @@` up to the next hunk
+        assert_eq!(hunk(&input, 30), Some(22..52));
+        assert_eq!(hunk(&input, 52), Some(52..177));
+        // Commit metadata and file headers aren't in any hunk
+        assert_eq!(hunk(&input, 0), None);
+        assert_eq!(hunk(&input, 9), None);
+        assert_eq!(hunk(&input, 18), None);
+    }
+
+    #[test]
+    fn pick_hunks() {
+        let input: Vec<String> = GIT_LOG.lines().map(String::from).collect();
+        let mut picker = HunkPicker::default();
+        // The first hunk after the commit metadata is picked
+        assert_eq!(picker.toggle(&input, 0), Some((10..18, true)));
+        assert_eq!(picker.toggle(&input, 52), Some((52..177, true)));
+        assert_eq!(picker.len(), 2);
+        assert_eq!(
+            picker.annotate(&input, &[9, 10, 17, 18, 52]),
+            [
+                None,
+                Some("*".to_string()),
+                Some("*".to_string()),
+                None,
+                Some("*".to_string())
+            ]
+        );
+        // The header of src/main.rs is given for its second hunk
+        let patch = picker.patch(&input);
+        assert_eq!(patch[..12], input[6..18]);
+        assert_eq!(patch[12..16], input[18..22]);
+        assert_eq!(patch[16..], input[52..177]);
+
+        assert_eq!(picker.toggle(&input, 12), Some((10..18, false)));
+        assert_eq!(picker.toggle(&input, 60), Some((52..177, false)));
+        assert!(picker.is_empty());
+        assert!(picker.patch(&input).is_empty());
+    }
+
+    #[test]
+    fn discard_picked_hunks() {
+        let input: Vec<String> = GIT_LOG.lines().map(String::from).collect();
+        let mut picker = HunkPicker::default();
+        picker.toggle(&input, 10);
+        picker.toggle(&input, 52);
+        // The header of the first hunk, from line 6, is dropped with it
+        picker.discard(7);
+        assert_eq!(picker.len(), 1);
+        let kept = input[7..].to_vec();
+        let patch = picker.patch(&kept);
+        assert_eq!(patch[..4], input[18..22]);
+        assert_eq!(patch[4..], input[52..177]);
+        assert_eq!(
+            picker.annotate(&kept, &[44, 45]),
+            [None, Some("*".to_string())]
+        );
+        picker.discard(input.len() - 7);
+        assert!(picker.is_empty());
+    }
+}
@@ -0,0 +1,381 @@
+use std::{
+    borrow::Cow,
+    io::{self, ErrorKind, Read},
+    path::PathBuf,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use tokio::{
+    fs::File,
+    io::{stdin, AsyncBufReadExt, AsyncRead, BufReader, ReadBuf},
+    runtime::{Builder, Runtime},
+    sync::mpsc::{channel, unbounded_channel, Receiver, UnboundedReceiver, UnboundedSender},
+    task::{spawn_blocking, JoinHandle},
+};
+use tracing::{trace, warn};
+
+#[cfg(unix)]
+use tokio::net::UnixStream;
+
+use crate::{clipboard, error::Error};
+
+/// How long to back off when a non-blocking source has no data available.
+const WOULD_BLOCK_BACKOFF: u64 = 10;
+/// How often a paused reader checks whether it may continue.
+const PAUSE_POLL: u64 = 50;
+/// Bytes read from the input at a time, the most a pipe hands over at once.
+const READ_BUFFER: usize = 64 * 1024;
+
+pub type InputReceiver = UnboundedReceiver<Result<Chunk, Error>>;
+type InputSender = UnboundedSender<Result<Chunk, Error>>;
+
+/// The runtime the inputs are read on, started when first needed and left
+/// running until cag exits, so a read blocked on a silent terminal or pipe
+/// never holds up quitting.
+pub fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        trace!("Starting input runtime");
+        Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("cag-input")
+            .enable_all()
+            .build()
+            .expect("Failed to start the input runtime")
+    })
+}
+
+/// A batch of lines read from the input.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub lines: Vec<String>,
+    /// Index of the merged input each line came from, empty unless merging.
+    pub sources: Vec<usize>,
+    /// Original bytes of the lines that weren't valid UTF-8, by index in
+    /// `lines`, for the hex view. Not kept when merging.
+    pub raw: Vec<(usize, Vec<u8>)>,
+}
+
+impl From<Vec<String>> for Chunk {
+    fn from(lines: Vec<String>) -> Self {
+        Chunk {
+            lines,
+            ..Default::default()
+        }
+    }
+}
+
+impl Chunk {
+    /// Adds a line read from the input, keeping its original bytes if they
+    /// aren't valid UTF-8.
+    fn push_bytes(&mut self, bytes: &[u8]) {
+        match String::from_utf8_lossy(bytes) {
+            Cow::Borrowed(line) => self.lines.push(line.to_string()),
+            Cow::Owned(line) => {
+                self.raw.push((self.lines.len(), bytes.to_vec()));
+                self.lines.push(line);
+            }
+        }
+    }
+
+    /// Adds the lines of a later chunk.
+    pub fn append(&mut self, other: Chunk) {
+        let start = self.lines.len();
+        self.lines.extend(other.lines);
+        self.sources.extend(other.sources);
+        self.raw.extend(
+            other
+                .raw
+                .into_iter()
+                .map(|(line, bytes)| (start + line, bytes)),
+        );
+    }
+}
+
+/// Lets the pager stop reading the input for a while. The producer then
+/// blocks on a full pipe instead of the buffer growing.
+#[derive(Debug, Clone, Default)]
+pub struct Pause(Arc<AtomicBool>);
+
+impl Pause {
+    /// Pauses or resumes reading, returning whether it is now paused.
+    pub fn toggle(&self) -> bool {
+        !self.0.fetch_xor(true, Ordering::Relaxed)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    async fn wait(&self) {
+        while self.is_paused() {
+            tokio::time::sleep(Duration::from_millis(PAUSE_POLL)).await;
+        }
+    }
+}
+
+/// Where the pager reads its input from.
+///
+/// Sources are only ever read sequentially so named pipes and process
+/// substitutions (`cag <(git log -p)`) work the same as regular files.
+#[derive(Debug, Clone)]
+pub enum InputSource {
+    Stdin,
+    Path(PathBuf),
+    /// Unix socket of a `cag --serve` to attach to.
+    Socket(PathBuf),
+    /// The system clipboard.
+    Clipboard,
+}
+
+impl InputSource {
+    async fn open(&self) -> io::Result<Box<dyn AsyncRead + Send + Unpin>> {
+        match self {
+            InputSource::Stdin => Ok(Box::new(stdin())),
+            InputSource::Path(path) => Ok(Box::new(File::open(path).await?)),
+            InputSource::Clipboard => Ok(Box::new(tokio::process::ChildStdout::from_std(
+                clipboard::paste()?,
+            )?)),
+            #[cfg(unix)]
+            InputSource::Socket(path) => Ok(Box::new(UnixStream::connect(path).await?)),
+            #[cfg(not(unix))]
+            InputSource::Socket(_) => Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "attaching is only supported on unix",
+            )),
+        }
+    }
+}
+
+/// Reads `source` on the input runtime and sends its lines in chunks.
+///
+/// A chunk is sent whenever reading further would have to wait for the
+/// writer, or at least read again, so slow producers show up on screen as
+/// soon as they write, and a chunk never holds more than one read of the
+/// input, whatever the size of the terminal.
+pub fn stream_input(source: InputSource, pause: Pause) -> (InputReceiver, JoinHandle<()>) {
+    trace!("Opening channel for input reader");
+    let (tx, rx) = unbounded_channel();
+    let handle = runtime().spawn(async move {
+        trace!("Opening input {source:?}");
+        let input = match source.open().await {
+            Ok(input) => input,
+            Err(err) => {
+                warn!("Error opening input: {err}");
+                let _ = tx.send(Err(Error::InputRead(err)));
+                return;
+            }
+        };
+        read_lines(BufReader::with_capacity(READ_BUFFER, input), &pause, &tx).await;
+    });
+    (rx, handle)
+}
+
+/// Reads the lines of `reader`, like a pipe handed over by a program
+/// embedding the pager. Its reads block, so they are made on the runtime's
+/// blocking threads.
+pub fn stream_reader(
+    reader: Box<dyn Read + Send>,
+    pause: Pause,
+) -> (InputReceiver, JoinHandle<()>) {
+    let (tx, rx) = unbounded_channel();
+    let handle = runtime().spawn(async move {
+        let input = Blocking::new(reader);
+        read_lines(BufReader::with_capacity(READ_BUFFER, input), &pause, &tx).await;
+    });
+    (rx, handle)
+}
+
+/// A blocking reader read on a blocking thread, one buffer ahead of whoever
+/// awaits it, so that a paused pager still holds up the writer.
+struct Blocking {
+    rx: Receiver<io::Result<Vec<u8>>>,
+    /// What is left of the last buffer received.
+    pending: Vec<u8>,
+}
+
+impl Blocking {
+    fn new(mut reader: Box<dyn Read + Send>) -> Self {
+        let (tx, rx) = channel(1);
+        spawn_blocking(move || {
+            let mut buf = vec![0; READ_BUFFER];
+            loop {
+                let read = match reader.read(&mut buf) {
+                    Ok(0) => return,
+                    Ok(read) => Ok(buf[..read].to_vec()),
+                    Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                    Err(err) => Err(err),
+                };
+                let failed = read.is_err();
+                if tx.blocking_send(read).is_err() || failed {
+                    return;
+                }
+            }
+        });
+        Blocking {
+            rx,
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl AsyncRead for Blocking {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.pending.is_empty() {
+            match ready!(self.rx.poll_recv(cx)) {
+                Some(read) => self.pending = read?,
+                // Read to the end
+                None => return Poll::Ready(Ok(())),
+            }
+        }
+        let read = std::cmp::min(buf.remaining(), self.pending.len());
+        buf.put_slice(&self.pending[..read]);
+        self.pending.drain(..read);
+        Poll::Ready(Ok(()))
+    }
+}
+
+async fn read_lines<R: AsyncRead + Unpin>(
+    mut input: BufReader<R>,
+    pause: &Pause,
+    tx: &InputSender,
+) {
+    let mut lines = Chunk::default();
+    let mut buf = Vec::new();
+    loop {
+        if pause.is_paused() {
+            trace!("Input paused");
+            if !flush(&mut lines, tx) {
+                return;
+            }
+            pause.wait().await;
+        }
+        trace!("Reading line");
+        match input.read_until(b'\n', &mut buf).await {
+            Ok(0) => {
+                trace!("No new lines");
+                if !buf.is_empty() {
+                    lines.push_bytes(&buf);
+                }
+                if !lines.lines.is_empty() {
+                    let _ = tx.send(Ok(lines));
+                }
+                return;
+            }
+            Ok(_) => {
+                if buf.last() == Some(&b'\n') {
+                    buf.pop();
+                }
+                lines.push_bytes(&buf);
+                buf.clear();
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                // Keep what has been read of the current line in `buf`.
+                trace!("Input would block, backing off");
+                if !flush(&mut lines, tx) {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(WOULD_BLOCK_BACKOFF)).await;
+                continue;
+            }
+            Err(err) => {
+                warn!("Got read error streaming input: {err}");
+                // Keep everything read before the error, even half a line
+                if !buf.is_empty() {
+                    lines.push_bytes(&buf);
+                }
+                if flush(&mut lines, tx) {
+                    let _ = tx.send(Err(Error::InputRead(err)));
+                }
+                return;
+            }
+        }
+
+        if input.buffer().is_empty() && !flush(&mut lines, tx) {
+            return;
+        }
+    }
+}
+
+/// Sends the pending lines, returning `false` if the receiver has gone away.
+fn flush(lines: &mut Chunk, tx: &InputSender) -> bool {
+    if lines.lines.is_empty() {
+        return true;
+    }
+    if let Err(err) = tx.send(Ok(std::mem::take(lines))) {
+        warn!("Error sending input streaming result: {err}");
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, Read};
+
+    use tokio::{io::BufReader, sync::mpsc::unbounded_channel};
+
+    use super::{read_lines, runtime, stream_reader, Pause};
+
+    #[test]
+    fn sends_a_chunk_per_read() {
+        let (tx, mut rx) = unbounded_channel();
+        runtime().block_on(read_lines(
+            BufReader::with_capacity(4, "a\nb\nc\nd\ne".as_bytes()),
+            &Pause::default(),
+            &tx,
+        ));
+        drop(tx);
+        let chunks: Vec<Vec<String>> = std::iter::from_fn(|| rx.blocking_recv())
+            .map(|chunk| chunk.unwrap().lines)
+            .collect();
+        // The trailing line without a newline is sent too
+        assert_eq!(chunks, [vec!["a", "b"], vec!["c", "d"], vec!["e"]]);
+    }
+
+    #[test]
+    fn keeps_bytes_of_invalid_lines() {
+        let (tx, mut rx) = unbounded_channel();
+        runtime().block_on(read_lines(
+            BufReader::new(&b"ok\nbin\xff\x00ary\n"[..]),
+            &Pause::default(),
+            &tx,
+        ));
+        let chunk = rx.blocking_recv().unwrap().unwrap();
+        assert_eq!(chunk.lines, vec!["ok", "bin\u{fffd}\0ary"]);
+        assert_eq!(chunk.raw, vec![(1, b"bin\xff\x00ary".to_vec())]);
+    }
+
+    /// Reads `"a\nb"`, then fails.
+    struct Failing(bool);
+
+    impl Read for Failing {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if std::mem::replace(&mut self.0, true) {
+                return Err(io::Error::other("broken"));
+            }
+            buf[..3].copy_from_slice(b"a\nb");
+            Ok(3)
+        }
+    }
+
+    #[test]
+    fn keep_lines_read_from_a_blocking_reader_before_it_fails() {
+        let (mut rx, handle) = stream_reader(Box::new(Failing(false)), Pause::default());
+        runtime().block_on(handle).unwrap();
+        let lines = rx.blocking_recv().unwrap().unwrap().lines;
+        assert_eq!(lines, ["a", "b"]);
+        assert!(rx.blocking_recv().unwrap().is_err());
+        assert!(rx.blocking_recv().is_none());
+    }
+}
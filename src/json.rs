@@ -0,0 +1,178 @@
+use serde::Deserialize;
+use serde_json::{Deserializer, Value};
+
+/// The `[json]` section of the config: how JSON Lines logs are shown with
+/// `--type json`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JsonConfig {
+    /// Fields shown for each line, in order. A field may list alternatives
+    /// like `msg|message`, the first one present is shown. Nested fields are
+    /// written like `http.status`.
+    pub fields: Vec<String>,
+    /// Field whose value groups lines in the context panel, like
+    /// `request_id`.
+    pub group: Option<String>,
+}
+
+impl Default for JsonConfig {
+    fn default() -> Self {
+        JsonConfig {
+            // Names used by Bunyan, pino, zap, logrus and Logstash
+            fields: [
+                "time|timestamp|ts|@timestamp",
+                "level|severity|lvl",
+                "msg|message",
+            ]
+            .map(str::to_string)
+            .to_vec(),
+            group: None,
+        }
+    }
+}
+
+/// Shows JSON Lines logs, like `kubectl logs --output json` or Bunyan output,
+/// as their most useful fields only.
+#[derive(Debug, Clone)]
+pub struct JsonLog {
+    /// Alternative names of each field shown.
+    fields: Vec<Vec<String>>,
+    group: Option<String>,
+}
+
+impl JsonLog {
+    pub fn new(config: &JsonConfig) -> Self {
+        JsonLog {
+            fields: config
+                .fields
+                .iter()
+                .map(|field| field.split('|').map(str::to_string).collect())
+                .collect(),
+            group: config.group.clone(),
+        }
+    }
+
+    /// The fields of `line` separated by spaces, or `None` if it isn't a
+    /// JSON object, like a stack trace printed in between, which is shown as
+    /// is.
+    pub fn render(&self, line: &str) -> Option<String> {
+        let object = parse(line)?;
+        let shown: Vec<String> = self
+            .fields
+            .iter()
+            .filter_map(|names| names.iter().find_map(|name| field(&object, name)))
+            .collect();
+        Some(shown.join(" "))
+    }
+
+    /// Value of the group field of `line`, if it is a JSON object with one.
+    pub fn group(&self, line: &str) -> Option<String> {
+        field(&parse(line)?, self.group.as_ref()?)
+    }
+
+    /// Heading of the group starting at `line`, like
+    /// `request_id=42: 12:00:01 INFO GET /`.
+    pub fn heading(&self, line: &str) -> Option<String> {
+        let group = self.group.as_ref()?;
+        let value = self.group(line)?;
+        let rendered = self.render(line)?;
+        Some(format!("{group}={value}: {rendered}"))
+    }
+}
+
+fn parse(line: &str) -> Option<Value> {
+    let line = line.trim();
+    if !line.starts_with('{') {
+        return None;
+    }
+    serde_json::from_str(line).ok()
+}
+
+/// Field `name` of `object` as text, with strings unquoted. A name with dots
+/// is looked up as is first, as some loggers flatten nested fields.
+fn field(object: &Value, name: &str) -> Option<String> {
+    let value = object
+        .get(name)
+        .or_else(|| object.pointer(&format!("/{}", name.replace('.', "/"))))?;
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Null => None,
+        value => Some(value.to_string()),
+    }
+}
+
+/// Pretty-prints the first JSON object in `line` over several lines, keeping
+/// the text before and after it, like a log message, on the first and last
+/// line.
+pub fn pretty_print(line: &str) -> Option<Vec<String>> {
+    for (start, _) in line.match_indices('{') {
+        let mut values = Deserializer::from_str(&line[start..]).into_iter::<Value>();
+        let Some(Ok(value)) = values.next() else {
+            continue;
+        };
+        let end = start + values.byte_offset();
+        // Nothing to expand in `{}`
+        if value.as_object().is_none_or(|object| object.is_empty()) {
+            continue;
+        }
+        let mut lines: Vec<String> = serde_json::to_string_pretty(&value)
+            .ok()?
+            .lines()
+            .map(str::to_string)
+            .collect();
+        lines[0].insert_str(0, &line[..start]);
+        lines.last_mut()?.push_str(&line[end..]);
+        return Some(lines);
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::{pretty_print, JsonConfig, JsonLog};
+
+    #[test]
+    fn pretty_print_object_in_log_line() {
+        let pretty =
+            pretty_print(r#"INFO {not json} got {"id": 1, "tags": ["a"], "z": {}} in 3ms"#)
+                .unwrap();
+        assert_eq!(
+            pretty,
+            vec![
+                "INFO {not json} got {",
+                r#"  "id": 1,"#,
+                r#"  "tags": ["#,
+                r#"    "a""#,
+                "  ],",
+                r#"  "z": {}"#,
+                "} in 3ms",
+            ]
+        );
+    }
+
+    #[test]
+    fn render_json_log_fields() {
+        let log = JsonLog::new(&JsonConfig {
+            group: Some("req.id".to_string()),
+            ..JsonConfig::default()
+        });
+        let line = r#"{"level":30,"time":"12:00:01","msg":"GET /","req":{"id":"a1"},"pid":7}"#;
+        assert_eq!(log.render(line).unwrap(), "12:00:01 30 GET /");
+        assert_eq!(log.group(line).unwrap(), "a1");
+        assert_eq!(log.heading(line).unwrap(), "req.id=a1: 12:00:01 30 GET /");
+        // Alternative names, and a missing field left out
+        assert_eq!(
+            log.render(r#"{"message":"started","severity":"INFO"}"#)
+                .unwrap(),
+            "INFO started"
+        );
+        assert_eq!(log.render("    at main (index.js:1:1)"), None);
+        assert_eq!(log.group(r#"{"msg":"no request"}"#), None);
+    }
+
+    #[test]
+    fn ignore_lines_without_objects() {
+        assert_eq!(pretty_print("fn main() {}"), None);
+        assert_eq!(pretty_print(r#"["not", "an object"]"#), None);
+    }
+}
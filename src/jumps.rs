@@ -0,0 +1,93 @@
+use std::collections::VecDeque;
+
+/// Number of positions kept to jump back to.
+const MAX_JUMPS: usize = 100;
+
+/// Positions jumped away from, like vim's jumplist, to return to them with
+/// `Ctrl-o` and `Ctrl-i`. Positions are buffer line numbers so they stay valid
+/// when merged inputs are hidden or shown.
+#[derive(Debug, Default)]
+pub struct JumpList {
+    back: VecDeque<usize>,
+    forward: Vec<usize>,
+}
+
+impl JumpList {
+    /// Records jumping away from `line`, forgetting the positions that had
+    /// been jumped back from.
+    pub fn push(&mut self, line: usize) {
+        self.forward.clear();
+        if self.back.back() != Some(&line) {
+            self.back.push_back(line);
+        }
+        if self.back.len() > MAX_JUMPS {
+            self.back.pop_front();
+        }
+    }
+
+    /// Forgets the positions in the first `lines` lines of the buffer,
+    /// numbering the rest from zero again.
+    pub fn discard(&mut self, lines: usize) {
+        self.back.retain(|line| *line >= lines);
+        self.forward.retain(|line| *line >= lines);
+        for line in self.back.iter_mut().chain(&mut self.forward) {
+            *line -= lines;
+        }
+    }
+
+    /// Jumps back from `line` to the last position jumped away from.
+    pub fn back(&mut self, line: usize) -> Option<usize> {
+        let previous = self.back.pop_back()?;
+        self.forward.push(line);
+        Some(previous)
+    }
+
+    /// Undoes jumping back from the position `line` was jumped back to.
+    pub fn forward(&mut self, line: usize) -> Option<usize> {
+        let next = self.forward.pop()?;
+        self.back.push_back(line);
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{JumpList, MAX_JUMPS};
+
+    #[test]
+    fn jump_back_and_forward() {
+        let mut jumps = JumpList::default();
+        assert_eq!(jumps.back(0), None);
+        jumps.push(0);
+        jumps.push(10);
+        assert_eq!(jumps.back(20), Some(10));
+        assert_eq!(jumps.back(10), Some(0));
+        assert_eq!(jumps.back(0), None);
+        assert_eq!(jumps.forward(0), Some(10));
+        assert_eq!(jumps.forward(10), Some(20));
+        assert_eq!(jumps.forward(20), None);
+        assert_eq!(jumps.back(20), Some(10));
+        // A new jump forgets where we jumped back from
+        jumps.push(10);
+        assert_eq!(jumps.forward(30), None);
+        jumps.discard(5);
+        assert_eq!(jumps.back(25), Some(5));
+        assert_eq!(jumps.back(5), None);
+    }
+
+    #[test]
+    fn bounded_history() {
+        let mut jumps = JumpList::default();
+        for line in 0..MAX_JUMPS + 10 {
+            jumps.push(line);
+        }
+        let mut line = MAX_JUMPS + 10;
+        let mut count = 0;
+        while let Some(previous) = jumps.back(line) {
+            line = previous;
+            count += 1;
+        }
+        assert_eq!(count, MAX_JUMPS);
+        assert_eq!(line, 10);
+    }
+}
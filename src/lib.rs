@@ -0,0 +1,58 @@
+//! cag, the context aware pager. The parts reading untrusted input, the
+//! context finders, the line buffer and the parser of terminal replies, are
+//! public so that they can be fuzzed, see `fuzz/`. [`PagerBuilder`] embeds the
+//! pager in another program, and [`run`] is the `cag` command.
+
+mod about;
+mod alert;
+mod args;
+pub mod background;
+mod builder;
+mod charset;
+mod clipboard;
+mod columns;
+mod command;
+mod commits;
+mod config;
+pub mod context_finder;
+mod diff;
+pub mod error;
+mod events;
+mod exec;
+mod extract;
+mod files;
+mod filter;
+mod gutter;
+mod header;
+mod hex;
+mod history;
+mod hunks;
+mod input;
+pub mod json;
+mod jumps;
+mod merge;
+mod pager;
+mod pager_state;
+mod preprocess;
+mod rate;
+mod review;
+mod rewrite;
+mod search;
+mod server;
+mod side_by_side;
+mod snapshot;
+mod start;
+mod stats;
+pub mod storage;
+mod theme;
+mod transform;
+mod view;
+mod viewport;
+mod wrap;
+mod yank;
+
+pub use builder::{Pager, PagerBuilder};
+#[cfg(feature = "test-backend")]
+pub use pager::render;
+pub use pager::run;
+pub use pager_state::{PagerEvent, PagerState};
@@ -0,0 +1,300 @@
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use regex::Regex;
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    task::JoinHandle,
+    time::timeout,
+};
+use tracing::{trace, warn};
+
+use crate::{
+    error::Error,
+    input::{runtime, stream_input, Chunk, InputReceiver, InputSource, Pause},
+};
+
+/// How long to wait for a quiet input before emitting what is already queued
+/// from the others, possibly slightly out of order.
+const MERGE_WAIT: u64 = 200;
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Reads every path on a task of its own and interleaves their lines into one
+/// chronologically ordered stream, tagging each line with the index of the
+/// path it came from.
+pub fn merge_inputs(paths: Vec<PathBuf>, pause: Pause) -> (InputReceiver, JoinHandle<()>) {
+    trace!("Opening channel for merged input");
+    let (tx, rx) = unbounded_channel::<Result<Chunk, Error>>();
+    let handle = runtime().spawn(async move {
+        let (tagged_tx, mut tagged_rx) =
+            unbounded_channel::<(usize, Option<Result<Chunk, Error>>)>();
+        let num_sources = paths.len();
+        for (index, path) in paths.into_iter().enumerate() {
+            let (mut source_rx, _handle) = stream_input(InputSource::Path(path), pause.clone());
+            let tagged_tx = tagged_tx.clone();
+            tokio::spawn(async move {
+                while let Some(chunk) = source_rx.recv().await {
+                    if tagged_tx.send((index, Some(chunk))).is_err() {
+                        return;
+                    }
+                }
+                let _ = tagged_tx.send((index, None));
+            });
+        }
+        drop(tagged_tx);
+
+        let mut merger = Merger::new(num_sources);
+        loop {
+            let chunk = match timeout(Duration::from_millis(MERGE_WAIT), tagged_rx.recv()).await {
+                Ok(Some((index, Some(Ok(chunk))))) => {
+                    merger.push(index, chunk.lines);
+                    merger.pop_ready()
+                }
+                Ok(Some((index, Some(Err(err))))) => {
+                    warn!("Error reading merged input {index}: {err}");
+                    if tx.send(Err(err)).is_err() {
+                        return;
+                    }
+                    continue;
+                }
+                Ok(Some((index, None))) => {
+                    trace!("Merged input {index} closed");
+                    merger.close(index);
+                    merger.pop_ready()
+                }
+                Err(_elapsed) => merger.drain(),
+                Ok(None) => {
+                    send(&tx, merger.drain());
+                    return;
+                }
+            };
+            if !send(&tx, chunk) {
+                return;
+            }
+        }
+    });
+    (rx, handle)
+}
+
+/// Sends a non-empty chunk, returning `false` if the receiver has gone away.
+fn send(tx: &UnboundedSender<Result<Chunk, Error>>, chunk: Chunk) -> bool {
+    if chunk.lines.is_empty() {
+        return true;
+    }
+    if let Err(err) = tx.send(Ok(chunk)) {
+        warn!("Error sending merged input: {err}");
+        return false;
+    }
+    true
+}
+
+struct Merger {
+    iso: Regex,
+    syslog: Regex,
+    queues: Vec<VecDeque<(String, String)>>,
+    last_keys: Vec<String>,
+    open: Vec<bool>,
+    last_source: Option<usize>,
+    /// Year given to syslog timestamps, which have none: that of the last ISO
+    /// timestamp seen in any input, or the current one until there is one.
+    year: String,
+}
+
+/// The current year in UTC.
+fn current_year() -> i64 {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() / 86_400) as i64;
+    // Days to civil date, counting eras of 400 years from 0000-03-01
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    // Years start in March, so January and February belong to the next one
+    year_of_era + era * 400 + i64::from(month >= 10)
+}
+
+impl Merger {
+    fn new(num_sources: usize) -> Self {
+        Merger {
+            iso: Regex::new(r"^\[?(\d{4}-\d{2}-\d{2})[T ](\d{2}:\d{2}:\d{2}(?:[.,]\d+)?)").unwrap(),
+            syslog: Regex::new(r"^([A-Z][a-z]{2}) +(\d{1,2}) (\d{2}:\d{2}:\d{2})").unwrap(),
+            queues: vec![VecDeque::new(); num_sources],
+            last_keys: vec![String::new(); num_sources],
+            open: vec![true; num_sources],
+            last_source: None,
+            year: format!("{:04}", current_year()),
+        }
+    }
+
+    /// Returns a lexicographically sortable key for the timestamp at the start
+    /// of `line`, if there is one.
+    fn timestamp_key(&mut self, line: &str) -> Option<String> {
+        if let Some(captures) = self.iso.captures(line) {
+            self.year = captures[1][..4].to_string();
+            return Some(format!(
+                "{} {}",
+                &captures[1],
+                captures[2].replace(',', ".")
+            ));
+        }
+        let captures = self.syslog.captures(line)?;
+        let month = MONTHS.iter().position(|month| *month == &captures[1])? + 1;
+        let day: u32 = captures[2].parse().ok()?;
+        Some(format!(
+            "{}-{month:02}-{day:02} {}",
+            self.year, &captures[3]
+        ))
+    }
+
+    /// Queues lines from a source. Lines without a timestamp, like stack
+    /// traces, sort together with the last timestamped line before them.
+    fn push(&mut self, source: usize, lines: Vec<String>) {
+        for line in lines {
+            if let Some(key) = self.timestamp_key(&line) {
+                self.last_keys[source] = key;
+            }
+            self.queues[source].push_back((self.last_keys[source].clone(), line));
+        }
+    }
+
+    fn close(&mut self, source: usize) {
+        self.open[source] = false;
+    }
+
+    /// Pops lines for as long as every open source has something queued, so
+    /// that no source can still produce an earlier line.
+    fn pop_ready(&mut self) -> Chunk {
+        let mut chunk = Chunk::default();
+        while self
+            .queues
+            .iter()
+            .zip(&self.open)
+            .all(|(queue, open)| !queue.is_empty() || !open)
+        {
+            if !self.pop_into(&mut chunk) {
+                break;
+            }
+        }
+        chunk
+    }
+
+    fn drain(&mut self) -> Chunk {
+        let mut chunk = Chunk::default();
+        while self.pop_into(&mut chunk) {}
+        chunk
+    }
+
+    fn pop_into(&mut self, chunk: &mut Chunk) -> bool {
+        let mut earliest: Option<usize> = None;
+        for (source, queue) in self.queues.iter().enumerate() {
+            let Some((key, _)) = queue.front() else {
+                continue;
+            };
+            earliest = match earliest {
+                Some(best) => {
+                    let best_key = &self.queues[best][0].0;
+                    // Keep continuation lines next to their header on ties
+                    if key < best_key || (key == best_key && self.last_source == Some(source)) {
+                        Some(source)
+                    } else {
+                        Some(best)
+                    }
+                }
+                None => Some(source),
+            };
+        }
+        let Some(source) = earliest else {
+            return false;
+        };
+        let (_key, line) = self.queues[source].pop_front().unwrap();
+        self.last_source = Some(source);
+        chunk.lines.push(line);
+        chunk.sources.push(source);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Merger;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_timestamps() {
+        let mut merger = Merger::new(1);
+        assert_eq!(
+            merger.timestamp_key("2023-04-12T17:49:27,5Z INFO start"),
+            Some("2023-04-12 17:49:27.5".to_string())
+        );
+        assert_eq!(
+            merger.timestamp_key("Apr  2 17:49:27 host app[1]: start"),
+            Some("2023-04-02 17:49:27".to_string())
+        );
+        assert_eq!(merger.timestamp_key("    at main.rs:10"), None);
+    }
+
+    #[test]
+    fn interleave_by_timestamp() {
+        let mut merger = Merger::new(2);
+        merger.push(
+            0,
+            lines(&["2023-01-01 10:00:00 a1", "2023-01-01 10:00:02 a2"]),
+        );
+        merger.push(
+            1,
+            lines(&[
+                "2023-01-01 10:00:01 b1",
+                "  continued",
+                "2023-01-01 10:00:03 b2",
+            ]),
+        );
+        merger.close(0);
+        merger.close(1);
+        let chunk = merger.pop_ready();
+        assert_eq!(chunk.sources, vec![0, 1, 1, 0, 1]);
+        assert_eq!(chunk.lines[2], "  continued");
+    }
+
+    #[test]
+    fn interleave_syslog_with_iso() {
+        let mut merger = Merger::new(2);
+        merger.push(
+            0,
+            lines(&["2023-04-02 17:49:26 a1", "2023-04-02 17:49:28 a2"]),
+        );
+        merger.push(1, lines(&["Apr  2 17:49:27 host app[1]: b1"]));
+        merger.close(0);
+        merger.close(1);
+        assert_eq!(merger.pop_ready().sources, vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn syslog_in_current_year() {
+        let mut merger = Merger::new(1);
+        let key = merger.timestamp_key("Apr  2 17:49:27 host app[1]: start");
+        let year = super::current_year();
+        assert!(year >= 2023);
+        assert_eq!(key, Some(format!("{year}-04-02 17:49:27")));
+    }
+
+    #[test]
+    fn wait_for_open_sources() {
+        let mut merger = Merger::new(2);
+        merger.push(0, lines(&["2023-01-01 10:00:00 a1"]));
+        assert!(merger.pop_ready().lines.is_empty());
+        assert_eq!(merger.drain().lines, lines(&["2023-01-01 10:00:00 a1"]));
+    }
+}
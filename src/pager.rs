@@ -0,0 +1,4241 @@
+use crate::{
+    about, alert, args, background, charset, clipboard, columns, command, commits, config,
+    context_finder, diff, error, events, exec, extract, files, filter, gutter, header, hex,
+    history, hunks, input, json, jumps, merge, pager_state, preprocess, rate, review, rewrite,
+    search, server, side_by_side, snapshot, start, stats, storage, theme, transform, view,
+    viewport, wrap, yank,
+};
+
+use alert::Alerts;
+use args::Args;
+use background::Background;
+use charset::Asciify;
+use clap::{parser::ValueSource, ArgMatches, CommandFactory, FromArgMatches, ValueEnum};
+use columns::ColumnSelection;
+use command::Command;
+use commits::{Commit, CommitList};
+use config::Config;
+use context_finder::{ContextFinder, InputType};
+use crossterm::{
+    cursor::Show,
+    event::{
+        DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture, Event,
+        KeyCode, KeyEvent, KeyModifiers, KeyboardEnhancementFlags, MouseButton, MouseEvent,
+        MouseEventKind, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute,
+    terminal::{
+        self, disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+    },
+};
+use diff::{ColorMode, DiffLine};
+use error::Error;
+use events::{Waiter, Wake};
+use exec::Exec;
+use extract::Extractor;
+use files::{FileList, Place};
+use filter::FilterInput;
+use gutter::{Gutter, TimeDelta};
+use header::HeaderItem;
+use history::{History, SearchHistory};
+use hunks::HunkPicker;
+use input::{runtime, stream_input, Chunk, InputReceiver, InputSource, Pause};
+use json::JsonLog;
+use jumps::JumpList;
+use merge::merge_inputs;
+use pager_state::{current_context, PagerEvent, PagerState};
+use preprocess::Preprocessor;
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    buffer::Buffer,
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
+    style::{Modifier, Style},
+    text::{Span, Spans, Text},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph, Widget},
+    Frame, Terminal,
+};
+use rate::Throughput;
+use regex::Regex;
+use review::ReviewTimer;
+use rewrite::Rewriter;
+use search::{CaseMode, Search, SearchOptions};
+use side_by_side::Row;
+use snapshot::Snapshot;
+use start::StartAt;
+use stats::Stats;
+use std::{
+    borrow::Cow,
+    collections::{BTreeSet, HashMap, VecDeque},
+    ffi::OsString,
+    io::{self, BufRead, Read, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicI32, Ordering},
+    time::{Duration, Instant},
+};
+use storage::{LineBuffer, Storage};
+use theme::{Theme, ThemeConfig};
+use tokio::{sync::mpsc::error::TryRecvError, task::JoinHandle, time::timeout};
+use tracing::{error, trace, Level};
+use view::FilterView;
+use viewport::Viewport;
+use yank::YankFormat;
+
+/// How long `-F` waits for the input to fill a screen before paging it.
+const INPUT_STREAM_TIMEOUT: u64 = 1000;
+/// How often to wake up when nothing happens, e.g. to turn the spinner, and
+/// the least time between frames drawn for new input.
+const TICK_RATE: u64 = 50;
+/// Least time between frames while the terminal is being resized, so
+/// dragging its border doesn't lay out every intermediate size.
+const RESIZE_INTERVAL: u64 = 100;
+/// How long to wait after the last key typed into the search prompt before
+/// searching, unless configured otherwise.
+const SEARCH_DEBOUNCE: u64 = 100;
+/// Lines searched at a time for the term being typed, between checks for
+/// more keys changing it.
+const SEARCH_SLICE: usize = 100_000;
+/// How long to wait for the terminal to report its background color.
+const BACKGROUND_QUERY_TIMEOUT: u64 = 100;
+/// How often to check whether the terminal background has changed, e.g.
+/// because the OS switched to dark mode.
+const BACKGROUND_REFRESH: u64 = 5000;
+/// How long the status flashes after an alert.
+const ALERT_FLASH: u64 = 1000;
+/// Frames of the spinner shown while the input is still being read.
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+/// Lines of the stderr of `--exec` shown at most in its panel.
+const STDERR_HEIGHT: usize = 5;
+/// Milliseconds each frame of the spinner is shown.
+const SPINNER_INTERVAL: u128 = 250;
+const ENVIRONMENT_VARIABLE_ENABLE_TRACING: &str = "ENABLE_TRACING";
+/// Set to anything but an empty string to draw without colors, see
+/// https://no-color.org.
+const ENVIRONMENT_VARIABLE_NO_COLOR: &str = "NO_COLOR";
+
+/// Runs cag as the `cag` command, with the arguments it was started with.
+pub fn run() -> Result<(), Error> {
+    if let Ok(enable_tracing) = std::env::var(ENVIRONMENT_VARIABLE_ENABLE_TRACING) {
+        if enable_tracing == "1" || &enable_tracing.to_lowercase() == "true" {
+            let file_appender = tracing_appender::rolling::hourly("./.logs/", "runlog");
+            tracing_subscriber::fmt()
+                .with_max_level(Level::TRACE)
+                .with_writer(file_appender)
+                .init();
+        }
+    }
+    let args = args::with_defaults(std::env::args_os(), |name| std::env::var(name).ok())
+        .unwrap_or_else(|err| {
+            eprintln!("{err}");
+            std::process::exit(1);
+        });
+    let matches = Args::command().get_matches_from(args);
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    // Otherwise the input type of files is told by their extension
+    let type_given = matches.value_source("input_type") == Some(ValueSource::CommandLine);
+    let no_color = no_color(&matches, |name| std::env::var_os(name));
+    if no_color {
+        args.color = ColorMode::Never;
+    }
+    if args.version {
+        println!("{}", about::version());
+        if args.verbose {
+            for feature in about::features() {
+                println!("{feature}");
+            }
+        }
+        if args.check_update {
+            match about::latest_release() {
+                Ok(latest) if latest == env!("CARGO_PKG_VERSION") => {
+                    println!("Up to date")
+                }
+                Ok(latest) => println!("cag {latest} is available"),
+                Err(err) => {
+                    eprintln!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        return Ok(());
+    }
+    let (config, rewriter, extractor, alerts) =
+        match Config::load(args.config.as_deref()).and_then(|mut config| {
+            config.theme.monochrome |= no_color;
+            let rewriter = Rewriter::new(&config.rewrite)?;
+            let extractor = Extractor::new(&config.extract)?;
+            let alerts = Alerts::new(&config.alerts)?;
+            Ok((config, rewriter, extractor, alerts))
+        }) {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        };
+
+    let snapshot = match args.resume.as_deref().map(Snapshot::load).transpose() {
+        Ok(snapshot) => snapshot,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
+    if let Some(snapshot) = &snapshot {
+        args.input_type = snapshot.input_type;
+    }
+    let mut files = FileList::new(std::mem::take(&mut args.input));
+    let mut paged = paged_args(&args, &files, type_given);
+
+    if let Some(socket) = &args.serve {
+        let (rx, _handle) = match snapshot {
+            Some(snapshot) => snapshot.stream(),
+            None => {
+                let (rx, handle, _exec) = open_input(&paged, Pause::default());
+                (rx, handle)
+            }
+        };
+        if let Err(err) = server::serve(socket, rx) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let (_, rows) = terminal::size()?;
+    let pause = Pause::default();
+    let (source_names, (rx, _handle, exec)) = match snapshot {
+        Some(mut snapshot) => {
+            let source_names = std::mem::take(&mut snapshot.source_names);
+            let (rx, handle) = snapshot.stream();
+            (source_names, (rx, handle, None))
+        }
+        None => (
+            args.merge.iter().map(|path| source_name(path)).collect(),
+            open_input(&paged, pause.clone()),
+        ),
+    };
+    let mut input = Input {
+        rx,
+        pause,
+        source_names,
+        buffered: Chunk::default(),
+        files: &mut files,
+        background: None,
+        finder: None,
+        exec,
+        keys: VecDeque::new(),
+    };
+    if paged.quit_if_one_screen && read_one_screen(&mut input, rows as usize)? {
+        let mut stdout = io::stdout().lock();
+        for line in &input.buffered.lines {
+            writeln!(stdout, "{line}")?;
+        }
+        return Ok(());
+    }
+
+    let guard = TerminalGuard::new()?;
+    let background = background::query(Duration::from_millis(BACKGROUND_QUERY_TIMEOUT));
+    input.background = background;
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = Terminal::new(backend)?;
+
+    // One pager after the other as `:n` and `:p` switch files
+    let mut summaries = Vec::new();
+    let res = loop {
+        match run_app(
+            &mut terminal,
+            paged,
+            input,
+            &config,
+            &rewriter,
+            &extractor,
+            &alerts,
+        ) {
+            Ok(summary) => summaries.extend(summary),
+            Err(err) => break Err(err),
+        }
+        if !files.take_switch() {
+            break Ok(());
+        }
+        paged = paged_args(&args, &files, type_given);
+        let pause = Pause::default();
+        let (rx, _handle, exec) = open_input(&paged, pause.clone());
+        input = Input {
+            rx,
+            pause,
+            source_names: Vec::new(),
+            buffered: Chunk::default(),
+            files: &mut files,
+            background,
+            finder: None,
+            exec,
+            keys: VecDeque::new(),
+        };
+    };
+
+    drop(guard);
+    if let Err(err) = res {
+        error!("{:?}", err);
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+    for summary in summaries {
+        eprintln!("{summary}");
+    }
+    // Exit like the default handler would have, now that the terminal is
+    // restored
+    match QUIT_SIGNAL.load(Ordering::Relaxed) {
+        0 => Ok(()),
+        signal => std::process::exit(128 + signal),
+    }
+}
+
+/// Puts the terminal into raw mode on the alternate screen, and back again
+/// when dropped, so that an early return with `?` doesn't leave it unusable.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new() -> Result<Self, Error> {
+        install_panic_hook();
+        install_signal_handlers();
+        // Create the guard first so a failure below still restores raw mode
+        let guard = TerminalGuard;
+        setup_terminal()?;
+        Ok(guard)
+    }
+}
+
+fn setup_terminal() -> Result<(), Error> {
+    trace!("Enabling raw mode");
+    enable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableFocusChange
+    )?;
+    // Tell Ctrl-i apart from Tab in terminals supporting the kitty keyboard
+    // protocol. Others ignore this and keep sending Tab for both.
+    #[cfg(unix)]
+    execute!(
+        io::stdout(),
+        PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+    )?;
+    Ok(())
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        if let Err(err) = restore_terminal() {
+            error!("Error restoring terminal: {err}");
+        }
+    }
+}
+
+fn restore_terminal() -> Result<(), Error> {
+    trace!("Disabling raw mode");
+    #[cfg(unix)]
+    execute!(io::stdout(), PopKeyboardEnhancementFlags)?;
+    disable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableFocusChange,
+        Show
+    )?;
+    Ok(())
+}
+
+/// Opens `path` at `line` in `$VISUAL` or `$EDITOR`, handing the terminal
+/// over to it until it exits. Returns a message if the editor failed.
+fn open_in_editor<B: Backend>(
+    terminal: &mut Terminal<B>,
+    path: &str,
+    line: usize,
+) -> Result<Option<String>, Error> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    // Allow editors with arguments, like `code --wait`
+    let mut words = editor.split_whitespace();
+    let Some(program) = words.next() else {
+        return Ok(Some("$EDITOR is empty".to_string()));
+    };
+    if !Path::new(path).exists() {
+        return Ok(Some(format!("No such file: {path}")));
+    }
+    let mut command = std::process::Command::new(program);
+    command.args(words).arg(format!("+{line}")).arg(path);
+    run_suspended(terminal, command)
+}
+
+/// Hands the terminal over to `command` until it exits. Returns a message if
+/// the command failed.
+fn run_suspended<B: Backend>(
+    terminal: &mut Terminal<B>,
+    mut command: std::process::Command,
+) -> Result<Option<String>, Error> {
+    let program = command.get_program().to_string_lossy().to_string();
+    restore_terminal()?;
+    let status = command.status();
+    forget_interrupt();
+    setup_terminal()?;
+    terminal.clear()?;
+    Ok(match status {
+        Ok(status) if status.success() => None,
+        Ok(status) => Some(format!("{program} {status}")),
+        Err(err) => Some(format!("Could not run {program}: {err}")),
+    })
+}
+
+/// Runs `command` with the shell, feeding it `lines`, and hands the terminal
+/// over to it until it exits and Enter is pressed, so that what it printed can
+/// be read. Returns a message with its exit status.
+fn pipe_to<B: Backend>(
+    terminal: &mut Terminal<B>,
+    command: &str,
+    lines: &[String],
+) -> Result<String, Error> {
+    restore_terminal()?;
+    let status = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take() {
+                // A command like `head` may exit before reading all of it
+                for line in lines {
+                    if writeln!(stdin, "{line}").is_err() {
+                        break;
+                    }
+                }
+            }
+            child.wait()
+        });
+    forget_interrupt();
+    let message = match status {
+        Ok(status) => format!("{command}: {status}"),
+        Err(err) => format!("Could not run {command}: {err}"),
+    };
+    // Input may be piped in, so wait for Enter on the terminal itself
+    print!("\n{message}. Press Enter to return to cag");
+    io::stdout().flush()?;
+    if let Ok(tty) = std::fs::File::open("/dev/tty") {
+        let _ = io::BufReader::new(tty).read_line(&mut String::new());
+    }
+    setup_terminal()?;
+    terminal.clear()?;
+    Ok(message)
+}
+
+/// Program opening URLs and files with their default application.
+#[cfg(target_os = "macos")]
+const OPENER: &str = "open";
+#[cfg(not(target_os = "macos"))]
+const OPENER: &str = "xdg-open";
+
+/// Runs `git action hash`, capturing what it prints, errors included, into a
+/// tab `height` rows high so long output can be scrolled through. Returns a
+/// message instead if it couldn't be run, and also if it failed.
+fn run_git(action: &str, hash: &str, height: usize) -> (Option<Tab>, Option<String>) {
+    let short_hash = &hash[..std::cmp::min(hash.len(), 7)];
+    let title = format!("git {action} {short_hash}");
+    let mut command = std::process::Command::new("git");
+    command.arg(action);
+    // There is no terminal for an editor to ask for the message in
+    if action == "revert" {
+        command.arg("--no-edit");
+    }
+    command.arg(hash).stdin(std::process::Stdio::null());
+    let output = io::pipe().and_then(|(mut reader, writer)| {
+        command.stdout(writer.try_clone()?).stderr(writer);
+        let mut child = command.spawn()?;
+        // The command holds on to the writing ends until dropped
+        drop(command);
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output)?;
+        Ok((output, child.wait()?))
+    });
+    let (output, status) = match output {
+        Ok(output) => output,
+        Err(err) => return (None, Some(format!("Could not run {title}: {err}"))),
+    };
+    let lines = String::from_utf8_lossy(&output)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    let message = (!status.success()).then(|| format!("{title} {status}"));
+    let tab = Tab {
+        title,
+        lines,
+        viewport: Viewport::new(height),
+    };
+    (Some(tab), message)
+}
+
+/// Saves a snapshot, returning a message saying how it went.
+fn save_snapshot(
+    path: &Path,
+    input_type: InputType,
+    source_names: &[String],
+    lines: &dyn Storage,
+    line_sources: &[usize],
+) -> String {
+    match snapshot::save(path, input_type, source_names, lines, line_sources) {
+        Ok(()) => format!("Saved {} lines to {}", lines.len(), path.display()),
+        Err(err) => err.to_string(),
+    }
+}
+
+/// Writes buffer lines `range` to `path` as plain text, like a patch for
+/// `git am`, returning a message saying how it went.
+fn write_lines(path: &Path, lines: &dyn Storage, range: Range<usize>) -> String {
+    let count = range.len();
+    let written = std::fs::File::create(path).and_then(|file| {
+        let mut file = io::BufWriter::new(file);
+        for line in lines.iter(range) {
+            writeln!(file, "{line}")?;
+        }
+        file.flush()
+    });
+    match written {
+        Ok(()) => format!("Wrote {count} lines to {}", path.display()),
+        Err(err) => format!("Could not write {}: {err}", path.display()),
+    }
+}
+
+/// Writes the patch of the hunks picked to `path`, or stages it with `git
+/// apply --cached` without one, returning a message saying how it went.
+fn emit_hunks(patch: Vec<String>, path: Option<&Path>) -> String {
+    if let Some(path) = path {
+        return write_lines(path, &patch, 0..patch.len());
+    }
+    let staged = std::process::Command::new("git")
+        .args(["apply", "--cached", "-"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take() {
+                for line in &patch {
+                    writeln!(stdin, "{line}")?;
+                }
+            }
+            child.wait_with_output()
+        });
+    match staged {
+        Ok(output) if output.status.success() => "Staged the hunks picked".to_string(),
+        Ok(output) => format!(
+            "git apply --cached {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        ),
+        Err(err) => format!("Could not run git apply --cached: {err}"),
+    }
+}
+
+/// Opens a URL or path in the background, returning a message saying how it
+/// went.
+fn open(item: &str) -> String {
+    let spawned = std::process::Command::new(OPENER)
+        .arg(item)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn();
+    match spawned {
+        Ok(_) => format!("Opened {item}"),
+        Err(err) => format!("Could not run {OPENER}: {err}"),
+    }
+}
+
+/// Message shown after copying `text` of a commit in `format`.
+fn copied_message(format: YankFormat, text: &str) -> String {
+    match format {
+        YankFormat::Hash | YankFormat::HashSubject => format!("Copied {text}"),
+        _ => format!("Copied the {}", format.label()),
+    }
+}
+
+/// Whether `NO_COLOR`, read with `var`, asks for no colors, unless `--color`
+/// is given.
+fn no_color(matches: &ArgMatches, var: impl Fn(&str) -> Option<OsString>) -> bool {
+    matches.value_source("color") != Some(ValueSource::CommandLine)
+        && var(ENVIRONMENT_VARIABLE_NO_COLOR).is_some_and(|value| !value.is_empty())
+}
+
+/// Number of the signal asking the pager to quit, or 0.
+static QUIT_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+/// Quits cleanly on SIGINT, SIGTERM and SIGHUP, e.g. when git is interrupted
+/// while cag is its pager, instead of leaving the terminal in raw mode.
+#[cfg(unix)]
+fn install_signal_handlers() {
+    extern "C" fn request_quit(signal: libc::c_int) {
+        QUIT_SIGNAL.store(signal, Ordering::Relaxed);
+    }
+    for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+        // SAFETY: the handler only stores to an atomic, which is
+        // async-signal-safe.
+        unsafe {
+            libc::signal(signal, request_quit as *const () as libc::sighandler_t);
+        }
+    }
+}
+
+/// Forgets a Ctrl-C meant for a command the terminal was handed over to, as
+/// it reaches the pager too.
+#[cfg(unix)]
+fn forget_interrupt() {
+    let _ = QUIT_SIGNAL.compare_exchange(libc::SIGINT, 0, Ordering::Relaxed, Ordering::Relaxed);
+}
+
+#[cfg(not(unix))]
+fn install_signal_handlers() {}
+
+#[cfg(not(unix))]
+fn forget_interrupt() {}
+
+/// Restores the terminal before the panic message is printed. The guard alone
+/// is not enough as unwinding only drops it after the message has been
+/// written to the alternate screen, where it is lost.
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        default_hook(info);
+    }));
+}
+
+/// What keyboard input is currently driving.
+enum State {
+    /// Scrolling the buffer. `count` holds a repeat count typed so far, as in
+    /// `25j`.
+    Pager { count: Option<usize> },
+    /// Choosing which merged inputs are shown.
+    Sources { selected: usize },
+    /// Typing a command at the `:` prompt.
+    Command { input: String },
+    /// Typing a search term at the `/` prompt, jumping to the first match as
+    /// it is typed. `origin` is the position to return to if cancelled,
+    /// `scope` the buffer lines searched if not all of them, and `recalled`
+    /// how many terms back the one recalled with Up is.
+    Search {
+        input: String,
+        origin: usize,
+        scope: Option<Range<usize>>,
+        recalled: Option<usize>,
+    },
+    /// Waiting for the option to toggle after `-`, as in `-i`.
+    Option,
+    /// Showing the key bindings until any key is pressed.
+    Help,
+    /// Showing `lines` in a popup until any key is pressed.
+    Overlay { title: String, lines: Vec<String> },
+    /// Choosing a git command to run on the commit `hash`.
+    Actions { hash: String, selected: usize },
+    /// Choosing a URL or path to open or copy.
+    Links { items: Vec<String>, selected: usize },
+    /// Choosing what to copy of a commit, each format with its text.
+    Yank {
+        choices: Vec<(YankFormat, String)>,
+        selected: usize,
+    },
+    /// Focusing one of `items` of the context panel with Tab.
+    Header {
+        items: Vec<HeaderItem>,
+        selected: usize,
+    },
+    /// Typing the pattern of the lines to show at the `&` prompt.
+    Filter { input: String },
+    /// Typing the shell command to pipe the buffer lines `context` to at the
+    /// `|` prompt.
+    Pipe {
+        input: String,
+        context: Range<usize>,
+    },
+    /// Typing the path to write the buffer lines of the current `context`
+    /// to, or of the whole buffer if `whole`, after `w`.
+    Write {
+        input: String,
+        context: Option<Range<usize>>,
+        whole: bool,
+    },
+    /// Choosing a commit in the commit list pane to scroll to.
+    Commits { selected: usize },
+    /// Waiting for the key of the external filter to run after `!`.
+    FilterKey,
+    /// Waiting for the name of the mark to set after `m`, or with `goto` to
+    /// jump to after `'`.
+    Mark { goto: bool },
+    /// Asking whether to go ahead with `action`.
+    Confirm { action: Confirmable },
+    /// Showing the derived buffer in `tabs[index]`.
+    Tab { index: usize },
+}
+
+/// Actions that may be hard to undo, asked about before going ahead if
+/// `confirm_actions` is set.
+enum Confirmable {
+    /// Running a git command changing the repository on the commit `hash`.
+    CommitAction { action: &'static str, hash: String },
+    /// Saving a snapshot over an existing file.
+    Save(PathBuf),
+    /// Writing the buffer lines `range` over an existing file.
+    Write { path: PathBuf, range: Range<usize> },
+}
+
+impl Confirmable {
+    fn prompt(&self) -> String {
+        match self {
+            Confirmable::CommitAction { action, hash } => {
+                format!(
+                    "Run git {action} {}? (y/n)",
+                    &hash[..std::cmp::min(hash.len(), 7)]
+                )
+            }
+            Confirmable::Save(path) | Confirmable::Write { path, .. } => {
+                format!("Overwrite {}? (y/n)", path.display())
+            }
+        }
+    }
+}
+
+/// A buffer derived from the input, like the result of `:sort`, shown in a
+/// tab of its own.
+struct Tab {
+    title: String,
+    lines: Vec<String>,
+    viewport: Viewport,
+}
+
+/// Rows left for the lines of a tab on a terminal `height` rows high, below
+/// the tab bar and within the margin.
+fn tab_height(height: u16) -> usize {
+    height.saturating_sub(3) as usize
+}
+
+/// Git commands offered for the current commit, and whether they change the
+/// repository. Those ask first if `confirm_actions` is set. The output of
+/// all of them is shown in a tab.
+const COMMIT_ACTIONS: &[(&str, bool)] = &[
+    ("show", false),
+    ("checkout", true),
+    ("cherry-pick", true),
+    ("revert", true),
+];
+
+/// Key bindings listed in the help overlay.
+const KEY_BINDINGS: &[(&str, &str)] = &[
+    ("j, Down", "Scroll down one line"),
+    ("k, Up", "Scroll up one line"),
+    ("d, Ctrl-d", "Scroll down half a screen"),
+    ("u, Ctrl-u", "Scroll up half a screen"),
+    ("PageDown", "Scroll down one screen"),
+    ("PageUp", "Scroll up one screen"),
+    (
+        "Shift-Down, Shift-Up",
+        "Scroll the context cut to fit its panel",
+    ),
+    ("0-9", "Repeat the next motion, e.g. 25j"),
+    (
+        "/",
+        "Search, ignoring case unless the term has capitals, see -i and -I",
+    ),
+    (
+        "s",
+        "Search the current commit or other outermost context only",
+    ),
+    ("n", "Jump to the next match"),
+    ("N", "Jump to the previous match"),
+    ("-i", "Toggle ignoring case unless the search has capitals"),
+    ("-I", "Toggle ignoring case in searches"),
+    ("-u", "Toggle ignoring the case of non-ASCII letters like Ä"),
+    ("-d", "Toggle ignoring diacritics, e.g. cafe finding café"),
+    ("g, G", "Jump to the start or the end"),
+    (
+        "H",
+        "Jump to the start clearing the filter, search and toggles like S",
+    ),
+    ("m{a-z}", "Mark the top line, e.g. ma"),
+    (
+        "'{a-z}",
+        "Jump to a mark, or '' back to before the last jump",
+    ),
+    (
+        "b, B",
+        "Jump to the next or previous line that raised an alert",
+    ),
+    (
+        "Ctrl-o, Ctrl-i",
+        "Jump back or forward, e.g. to before a search",
+    ),
+    (
+        "Tab",
+        "Select the hash, author, file or links of the context",
+    ),
+    ("a", "Toggle the annotation gutter"),
+    ("D", "Toggle a column with the time since the previous line"),
+    ("T", "Measure the time from the top line instead, or stop"),
+    ("F", "Follow new input, showing how fast it arrives"),
+    ("p", "Pause or resume reading the input"),
+    ("R", "Reload a file or run --exec again, at the same commit"),
+    ("r", "Run --exec again, at the same commit"),
+    (
+        "E",
+        "Show or hide what the command of --exec wrote to stderr",
+    ),
+    ("M", "Choose which merged inputs are shown"),
+    ("y", "Copy the commit hash, or the context if there is none"),
+    ("Y", "Choose what to copy of the commit"),
+    (
+        "c",
+        "Run git show, checkout, cherry-pick or revert on the commit",
+    ),
+    ("o", "List URLs and paths in the context to open or copy"),
+    ("O", "List URLs and paths in the whole input"),
+    ("t", "Hide or show the context panel"),
+    ("x", "Toggle a hex dump of the lines on screen"),
+    ("S", "Toggle showing removed and added lines side by side"),
+    ("W", "Toggle wrapping long lines instead of cutting them"),
+    ("J", "Expand or collapse the first JSON object on screen"),
+    ("e", "Edit the file of the current diff in $EDITOR"),
+    ("!", "Run a configured filter, showing its output in a tab"),
+    ("&", "Show only lines matching a pattern, or all if empty"),
+    ("Space", "Pick the hunk at the top with --pick-hunks"),
+    (
+        "|",
+        "Pipe the commit to a shell command, e.g. |git apply --check",
+    ),
+    ("w", "Write the commit to a file, or all lines after Tab"),
+    (":", "Enter a command, e.g. :columns 1,3"),
+    ("L", "List the commits to pick one to scroll to"),
+    ("[, ]", "Switch to the previous or next tab, e.g. of :sort"),
+    ("?, F1", "Show this help"),
+    ("Alt-1..9", "Run a recent search, command or & filter again"),
+    ("q", "Quit"),
+];
+
+/// Whether buffer line `line` is shown, as it is neither from a hidden merged
+/// input nor filtered out by the `&` pattern.
+fn is_shown(
+    line: usize,
+    lines: &dyn Storage,
+    line_sources: &[usize],
+    hidden_sources: &[bool],
+    filter: Option<&Regex>,
+) -> bool {
+    line_sources
+        .get(line)
+        .is_none_or(|source| !hidden_sources[*source])
+        && filter.is_none_or(|filter| filter.is_match(&lines.line(line)))
+}
+
+/// Updates `view` after hiding or showing merged inputs or changing the `&`
+/// pattern.
+fn refilter(
+    view: &mut FilterView,
+    viewport: &mut Viewport,
+    lines: &dyn Storage,
+    line_sources: &[usize],
+    hidden_sources: &[bool],
+    filter: Option<&Regex>,
+) {
+    // Keep the top line in place, or the closest one still shown, so
+    // toggling a filter off opens the full view where it was left
+    let top = top_line(view, viewport.position, lines.len());
+    if filter.is_none() && !hidden_sources.iter().any(|hidden| *hidden) {
+        view.clear();
+    } else {
+        view.filter(0..lines.len(), |line| {
+            is_shown(line, lines, line_sources, hidden_sources, filter)
+        });
+    }
+    viewport.position = viewport.clamp(view.nearest_row(top), view.len(lines.len()));
+}
+
+/// `map` without the entries of the first `lines` buffer lines, keyed by the
+/// line numbers of the rest counting from zero again.
+fn discard_keys<V>(map: HashMap<usize, V>, lines: usize) -> HashMap<usize, V> {
+    map.into_iter()
+        .filter_map(|(line, value)| Some((line.checked_sub(lines)?, value)))
+        .collect()
+}
+
+/// Buffer line shown at the top when scrolled to `position`.
+fn top_line(view: &FilterView, position: usize, num_lines: usize) -> usize {
+    view.lines(position..position + 1, num_lines)
+        .first()
+        .copied()
+        .unwrap_or(num_lines)
+}
+
+/// Position showing buffer line `line` at the top, or as close to it as
+/// scrolling allows.
+fn show_line(view: &FilterView, line: usize, num_rows: usize, viewport: &Viewport) -> usize {
+    viewport.clamp(view.row(line), num_rows)
+}
+
+/// Position showing the first match of `search` from where the search
+/// started at `origin`, or `origin` itself if there is none.
+fn first_match(
+    search: &mut Search,
+    view: &FilterView,
+    origin: usize,
+    num_lines: usize,
+    viewport: &Viewport,
+) -> usize {
+    match search.first_from(top_line(view, origin, num_lines)) {
+        Some(line) => show_line(view, line, view.len(num_lines), viewport),
+        None => origin,
+    }
+}
+
+fn refresh_background(
+    background: &mut Option<Background>,
+    config: &ThemeConfig,
+    theme: &mut Theme,
+    redraw: &mut bool,
+) {
+    let current = background::query(Duration::from_millis(BACKGROUND_QUERY_TIMEOUT));
+    if current.is_some() && current != *background {
+        trace!("Terminal background changed to {current:?}");
+        *background = current;
+        *theme = config.theme(*background);
+        *redraw = true;
+    }
+}
+
+/// `args` for paging the current one of `files`, whose input type is told by
+/// its extension unless `--type` was given.
+fn paged_args(args: &Args, files: &FileList, type_given: bool) -> Args {
+    let mut paged = args.clone();
+    if let Some(path) = files.current() {
+        paged.input = vec![path.to_path_buf()];
+        if let Some(input_type) = InputType::for_path(path).filter(|_| !type_given) {
+            paged.input_type = input_type;
+        }
+    }
+    paged
+}
+
+/// Starts reading the input, of which `args` has at most one file, along
+/// with the command run for it with `--exec`.
+fn open_input(args: &Args, pause: Pause) -> (InputReceiver, JoinHandle<()>, Option<Exec>) {
+    if let Some(command) = &args.exec {
+        return Exec::spawn(command, pause);
+    }
+    let (rx, handle) = if !args.merge.is_empty() {
+        merge_inputs(args.merge.clone(), pause)
+    } else if let Some(socket) = &args.attach {
+        stream_input(InputSource::Socket(socket.clone()), pause)
+    } else if let Some(path) = args.input.first() {
+        stream_input(InputSource::Path(path.clone()), pause)
+    } else if args.from_clipboard {
+        stream_input(InputSource::Clipboard, pause)
+    } else {
+        stream_input(InputSource::Stdin, pause)
+    };
+    (rx, handle, None)
+}
+
+/// Where the top line is, to come back to when paging the input again.
+fn place(core: &PagerState) -> Place {
+    let line = core.top_line();
+    let anchor = core
+        .finder
+        .boundaries(&core.lines, std::cmp::min(line + 1, core.lines.len()))
+        .map(|(start, _)| (core.lines.line(start).into_owned(), line - start));
+    Place {
+        line,
+        search: core.search.as_ref().map(|search| search.term().to_string()),
+        anchor,
+    }
+}
+
+/// The input being paged, possibly with its first lines already read.
+pub(crate) struct Input<'a> {
+    pub rx: InputReceiver,
+    pub pause: Pause,
+    /// Names of the merged inputs, empty unless merging.
+    pub source_names: Vec<String>,
+    pub buffered: Chunk,
+    /// Files given, of which the input is the current one if reading files.
+    pub files: &'a mut FileList,
+    /// Background of the terminal, queried once for all the files paged
+    /// since typed keys would be lost in the reply.
+    pub background: Option<Background>,
+    /// Finder of the contexts given by a program embedding the pager,
+    /// instead of the one of the input type.
+    pub finder: Option<Box<dyn ContextFinder>>,
+    /// Command whose output is the input, with `--exec`.
+    pub exec: Option<Exec>,
+    /// Keys handled before any typed, like in tests.
+    pub keys: VecDeque<KeyEvent>,
+}
+
+/// Reads into `input.buffered` until the input ends or no longer fits in
+/// `rows`, and returns whether all of it fits. Gives up after
+/// `INPUT_STREAM_TIMEOUT` so that a slow, still running command isn't kept
+/// off screen.
+fn read_one_screen(input: &mut Input<'_>, rows: usize) -> Result<bool, Error> {
+    let deadline = Instant::now() + Duration::from_millis(INPUT_STREAM_TIMEOUT);
+    while input.buffered.lines.len() <= rows {
+        let Some(wait) = deadline.checked_duration_since(Instant::now()) else {
+            return Ok(false);
+        };
+        match runtime().block_on(timeout(wait, input.rx.recv())) {
+            Ok(Some(chunk)) => {
+                let chunk = chunk?;
+                input.buffered.append(chunk);
+            }
+            Ok(None) => return Ok(true),
+            Err(_elapsed) => return Ok(false),
+        }
+    }
+    Ok(false)
+}
+
+/// `key` as the key it acts like in the `keys` config, if any.
+fn rebind(mut key: KeyEvent, keys: &HashMap<char, char>) -> KeyEvent {
+    if let KeyCode::Char(c) = key.code {
+        if let Some(bound) = keys.get(&c) {
+            key.code = KeyCode::Char(*bound);
+        }
+    }
+    key
+}
+
+fn source_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .to_string()
+}
+
+pub(crate) fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    args: Args,
+    input: Input,
+    config: &Config,
+    rewriter: &Rewriter,
+    extractor: &Extractor,
+    alerts: &Alerts,
+) -> Result<Option<String>, Error> {
+    let Input {
+        mut rx,
+        pause,
+        source_names,
+        buffered,
+        files,
+        mut background,
+        finder,
+        exec,
+        keys,
+    } = input;
+    let mut hidden_sources = vec![false; source_names.len()];
+    let mut message: Option<String> = None;
+    // Show the pager right away, waiting for a slow producer in it, with
+    // what `-F` has already read if anything
+    let first_chunk = buffered;
+    let has_colors = first_chunk.lines.iter().any(|line| diff::has_colors(line));
+    let mut preprocessor = match &args.preprocess {
+        Some(command) => match Preprocessor::spawn(command) {
+            Ok(preprocessor) => Some(preprocessor),
+            Err(err) => {
+                message = Some(format!("Could not run {command}: {err}"));
+                None
+            }
+        },
+        None => None,
+    };
+    if let Some(preprocessor) = &preprocessor {
+        preprocessor.feed(&first_chunk.lines);
+    }
+    let mut all_lines = LineBuffer::new(
+        args.spill_over
+            .map(|megabytes| megabytes.get().saturating_mul(1024 * 1024)),
+    );
+    all_lines.extend(first_chunk.lines)?;
+    let mut line_sources = first_chunk.sources;
+    let mut raw_lines: HashMap<usize, Vec<u8>> = first_chunk.raw.into_iter().collect();
+    // Lines the part of the context cut to fit the panel is scrolled by, and
+    // the position it was scrolled at, as moving the pager resets it
+    let mut context_scroll = (0, 0);
+    let mut toggles = Toggles::new(&args, config);
+    // Whether more input may still arrive
+    let mut streaming = true;
+    let started = Instant::now();
+    let mut spinner_frame = 0;
+    let mut throughput = Throughput::new(Instant::now());
+    // How many lines the command of `--exec` had written to stderr when last
+    // drawn
+    let mut stderr_seen = 0;
+    let json_log = JsonLog::new(&config.json);
+    let finder: Box<dyn ContextFinder> = match finder {
+        Some(finder) => finder,
+        None => context_finder::builtin(args.input_type, Some(json_log.clone())),
+    };
+    let mut core = PagerState::with_lines(finder, terminal.size()?.height as usize, all_lines);
+    core.finder.extend(&core.lines);
+    // Until found or the user moves
+    let mut start_at = StartAt::new(
+        args.input_type,
+        args.pattern.as_deref(),
+        config.start_at.get(&args.input_type).map(String::as_str),
+    )?;
+    if let Some(line) = start_at
+        .as_ref()
+        .and_then(|start_at| start_at.find(&core.lines, 0..core.lines.len()))
+    {
+        core.viewport.position = show_line(&core.view, line, core.lines.len(), &core.viewport);
+        start_at = None;
+    }
+    let gutter = if source_names.is_empty() {
+        gutter::for_input_type(args.input_type)
+    } else {
+        Some(gutter::log_level())
+    };
+    let mut time_delta = TimeDelta::new();
+    let mut hunk_picker = HunkPicker::default();
+
+    let mut expanded_json: HashMap<usize, Vec<String>> = HashMap::new();
+
+    let mut columns: Option<ColumnSelection> = None;
+
+    // Search for the term being typed, with when to start scanning for it
+    let mut pending_search: Option<(Search, Instant)> = None;
+    let search_debounce = Duration::from_millis(config.search_debounce.unwrap_or(SEARCH_DEBOUNCE));
+    core.search_options = SearchOptions {
+        case: config.search_case,
+        unicode: config.search_unicode,
+        normalization: config.search_normalize,
+        fold_diacritics: config.search_fold_diacritics,
+    };
+    // Back where the file was left when switching to it again or reloading
+    // it, once read: in the same context if it is found before the input
+    // ends, or else at the same line
+    let mut return_to = None;
+    let mut anchor = None;
+    if let Some(place) = files.place() {
+        return_to = Some(place.line);
+        anchor = place.anchor.is_some().then(|| place.clone());
+        start_at = None;
+        core.search = place.search.as_deref().and_then(|term| {
+            let mut search = Search::pending(term, core.search_options)?;
+            search.extend(&core.lines);
+            Some(search)
+        });
+    }
+    let mut jumps = JumpList::default();
+    // Buffer lines marked with `m`, by name
+    let mut marks: HashMap<char, usize> = HashMap::new();
+    let mut tabs: Vec<Tab> = Vec::new();
+    let mut commit_list = (args.input_type == InputType::Git).then(CommitList::new);
+    // Only lines matching it are shown
+    let mut line_filter: Option<Regex> = None;
+    // Lines that raised an alert while following
+    let mut bookmarks: BTreeSet<usize> = BTreeSet::new();
+    let mut flash_until: Option<Instant> = None;
+    // Lines dropped because of `--max-lines`
+    let mut dropped_lines = 0;
+    // Whether the terminal was resized since the last frame
+    let mut resized = false;
+    let mut last_frame = Instant::now();
+    let mut color_diff = match args.color {
+        ColorMode::Auto => args.input_type == InputType::Git && !has_colors,
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+    };
+
+    let ascii = config.charset.is_ascii();
+    let mut theme = config.theme.theme(background);
+    let mut last_background_query = Instant::now();
+
+    let mut state = State::Pager { count: None };
+    let mut redraw = true;
+    let mut focused = true;
+    let mut review = args.review_stats.then(ReviewTimer::default);
+    let mut history = History::default();
+    let mut search_history = match config
+        .save_search_history
+        .then(history::search_history_path)
+        .flatten()
+    {
+        Some(path) => SearchHistory::load(path).unwrap_or_else(|err| {
+            message = Some(format!("Could not read the search history: {err}"));
+            SearchHistory::default()
+        }),
+        None => SearchHistory::default(),
+    };
+    let mut replay = keys;
+    // Key of `double_press` pressed once, to act if pressed again
+    let mut armed: Option<char> = None;
+    let mut waiter = Waiter::new();
+    // Input the waiter woke up for, handled with whatever else was read
+    let mut woken_by: Option<Result<Chunk, Error>> = None;
+
+    loop {
+        if QUIT_SIGNAL.load(Ordering::Relaxed) != 0 {
+            break;
+        }
+        loop {
+            let maybe_new_lines = match woken_by.take().map_or_else(|| rx.try_recv(), Ok) {
+                Ok(maybe_new_lines) => maybe_new_lines,
+                Err(TryRecvError::Disconnected) if streaming => {
+                    // Show the final line count
+                    streaming = false;
+                    anchor = None;
+                    if let Some(preprocessor) = &mut preprocessor {
+                        preprocessor.finish();
+                    }
+                    redraw = true;
+                    break;
+                }
+                Err(_) => break,
+            };
+            trace!("Got more lines");
+            // Keep paging what was read if the input breaks off
+            let chunk = match maybe_new_lines {
+                Ok(chunk) => chunk,
+                Err(err) => {
+                    message = Some(err.to_string());
+                    redraw = true;
+                    continue;
+                }
+            };
+            let bytes = chunk.lines.iter().map(|line| line.len() + 1).sum();
+            throughput.record(Instant::now(), chunk.lines.len(), bytes);
+            let start = core.lines.len();
+            if core.following && !alerts.is_empty() {
+                let alerted = alerts.matching(&chunk.lines, start);
+                if !alerted.is_empty() {
+                    bookmarks.extend(alerted);
+                    // Ring the bell
+                    let mut stdout = io::stdout();
+                    stdout.write_all(b"\x07")?;
+                    stdout.flush()?;
+                    flash_until = Some(Instant::now() + Duration::from_millis(ALERT_FLASH));
+                    redraw = true;
+                }
+            }
+            // Input colored by git itself is left alone
+            if args.color == ColorMode::Auto
+                && color_diff
+                && chunk.lines.iter().any(|line| diff::has_colors(line))
+            {
+                color_diff = false;
+            }
+            if let Some(preprocessor) = &preprocessor {
+                preprocessor.feed(&chunk.lines);
+            }
+            core.handle(PagerEvent::NewLines(chunk.lines))?;
+            line_sources.extend(chunk.sources);
+            raw_lines.extend(
+                chunk
+                    .raw
+                    .into_iter()
+                    .map(|(line, bytes)| (start + line, bytes)),
+            );
+            core.view.extend(start..core.lines.len(), |line| {
+                is_shown(
+                    line,
+                    &core.lines,
+                    &line_sources,
+                    &hidden_sources,
+                    line_filter.as_ref(),
+                )
+            });
+            if let Some(commit_list) = &mut commit_list {
+                commit_list.extend(&core.lines);
+            }
+            if let Some(line) = start_at
+                .as_ref()
+                .and_then(|start_at| start_at.find(&core.lines, start..core.lines.len()))
+            {
+                let num_rows = core.view.len(core.lines.len());
+                core.viewport.position = show_line(&core.view, line, num_rows, &core.viewport);
+                start_at = None;
+            }
+            if let Some(line) = anchor
+                .as_ref()
+                .and_then(|place| place.find_anchor(&core.lines, start..core.lines.len()))
+            {
+                return_to = Some(line);
+                anchor = None;
+            }
+            // Also past the rows the filtered view gained
+            if core.following {
+                core.handle(PagerEvent::Bottom)?;
+            }
+            // Keep reading but don't render new lines nobody is looking at
+            redraw |= focused;
+        }
+        if let Some(line) = return_to.filter(|line| anchor.is_none() && *line < core.lines.len()) {
+            let num_rows = core.view.len(core.lines.len());
+            core.viewport.position = show_line(&core.view, line, num_rows, &core.viewport);
+            return_to = None;
+            redraw = true;
+        }
+        if let Some(preprocessor) = &mut preprocessor {
+            redraw |= preprocessor.receive(&core.lines) && focused;
+        }
+        // Drop the oldest lines beyond `--max-lines`, numbering the rest from
+        // zero again wherever lines are referred to
+        let excess = args
+            .max_lines
+            .map_or(0, |max| core.lines.len().saturating_sub(max.get()));
+        if excess > 0 {
+            trace!("Dropping {excess} lines");
+            core.lines.drain_front(excess);
+            line_sources.drain(..std::cmp::min(excess, line_sources.len()));
+            raw_lines = discard_keys(raw_lines, excess);
+            expanded_json = discard_keys(expanded_json, excess);
+            marks.retain(|_, line| *line >= excess);
+            for line in marks.values_mut() {
+                *line -= excess;
+            }
+            bookmarks = bookmarks
+                .into_iter()
+                .filter_map(|line| line.checked_sub(excess))
+                .collect();
+            jumps.discard(excess);
+            hunk_picker.discard(excess);
+            core.finder.discard(excess);
+            time_delta.anchor = time_delta.anchor.and_then(|line| line.checked_sub(excess));
+            let rows = core.view.discard(excess);
+            core.viewport.position = core.viewport.position.saturating_sub(rows);
+            if let State::Search { origin, .. } = &mut state {
+                *origin = origin.saturating_sub(rows);
+            }
+            if let Some(search) = &mut core.search {
+                search.discard(excess);
+            }
+            if let Some((search, _)) = &mut pending_search {
+                search.discard(excess);
+            }
+            if let Some(commit_list) = &mut commit_list {
+                let commits = commit_list.discard(excess);
+                if let State::Commits { selected } = &mut state {
+                    match commit_list.commits().len() {
+                        0 => {
+                            state = State::Pager { count: None };
+                            message = Some("No commits left".to_string());
+                        }
+                        len => *selected = selected.saturating_sub(commits).min(len - 1),
+                    }
+                }
+            }
+            dropped_lines += excess;
+        }
+        // Update the rate every second, also when nothing is read
+        if throughput.advance(Instant::now()) && core.following {
+            redraw |= focused;
+        }
+        let frame = (started.elapsed().as_millis() / SPINNER_INTERVAL) as usize;
+        if streaming && frame != spinner_frame {
+            spinner_frame = frame;
+            redraw |= focused;
+        }
+        if flash_until.is_some_and(|until| Instant::now() >= until) {
+            flash_until = None;
+            redraw = true;
+        }
+        if let Some(exec) = &exec {
+            let (_, written) = exec.stderr(0);
+            redraw |= written != stderr_seen;
+            stderr_seen = written;
+        }
+        // The term typed is searched for a slice of the buffer at a time
+        // once typing pauses, so a key changing it meanwhile cancels the scan
+        let searched = match (&mut pending_search, &state) {
+            (Some((pending, due)), State::Search { .. }) => {
+                Instant::now() >= *due && pending.scan(&core.lines, SEARCH_SLICE)
+            }
+            _ => false,
+        };
+        if let (true, State::Search { origin, .. }) = (searched, &state) {
+            if let Some((mut found, _)) = pending_search.take() {
+                core.viewport.position = first_match(
+                    &mut found,
+                    &core.view,
+                    *origin,
+                    core.lines.len(),
+                    &core.viewport,
+                );
+                core.search = Some(found);
+                redraw = true;
+            }
+        }
+
+        if context_scroll.1 != core.viewport.position {
+            context_scroll = (0, core.viewport.position);
+        }
+
+        // Resizes coming in quick succession are drawn together
+        let resize_wait = Duration::from_millis(RESIZE_INTERVAL)
+            .checked_sub(last_frame.elapsed())
+            .filter(|_| resized);
+        if redraw && resize_wait.is_none() {
+            // Side by side, pairs of removed and added lines share a row, and
+            // a filter may leave lines out
+            let rows = match toggles.side_by_side || preprocessor.is_some() {
+                true => 2 * terminal.size()?.height as usize,
+                false => terminal.size()?.height as usize,
+            };
+            let visible = core.view.lines(
+                core.viewport.position..core.viewport.position + rows,
+                core.lines.len(),
+            );
+            let tail = match toggles.wrap {
+                true => {
+                    let num_rows = core.num_rows();
+                    core.view
+                        .lines(num_rows.saturating_sub(rows)..num_rows, core.lines.len())
+                }
+                false => Vec::new(),
+            };
+            let annotations = [
+                gutter.as_deref().filter(|_| toggles.show_gutter),
+                Some(&time_delta as &dyn Gutter).filter(|_| toggles.show_time_delta),
+                Some(&hunk_picker as &dyn Gutter).filter(|_| args.pick_hunks.is_some()),
+            ]
+            .into_iter()
+            .flatten()
+            .map(|gutter| (gutter.width(), gutter.annotate(&core.lines, &visible)))
+            .collect();
+            let context = visible
+                .first()
+                .map(|line| core.finder.get_context(&core.lines, *line))
+                .unwrap_or_default();
+            let (stderr, written) = exec
+                .as_ref()
+                .filter(|_| toggles.show_stderr)
+                .map_or((Vec::new(), 0), |exec| exec.stderr(STDERR_HEIGHT));
+            let screen = Screen {
+                lines: &core.lines,
+                visible: &visible,
+                tail: &tail,
+                line_sources: &line_sources,
+                source_names: &source_names,
+                hidden_sources: &hidden_sources,
+                expanded_json: &expanded_json,
+                raw_lines: &raw_lines,
+                hex_view: toggles.hex_view,
+                side_by_side: toggles.side_by_side,
+                wrap: toggles.wrap,
+                preprocessor: preprocessor.as_ref(),
+                context: &context,
+                boundaries: visible
+                    .first()
+                    .filter(|_| toggles.show_context)
+                    .and_then(|line| core.finder.boundaries(&core.lines, *line)),
+                show_context: toggles.show_context,
+                context_height: args.context_height,
+                context_scroll: context_scroll.0,
+                state: &state,
+                message: message.as_deref(),
+                status: {
+                    let status: Vec<String> = [
+                        files.status(),
+                        commit_list
+                            .as_ref()
+                            .zip(visible.first())
+                            .and_then(|(commit_list, line)| commit_list.progress(*line)),
+                        core.search.as_ref().map(Search::status),
+                        line_filter.as_ref().map(|filter| {
+                            format!("&{filter}: {} lines", core.view.len(core.lines.len()))
+                        }),
+                        (!bookmarks.is_empty()).then(|| format!("Alerts: {}", bookmarks.len())),
+                        args.pick_hunks.as_ref().map(|_| match hunk_picker.len() {
+                            1 => "1 hunk picked".to_string(),
+                            picked => format!("{picked} hunks picked"),
+                        }),
+                        Some(match (streaming, core.lines.len()) {
+                            (true, lines) => format!(
+                                "{} Loading... {lines} lines",
+                                SPINNER[spinner_frame % SPINNER.len()]
+                            ),
+                            (false, 1) => "1 line".to_string(),
+                            (false, lines) => format!("{lines} lines"),
+                        }),
+                        (dropped_lines > 0).then(|| format!("{dropped_lines} lines dropped")),
+                        (!toggles.show_stderr && stderr_seen > 0)
+                            .then(|| format!("stderr: {stderr_seen} lines, E to show")),
+                        pause.is_paused().then(|| "Paused".to_string()),
+                        (core.following && !pause.is_paused())
+                            .then(|| format!("Following │ {}", throughput.summary())),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                    (!status.is_empty()).then(|| status.join(" │ "))
+                },
+                stderr: &stderr,
+                stderr_written: written,
+                waiting: streaming && core.lines.len() == 0 && dropped_lines == 0,
+                search: core.search.as_ref(),
+                tabs: &tabs,
+                commits: commit_list.as_ref().map_or(&[], CommitList::commits),
+                alerting: flash_until.is_some(),
+                rewriter,
+                columns: columns.as_ref().filter(|_| toggles.show_columns),
+                json_log: (args.input_type == InputType::Json).then_some(&json_log),
+                annotations,
+                theme: &theme,
+                recent: &history,
+                color_diff,
+                dim: !focused && config.dim_unfocused,
+                ascii,
+                input_type: args.input_type,
+            };
+            terminal.draw(|frame| pager(frame, &screen, &mut core.viewport))?;
+            redraw = false;
+            last_frame = Instant::now();
+            if std::mem::take(&mut resized) {
+                let position = core.viewport.position;
+                core.handle(PagerEvent::Resize(core.viewport.height))?;
+                redraw |= core.viewport.position != position;
+            }
+            // How many wrapped lines fit on the last screen is only known once
+            // they are drawn
+            let bottom = core.viewport.max_scroll(core.num_rows());
+            if core.following && core.viewport.position != bottom {
+                core.viewport.position = bottom;
+                redraw = true;
+            }
+        }
+
+        if let (Some(review), Some(commit_list)) = (&mut review, &commit_list) {
+            let commit = commit_list
+                .containing(top_line(
+                    &core.view,
+                    core.viewport.position,
+                    core.lines.len(),
+                ))
+                .map(|index| &commit_list.commits()[index])
+                .filter(|_| focused);
+            review.view(
+                commit.map(|commit| (commit.hash.as_str(), commit.subject.as_str())),
+                Instant::now(),
+            );
+        }
+
+        // Keys of a recent search or command being re-run come first
+        let key = match replay.pop_front() {
+            Some(key) => key,
+            None => {
+                let timeout = resize_wait
+                    .filter(|_| redraw)
+                    .unwrap_or(Duration::from_millis(TICK_RATE));
+                // Wake up to search once typing pauses, and keep scanning
+                // right away unless a key is waiting
+                let timeout = match (&pending_search, &state) {
+                    (Some((_, due)), State::Search { .. }) => {
+                        std::cmp::min(timeout, due.saturating_duration_since(Instant::now()))
+                    }
+                    _ => timeout,
+                };
+                let input_after = last_frame + Duration::from_millis(TICK_RATE);
+                let wake = match waiter.wait(&mut rx, streaming, input_after, timeout) {
+                    // Interrupted by a signal, which is checked for above
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    wake => wake?,
+                };
+                let event = match wake {
+                    Wake::Event(event) => event,
+                    // Its end is noticed when it can't be read from anymore
+                    Wake::Input(chunk) => {
+                        woken_by = chunk;
+                        continue;
+                    }
+                    Wake::Timeout => {
+                        // Only query while idle so the reply doesn't mix with typed keys.
+                        // Terminals that didn't answer the first query are not asked again.
+                        if focused
+                            && background.is_some()
+                            && last_background_query.elapsed()
+                                > Duration::from_millis(BACKGROUND_REFRESH)
+                        {
+                            refresh_background(
+                                &mut background,
+                                &config.theme,
+                                &mut theme,
+                                &mut redraw,
+                            );
+                            last_background_query = Instant::now();
+                        }
+                        continue;
+                    }
+                };
+                match event {
+                    // Not while typing into a prompt
+                    Event::Key(key) if matches!(state, State::Pager { .. }) => {
+                        rebind(key, &config.keys)
+                    }
+                    Event::Key(key) => key,
+                    Event::FocusGained => {
+                        focused = true;
+                        redraw = true;
+                        if background.is_some() {
+                            refresh_background(
+                                &mut background,
+                                &config.theme,
+                                &mut theme,
+                                &mut redraw,
+                            );
+                            last_background_query = Instant::now();
+                        }
+                        continue;
+                    }
+                    Event::Resize(_, _) => {
+                        resized = true;
+                        redraw = true;
+                        continue;
+                    }
+                    // Clicking the indicator of cut context lines shows them all
+                    Event::Mouse(MouseEvent {
+                        kind: MouseEventKind::Down(MouseButton::Left),
+                        row,
+                        ..
+                    }) if toggles.show_context && matches!(state, State::Pager { .. }) => {
+                        let context = panel_context(
+                            &*core.finder,
+                            &core.lines,
+                            &core.view,
+                            core.viewport.position,
+                        );
+                        let (stacked, hidden, _) = stack_context(&context, args.context_height, 0);
+                        // Below the top margin and the lines stacked
+                        if hidden > 0 && row as usize == stacked.len() + 1 {
+                            state = context_overlay(
+                                &*core.finder,
+                                &core.lines,
+                                &core.view,
+                                core.viewport.position,
+                            );
+                            redraw = true;
+                        }
+                        continue;
+                    }
+                    Event::FocusLost => {
+                        focused = false;
+                        // Draw one last dimmed frame, then pause until focus returns
+                        redraw = config.dim_unfocused;
+                        continue;
+                    }
+                    _ => continue,
+                }
+            }
+        };
+        redraw = true;
+        message = None;
+        start_at = None;
+        return_to = None;
+        anchor = None;
+        // Raw mode turns Ctrl-C into a key press instead of SIGINT, so it
+        // quits from prompts and panels too
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            break;
+        }
+        let num_rows = core.view.len(core.lines.len());
+        if let (KeyCode::Char(digit @ '1'..='9'), true) =
+            (key.code, key.modifiers.contains(KeyModifiers::ALT))
+        {
+            if let State::Pager { .. }
+            | State::Command { .. }
+            | State::Search { .. }
+            | State::Filter { .. }
+            | State::Pipe { .. } = state
+            {
+                let number = digit as usize - '0' as usize;
+                match history.get(number) {
+                    Some(entry) => {
+                        // Leave a prompt being typed as if cancelled
+                        if !matches!(state, State::Pager { .. }) {
+                            replay.push_back(KeyEvent::from(KeyCode::Esc));
+                        }
+                        replay.extend(history::keys(entry));
+                    }
+                    None => message = Some(format!("No recent search or command {number}")),
+                }
+                continue;
+            }
+        }
+        match &mut state {
+            State::Pager { count } => {
+                if let KeyCode::Char(digit @ '0'..='9') = key.code {
+                    let digit = digit as usize - '0' as usize;
+                    *count = Some(count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+                    continue;
+                }
+                if let KeyCode::Char(c) = key.code {
+                    if config.double_press.contains(&c)
+                        && !key.modifiers.contains(KeyModifiers::CONTROL)
+                        && armed.replace(c) != Some(c)
+                    {
+                        message = Some(format!("Press {c} again"));
+                        continue;
+                    }
+                }
+                armed = None;
+                let times = count.take().unwrap_or(1).max(1);
+                let lines = |amount: usize| amount.saturating_mul(times);
+                // Scrolling back to look at something stops following
+                if matches!(
+                    key.code,
+                    KeyCode::Char('k' | 'u' | 'g') | KeyCode::Up | KeyCode::PageUp
+                ) && !key.modifiers.contains(KeyModifiers::SHIFT)
+                {
+                    core.following = false;
+                }
+                match key.code {
+                    KeyCode::Char('q') => break,
+                    KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        let current =
+                            top_line(&core.view, core.viewport.position, core.lines.len());
+                        match jumps.back(current) {
+                            Some(line) => {
+                                core.following = false;
+                                core.viewport.position =
+                                    show_line(&core.view, line, num_rows, &core.viewport);
+                            }
+                            None => message = Some("Nothing to jump back to".to_string()),
+                        }
+                    }
+                    // Only told apart from Tab by terminals with the kitty
+                    // keyboard protocol, others send Ctrl-i as Tab
+                    KeyCode::Char('i') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        let current =
+                            top_line(&core.view, core.viewport.position, core.lines.len());
+                        match jumps.forward(current) {
+                            Some(line) => {
+                                core.following = false;
+                                core.viewport.position =
+                                    show_line(&core.view, line, num_rows, &core.viewport);
+                            }
+                            None => message = Some("Nothing to jump forward to".to_string()),
+                        }
+                    }
+                    KeyCode::Up | KeyCode::Down if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                        let context = panel_context(
+                            &*core.finder,
+                            &core.lines,
+                            &core.view,
+                            core.viewport.position,
+                        );
+                        let (_, _, max_scroll) = stack_context(&context, args.context_height, 0);
+                        let scroll = if key.code == KeyCode::Up {
+                            context_scroll.0.saturating_sub(lines(1))
+                        } else {
+                            std::cmp::min(context_scroll.0.saturating_add(lines(1)), max_scroll)
+                        };
+                        context_scroll = (scroll, core.viewport.position);
+                    }
+                    KeyCode::Char('j') | KeyCode::Down => {
+                        core.handle(PagerEvent::ScrollDown(lines(1)))?;
+                    }
+                    KeyCode::Char('g') => {
+                        jumps.push(top_line(
+                            &core.view,
+                            core.viewport.position,
+                            core.lines.len(),
+                        ));
+                        core.handle(PagerEvent::Top)?;
+                    }
+                    KeyCode::Char(direction @ ('b' | 'B')) => {
+                        let current =
+                            top_line(&core.view, core.viewport.position, core.lines.len());
+                        let bookmark = match direction {
+                            'b' => bookmarks.range(current + 1..).next(),
+                            _ => bookmarks.range(..current).next_back(),
+                        };
+                        match bookmark {
+                            Some(&line) => {
+                                jumps.push(current);
+                                core.following = false;
+                                core.viewport.position =
+                                    show_line(&core.view, line, num_rows, &core.viewport);
+                            }
+                            None if bookmarks.is_empty() => {
+                                message = Some("No alerts yet".to_string())
+                            }
+                            None => message = Some("No more alerts".to_string()),
+                        }
+                    }
+                    KeyCode::Char('G') => {
+                        jumps.push(top_line(
+                            &core.view,
+                            core.viewport.position,
+                            core.lines.len(),
+                        ));
+                        core.handle(PagerEvent::Bottom)?;
+                    }
+                    // Read the input again, back in the same context, with `r`
+                    // also running the command of `--exec` again
+                    KeyCode::Char('R') if exec.is_some() || files.current().is_some() => {
+                        files.reload(place(&core));
+                        break;
+                    }
+                    KeyCode::Char('r') if exec.is_some() => {
+                        files.reload(place(&core));
+                        break;
+                    }
+                    KeyCode::Char('E') => match &exec {
+                        Some(_) => toggles.show_stderr = !toggles.show_stderr,
+                        None => message = Some("Not running a command, see --exec".to_string()),
+                    },
+                    // Back to the view cag started with, keeping the marks and
+                    // alerts, with `Ctrl-o` going back to where it was left
+                    KeyCode::Char('H') => {
+                        jumps.push(top_line(
+                            &core.view,
+                            core.viewport.position,
+                            core.lines.len(),
+                        ));
+                        core.following = false;
+                        core.search = None;
+                        line_filter = None;
+                        hidden_sources.fill(false);
+                        core.view.clear();
+                        expanded_json.clear();
+                        toggles = Toggles::new(&args, config);
+                        context_scroll = (0, 0);
+                        time_delta.anchor = None;
+                        core.viewport.position = 0;
+                        message = Some("Back to the initial view".to_string());
+                    }
+                    KeyCode::Char('k') | KeyCode::Up => {
+                        core.handle(PagerEvent::ScrollUp(lines(1)))?;
+                    }
+                    KeyCode::PageDown => {
+                        core.handle(PagerEvent::ScrollDown(lines(core.viewport.shown())))?;
+                    }
+                    KeyCode::PageUp => {
+                        core.handle(PagerEvent::ScrollUp(lines(core.viewport.shown())))?;
+                    }
+                    // Both `d` and `Ctrl-d` (and likewise `u`) scroll half a screen
+                    KeyCode::Char('d') => {
+                        core.handle(PagerEvent::ScrollDown(lines(core.viewport.half_page())))?;
+                    }
+                    KeyCode::Char('u') => {
+                        core.handle(PagerEvent::ScrollUp(lines(core.viewport.half_page())))?;
+                    }
+                    KeyCode::Char('p') => {
+                        message = Some(if pause.toggle() {
+                            "Paused reading input, p to resume".to_string()
+                        } else {
+                            "Resumed reading input".to_string()
+                        })
+                    }
+                    KeyCode::Char('F') => {
+                        core.handle(PagerEvent::Follow(!core.following))?;
+                    }
+                    KeyCode::Char('M') if !source_names.is_empty() => {
+                        state = State::Sources { selected: 0 }
+                    }
+                    KeyCode::Char('D') => toggles.show_time_delta = !toggles.show_time_delta,
+                    KeyCode::Char('T') => {
+                        let line = top_line(&core.view, core.viewport.position, core.lines.len());
+                        if time_delta.anchor == Some(line) {
+                            time_delta.anchor = None;
+                            message = Some("Showing the time since the previous line".to_string());
+                        } else if line < core.lines.len()
+                            && time_delta.seconds(&core.lines.line(line)).is_some()
+                        {
+                            time_delta.anchor = Some(line);
+                            toggles.show_time_delta = true;
+                            message = Some(format!("Showing the time since line {}", line + 1));
+                        } else {
+                            message = Some("No timestamp on the top line".to_string());
+                        }
+                    }
+                    KeyCode::Char('a') if gutter.is_some() => {
+                        toggles.show_gutter = !toggles.show_gutter
+                    }
+                    KeyCode::Char('a') => {
+                        message = Some("No gutter for this input type".to_string())
+                    }
+                    KeyCode::Char(c @ ('y' | 'Y')) => {
+                        let line = core
+                            .view
+                            .lines(
+                                core.viewport.position..core.viewport.position + 1,
+                                core.lines.len(),
+                            )
+                            .first()
+                            .copied();
+                        let context = line
+                            .map(|line| current_context(&*core.finder, &core.lines, line))
+                            .unwrap_or_default();
+                        let context_lines: Vec<String> = context
+                            .iter()
+                            .flat_map(|level| level.iter())
+                            .cloned()
+                            .collect();
+                        let (Some(hash), Some(line)) = (core.finder.identifier(&context), line)
+                        else {
+                            // Without a commit there is only the context
+                            message = Some(if context_lines.is_empty() {
+                                "No context to copy".to_string()
+                            } else {
+                                clipboard::copy(&mut io::stdout(), &context_lines.join("\n"))?;
+                                "Copied the context".to_string()
+                            });
+                            continue;
+                        };
+                        let patch = core
+                            .finder
+                            .find_range(&core.lines, line)
+                            .map(|range| {
+                                core.lines
+                                    .lines(range.start..std::cmp::min(range.end, core.lines.len()))
+                                    .into_owned()
+                            })
+                            .unwrap_or_default();
+                        let subject = commit_list
+                            .as_ref()
+                            .and_then(|commit_list| {
+                                let index = commit_list.containing(line)?;
+                                Some(commit_list.commits()[index].subject.as_str())
+                            })
+                            .unwrap_or("");
+                        let commit = yank::Commit {
+                            hash,
+                            subject,
+                            patch: &patch,
+                            context: &context_lines,
+                        };
+                        let format = if c == 'y' {
+                            config.yank.y
+                        } else {
+                            config.yank.shift_y
+                        };
+                        let forge_url = config.yank.forge_url.as_deref();
+                        match format.text(&commit, forge_url) {
+                            Some(text) => {
+                                clipboard::copy(&mut io::stdout(), &text)?;
+                                message = Some(copied_message(format, &text));
+                            }
+                            None if format == YankFormat::Choose => {
+                                state = State::Yank {
+                                    choices: yank::choices(&commit, forge_url),
+                                    selected: 0,
+                                }
+                            }
+                            None => {
+                                message = Some(
+                                    "Set forge_url in the [yank] config for Markdown links"
+                                        .to_string(),
+                                )
+                            }
+                        }
+                    }
+                    KeyCode::Char('c') => {
+                        let context = core
+                            .view
+                            .lines(
+                                core.viewport.position..core.viewport.position + 1,
+                                core.lines.len(),
+                            )
+                            .first()
+                            .map(|line| current_context(&*core.finder, &core.lines, *line))
+                            .unwrap_or_default();
+                        match core.finder.identifier(&context) {
+                            Some(hash) => {
+                                state = State::Actions {
+                                    hash: hash.to_string(),
+                                    selected: 0,
+                                }
+                            }
+                            None => message = Some("Not inside a commit".to_string()),
+                        }
+                    }
+                    KeyCode::Char(c @ ('o' | 'O')) => {
+                        let items = if c == 'o' {
+                            let context = core
+                                .view
+                                .lines(
+                                    core.viewport.position..core.viewport.position + 1,
+                                    core.lines.len(),
+                                )
+                                .first()
+                                .map(|line| current_context(&*core.finder, &core.lines, *line))
+                                .unwrap_or_default();
+                            extractor.extract(context.iter().flat_map(|level| level.iter()))
+                        } else {
+                            extractor
+                                .extract((0..core.lines.len()).map(|line| core.lines.line(line)))
+                        };
+                        if items.is_empty() {
+                            message = Some("No URLs or paths found".to_string());
+                        } else {
+                            state = State::Links { items, selected: 0 };
+                        }
+                    }
+                    KeyCode::Tab => {
+                        let context = core
+                            .view
+                            .lines(
+                                core.viewport.position..core.viewport.position + 1,
+                                core.lines.len(),
+                            )
+                            .first()
+                            .map(|line| current_context(&*core.finder, &core.lines, *line))
+                            .unwrap_or_default();
+                        let mut items =
+                            header::items(context.iter().flat_map(|level| level.iter()), extractor);
+                        let shown = panel_context(
+                            &*core.finder,
+                            &core.lines,
+                            &core.view,
+                            core.viewport.position,
+                        );
+                        let (_, hidden, _) = stack_context(&shown, args.context_height, 0);
+                        if hidden > 0 && toggles.show_context {
+                            items.push(HeaderItem::More(header::more_lines(hidden)));
+                        }
+                        if items.is_empty() {
+                            message = Some("Nothing to select in the context".to_string());
+                        } else {
+                            state = State::Header { items, selected: 0 };
+                        }
+                    }
+                    KeyCode::Char('x') => toggles.hex_view = !toggles.hex_view,
+                    KeyCode::Char('S') => toggles.side_by_side = !toggles.side_by_side,
+                    KeyCode::Char('W') => toggles.wrap = !toggles.wrap,
+                    // Room for more lines on small terminals
+                    KeyCode::Char('t') => {
+                        toggles.show_context = !toggles.show_context;
+                        message = Some(if toggles.show_context {
+                            "Showing the context".to_string()
+                        } else {
+                            "Context hidden".to_string()
+                        });
+                    }
+                    KeyCode::Char('J') => {
+                        let visible = core.view.lines(core.viewport.rows(), core.lines.len());
+                        let first_json = visible.into_iter().find_map(|line| {
+                            if expanded_json.contains_key(&line) {
+                                Some((line, None))
+                            } else {
+                                json::pretty_print(&core.lines.line(line))
+                                    .map(|pretty| (line, Some(pretty)))
+                            }
+                        });
+                        match first_json {
+                            Some((line, Some(pretty))) => {
+                                expanded_json.insert(line, pretty);
+                            }
+                            Some((line, None)) => {
+                                expanded_json.remove(&line);
+                            }
+                            None => message = Some("No JSON object on screen".to_string()),
+                        }
+                    }
+                    KeyCode::Char('e') => {
+                        let location = core
+                            .view
+                            .lines(
+                                core.viewport.position..core.viewport.position + 1,
+                                core.lines.len(),
+                            )
+                            .first()
+                            .and_then(|line| core.finder.diff_location(&core.lines, *line));
+                        message = match location {
+                            Some((path, line)) => open_in_editor(terminal, &path, line)?,
+                            None => Some("Not inside a diff".to_string()),
+                        };
+                    }
+                    KeyCode::Char(':') => {
+                        state = State::Command {
+                            input: String::new(),
+                        }
+                    }
+                    KeyCode::Char('/') => {
+                        core.search = None;
+                        state = State::Search {
+                            input: String::new(),
+                            origin: core.viewport.position,
+                            scope: None,
+                            recalled: None,
+                        }
+                    }
+                    // Search only the current commit, or whatever the
+                    // outermost context is
+                    KeyCode::Char('s') => {
+                        let current =
+                            top_line(&core.view, core.viewport.position, core.lines.len());
+                        match core.finder.find_range(&core.lines, current) {
+                            Some(scope) => {
+                                core.search = None;
+                                state = State::Search {
+                                    input: String::new(),
+                                    origin: core.viewport.position,
+                                    scope: Some(scope),
+                                    recalled: None,
+                                }
+                            }
+                            None => message = Some("Not inside a context".to_string()),
+                        }
+                    }
+                    // Write the current commit, or whatever the outermost
+                    // context is, with Tab switching to the whole buffer
+                    KeyCode::Char('w') => {
+                        let current =
+                            top_line(&core.view, core.viewport.position, core.lines.len());
+                        let context = core.finder.find_range(&core.lines, current);
+                        state = State::Write {
+                            input: String::new(),
+                            whole: context.is_none(),
+                            context,
+                        }
+                    }
+                    // Pipe the current commit, or whatever the outermost
+                    // context is, to a command like `git apply --check`
+                    KeyCode::Char('|') => {
+                        let current =
+                            top_line(&core.view, core.viewport.position, core.lines.len());
+                        match core.finder.find_range(&core.lines, current) {
+                            Some(context) => {
+                                state = State::Pipe {
+                                    input: String::new(),
+                                    context,
+                                }
+                            }
+                            None => message = Some("Not inside a context".to_string()),
+                        }
+                    }
+                    // Pick the hunk at the top, moving on to the next one
+                    // like `git add -p`
+                    KeyCode::Char(' ') if args.pick_hunks.is_some() => {
+                        let current =
+                            top_line(&core.view, core.viewport.position, core.lines.len());
+                        match hunk_picker.toggle(&core.lines, current) {
+                            Some((hunk, true)) => {
+                                let num_rows = core.view.len(core.lines.len());
+                                core.viewport.position =
+                                    show_line(&core.view, hunk.end, num_rows, &core.viewport);
+                            }
+                            Some((_, false)) => (),
+                            None => message = Some("No hunk to pick".to_string()),
+                        }
+                    }
+                    KeyCode::Char('-') => state = State::Option,
+                    KeyCode::Char('m') => state = State::Mark { goto: false },
+                    KeyCode::Char('\'') => state = State::Mark { goto: true },
+                    KeyCode::Char('!') if !config.filter.is_empty() => state = State::FilterKey,
+                    KeyCode::Char('!') => message = Some("No filters configured".to_string()),
+                    KeyCode::Char('L') => match &commit_list {
+                        Some(commit_list) if !commit_list.commits().is_empty() => {
+                            let line =
+                                top_line(&core.view, core.viewport.position, core.lines.len());
+                            state = State::Commits {
+                                selected: commit_list.containing(line).unwrap_or(0),
+                            }
+                        }
+                        Some(_) => message = Some("No commits found yet".to_string()),
+                        None => message = Some("Only git input has commits to list".to_string()),
+                    },
+                    KeyCode::Char('&') => {
+                        state = State::Filter {
+                            input: String::new(),
+                        }
+                    }
+                    KeyCode::Char(']') if !tabs.is_empty() => state = State::Tab { index: 0 },
+                    KeyCode::Char('[') if !tabs.is_empty() => {
+                        state = State::Tab {
+                            index: tabs.len() - 1,
+                        }
+                    }
+                    KeyCode::Char('[' | ']') => {
+                        message = Some("No other tabs, try :sort or :uniq -c".to_string())
+                    }
+                    KeyCode::Char(direction @ ('n' | 'N')) => {
+                        let event = match direction {
+                            'n' => PagerEvent::NextMatch,
+                            _ => PagerEvent::PreviousMatch,
+                        };
+                        let top = core.top_line();
+                        let mut said = None;
+                        for _ in 0..times {
+                            said = core.handle(event.clone())?.or(said);
+                        }
+                        // With a term, only wrapping around is worth a word
+                        if said.is_some() && core.search.is_some() {
+                            flash_until = Some(Instant::now() + Duration::from_millis(ALERT_FLASH));
+                        }
+                        message = said.or(message);
+                        if core.top_line() != top {
+                            jumps.push(top);
+                        }
+                    }
+                    KeyCode::Char('?') | KeyCode::F(1) => state = State::Help,
+                    _ => (),
+                }
+            }
+            State::Command { input } => match key.code {
+                KeyCode::Enter => {
+                    if !input.is_empty() {
+                        history.push(format!(":{input}"));
+                    }
+                    let command = Command::parse(input);
+                    state = State::Pager { count: None };
+                    match command {
+                        Ok(Command::Columns(Some(selection))) => {
+                            columns = Some(selection);
+                            toggles.show_columns = true;
+                        }
+                        Ok(Command::Columns(None)) if columns.is_some() => {
+                            toggles.show_columns = !toggles.show_columns
+                        }
+                        Ok(Command::Columns(None)) => {
+                            message = Some("No columns selected yet".to_string())
+                        }
+                        Ok(Command::Transform(transform)) => {
+                            let shown: Vec<Cow<str>> = core
+                                .view
+                                .lines(0..num_rows, core.lines.len())
+                                .into_iter()
+                                .map(|line| core.lines.line(line))
+                                .collect();
+                            tabs.push(Tab {
+                                title: transform.title().to_string(),
+                                lines: transform.apply(&shown),
+                                viewport: Viewport::new(tab_height(terminal.size()?.height)),
+                            });
+                            state = State::Tab {
+                                index: tabs.len() - 1,
+                            };
+                        }
+                        Ok(Command::TopK { pattern, limit }) => match Regex::new(&pattern) {
+                            Ok(regex) => {
+                                let shown: Vec<Cow<str>> = core
+                                    .view
+                                    .lines(0..num_rows, core.lines.len())
+                                    .into_iter()
+                                    .map(|line| core.lines.line(line))
+                                    .collect();
+                                let values = transform::top_values(&regex, &shown, limit);
+                                let width = values
+                                    .first()
+                                    .map_or(0, |(_, count)| count.to_string().len());
+                                state = State::Overlay {
+                                    title: format!("Top values of {pattern}"),
+                                    lines: values
+                                        .into_iter()
+                                        .map(|(value, count)| format!("{count:>width$} {value}"))
+                                        .collect(),
+                                };
+                            }
+                            Err(err) => message = Some(Error::from(err).to_string()),
+                        },
+                        Ok(Command::About) => {
+                            state = State::Overlay {
+                                title: about::version(),
+                                lines: about::features(),
+                            };
+                        }
+                        Ok(Command::Stats) => {
+                            let stats = Stats {
+                                lines: core.lines.len(),
+                                bytes: core.lines.bytes(),
+                                spilled: core.lines.is_spilled(),
+                                buffer_memory: core.lines.memory(),
+                                indexes: vec![
+                                    ("contexts", Some(core.finder.index_size())),
+                                    (
+                                        "search matches",
+                                        core.search.as_ref().map(Search::index_size),
+                                    ),
+                                    ("filtered rows", core.view.index_size()),
+                                ],
+                                dropped: dropped_lines,
+                                streaming,
+                                stream_status: if pause.is_paused() {
+                                    Some("paused".to_string())
+                                } else if core.following {
+                                    Some(format!("following, {}", throughput.summary()))
+                                } else {
+                                    None
+                                },
+                            };
+                            state = State::Overlay {
+                                title: "Stats".to_string(),
+                                lines: stats.lines(),
+                            };
+                        }
+                        Ok(command @ (Command::NextFile | Command::PreviousFile)) => {
+                            let place = place(&core);
+                            let switched = match command {
+                                Command::NextFile => files.next(place),
+                                _ => files.previous(place),
+                            };
+                            if switched {
+                                break;
+                            }
+                            message = Some(match (files.current(), command) {
+                                (None, _) => "Not reading files".to_string(),
+                                (_, Command::NextFile) => "No next file".to_string(),
+                                _ => "No previous file".to_string(),
+                            });
+                        }
+                        Ok(Command::Save(path)) if config.confirm_actions && path.exists() => {
+                            state = State::Confirm {
+                                action: Confirmable::Save(path),
+                            }
+                        }
+                        Ok(Command::Save(path)) => {
+                            message = Some(save_snapshot(
+                                &path,
+                                args.input_type,
+                                &source_names,
+                                &core.lines,
+                                &line_sources,
+                            ))
+                        }
+                        Err(err) => message = Some(err),
+                    }
+                }
+                KeyCode::Backspace if input.is_empty() => state = State::Pager { count: None },
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Esc => state = State::Pager { count: None },
+                _ => (),
+            },
+            State::Search {
+                input,
+                origin,
+                scope,
+                recalled,
+            } => {
+                match key.code {
+                    KeyCode::Enter => {
+                        if !input.is_empty() {
+                            history.push(format!("/{input}"));
+                            if let Err(err) = search_history.push(input) {
+                                message = Some(format!("Could not save the search history: {err}"));
+                            }
+                        }
+                        // Don't wait for typing to pause when it's done
+                        if let Some((mut found, _)) = pending_search.take() {
+                            found.extend(&core.lines);
+                            core.viewport.position = first_match(
+                                &mut found,
+                                &core.view,
+                                *origin,
+                                core.lines.len(),
+                                &core.viewport,
+                            );
+                            core.search = Some(found);
+                        }
+                        if core.viewport.position != *origin {
+                            jumps.push(top_line(&core.view, *origin, core.lines.len()));
+                        }
+                        state = State::Pager { count: None };
+                        continue;
+                    }
+                    KeyCode::Esc => {
+                        core.search = None;
+                        pending_search = None;
+                        core.viewport.position = *origin;
+                        state = State::Pager { count: None };
+                        continue;
+                    }
+                    KeyCode::Backspace if input.is_empty() => {
+                        pending_search = None;
+                        core.viewport.position = *origin;
+                        state = State::Pager { count: None };
+                        continue;
+                    }
+                    KeyCode::Backspace => {
+                        input.pop();
+                    }
+                    KeyCode::Char(c) => input.push(c),
+                    KeyCode::Up => {
+                        let back = recalled.map_or(0, |back| back + 1);
+                        let Some(term) = search_history.recall(back) else {
+                            continue;
+                        };
+                        *input = term.to_string();
+                        *recalled = Some(back);
+                    }
+                    // Back down past the last term searched is an empty
+                    // prompt again
+                    KeyCode::Down => match recalled.take() {
+                        Some(0) => input.clear(),
+                        Some(back) => {
+                            *input = search_history.recall(back - 1).unwrap_or("").to_string();
+                            *recalled = Some(back - 1);
+                        }
+                        None => continue,
+                    },
+                    _ => continue,
+                }
+                // Search again from where the search started once typing
+                // pauses, replacing the search for the term typed before
+                pending_search = Search::pending(input, core.search_options).map(|pending| {
+                    (
+                        pending.within(scope.clone()),
+                        Instant::now() + search_debounce,
+                    )
+                });
+                if pending_search.is_none() {
+                    core.search = None;
+                    core.viewport.position = *origin;
+                }
+            }
+            State::Write {
+                input,
+                context,
+                whole,
+            } => match key.code {
+                KeyCode::Enter if input.trim().is_empty() => {
+                    message = Some("No file given".to_string());
+                    state = State::Pager { count: None };
+                }
+                KeyCode::Enter => {
+                    let path = PathBuf::from(input.trim());
+                    // The context may have grown since, as a commit still
+                    // being read
+                    let range = match context {
+                        Some(context) if !*whole => {
+                            context.start..std::cmp::min(context.end, core.lines.len())
+                        }
+                        _ => 0..core.lines.len(),
+                    };
+                    if config.confirm_actions && path.exists() {
+                        state = State::Confirm {
+                            action: Confirmable::Write { path, range },
+                        };
+                    } else {
+                        message = Some(write_lines(&path, &core.lines, range));
+                        state = State::Pager { count: None };
+                    }
+                }
+                KeyCode::Tab if context.is_some() => *whole = !*whole,
+                KeyCode::Backspace if input.is_empty() => state = State::Pager { count: None },
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Esc => state = State::Pager { count: None },
+                _ => (),
+            },
+            State::Pipe { input, context } => match key.code {
+                KeyCode::Enter if input.trim().is_empty() => state = State::Pager { count: None },
+                KeyCode::Enter => {
+                    history.push(format!("|{input}"));
+                    // The context may have grown since, as a commit still
+                    // being read
+                    let end = std::cmp::min(context.end, core.lines.len());
+                    let lines = core.lines.lines(context.start..end);
+                    message = Some(pipe_to(terminal, input, &lines)?);
+                    state = State::Pager { count: None };
+                }
+                KeyCode::Backspace if input.is_empty() => state = State::Pager { count: None },
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Esc => state = State::Pager { count: None },
+                _ => (),
+            },
+            State::Filter { input } => match key.code {
+                KeyCode::Enter => {
+                    if !input.is_empty() {
+                        history.push(format!("&{input}"));
+                    }
+                    let filter = match input.as_str() {
+                        "" => None,
+                        pattern => match Regex::new(pattern) {
+                            Ok(filter) => Some(filter),
+                            Err(err) => {
+                                message = Some(Error::from(err).to_string());
+                                continue;
+                            }
+                        },
+                    };
+                    state = State::Pager { count: None };
+                    line_filter = filter;
+                    refilter(
+                        &mut core.view,
+                        &mut core.viewport,
+                        &core.lines,
+                        &line_sources,
+                        &hidden_sources,
+                        line_filter.as_ref(),
+                    );
+                }
+                KeyCode::Backspace if input.is_empty() => state = State::Pager { count: None },
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Esc => state = State::Pager { count: None },
+                _ => (),
+            },
+            State::Commits { selected } => {
+                let commits = commit_list.as_ref().map_or(&[][..], CommitList::commits);
+                match key.code {
+                    KeyCode::Char('j') | KeyCode::Down => {
+                        *selected = std::cmp::min(*selected + 1, commits.len().saturating_sub(1))
+                    }
+                    KeyCode::Char('k') | KeyCode::Up => *selected = selected.saturating_sub(1),
+                    KeyCode::Char('g') => *selected = 0,
+                    KeyCode::Char('G') => *selected = commits.len().saturating_sub(1),
+                    KeyCode::Enter => {
+                        let Some(commit) = commits.get(*selected) else {
+                            continue;
+                        };
+                        jumps.push(top_line(
+                            &core.view,
+                            core.viewport.position,
+                            core.lines.len(),
+                        ));
+                        core.following = false;
+                        core.viewport.position =
+                            show_line(&core.view, commit.line, num_rows, &core.viewport);
+                    }
+                    KeyCode::Char('q') | KeyCode::Char('L') | KeyCode::Esc => {
+                        state = State::Pager { count: None }
+                    }
+                    _ => (),
+                }
+            }
+            State::Mark { goto } => {
+                let goto = *goto;
+                state = State::Pager { count: None };
+                let KeyCode::Char(name) = key.code else {
+                    continue;
+                };
+                let current = top_line(&core.view, core.viewport.position, core.lines.len());
+                if !goto {
+                    if current < core.lines.len() {
+                        marks.insert(name, current);
+                        message = Some(format!("Marked line {} as {name}", current + 1));
+                    }
+                    continue;
+                }
+                // `''` goes back to where the last jump started, and again
+                // to return, as in vim
+                let line = match name {
+                    '\'' => jumps.back(current).inspect(|_| jumps.push(current)),
+                    name => marks.get(&name).copied().inspect(|_| jumps.push(current)),
+                };
+                match line {
+                    Some(line) => {
+                        core.following = false;
+                        core.viewport.position =
+                            show_line(&core.view, line, num_rows, &core.viewport);
+                    }
+                    None => message = Some(format!("Mark {name} not set")),
+                }
+            }
+            State::FilterKey => {
+                state = State::Pager { count: None };
+                let KeyCode::Char(c) = key.code else {
+                    continue;
+                };
+                let Some(binding) = config.filter.iter().find(|binding| binding.key == c) else {
+                    message = Some(format!("No filter bound to !{c}"));
+                    continue;
+                };
+                let shown = core.view.lines(core.viewport.rows(), core.lines.len());
+                let result = match binding.input {
+                    FilterInput::Screen => filter::run(
+                        &binding.command,
+                        shown.iter().map(|line| core.lines.line(*line)),
+                    ),
+                    FilterInput::Context => {
+                        let line = shown.first().copied().unwrap_or(0);
+                        let context = current_context(&*core.finder, &core.lines, line);
+                        filter::run(
+                            &binding.command,
+                            context
+                                .iter()
+                                .flat_map(|level| level.iter().map(String::as_str)),
+                        )
+                    }
+                };
+                match result {
+                    Ok(lines) => {
+                        tabs.push(Tab {
+                            title: binding.command.clone(),
+                            lines,
+                            viewport: Viewport::new(tab_height(terminal.size()?.height)),
+                        });
+                        state = State::Tab {
+                            index: tabs.len() - 1,
+                        };
+                    }
+                    Err(err) => message = Some(err.to_string()),
+                }
+            }
+            State::Confirm { action } => {
+                let mut ran = None;
+                if key.code == KeyCode::Char('y') {
+                    message = match action {
+                        Confirmable::CommitAction { action, hash } => {
+                            let (tab, message) =
+                                run_git(action, hash, tab_height(terminal.size()?.height));
+                            ran = tab;
+                            message
+                        }
+                        Confirmable::Save(path) => Some(save_snapshot(
+                            path,
+                            args.input_type,
+                            &source_names,
+                            &core.lines,
+                            &line_sources,
+                        )),
+                        Confirmable::Write { path, range } => {
+                            Some(write_lines(path, &core.lines, range.clone()))
+                        }
+                    };
+                } else {
+                    message = Some("Cancelled".to_string());
+                }
+                state = match ran {
+                    Some(tab) => {
+                        tabs.push(tab);
+                        State::Tab {
+                            index: tabs.len() - 1,
+                        }
+                    }
+                    None => State::Pager { count: None },
+                };
+            }
+            State::Tab { index } => {
+                let tab = &mut tabs[*index];
+                tab.viewport.height = tab_height(terminal.size()?.height);
+                let num_lines = tab.lines.len();
+                match key.code {
+                    KeyCode::Char('j') | KeyCode::Down => tab.viewport.scroll_down(1, num_lines),
+                    KeyCode::Char('k') | KeyCode::Up => tab.viewport.scroll_up(1),
+                    KeyCode::Char('d') => tab
+                        .viewport
+                        .scroll_down(tab.viewport.half_page(), num_lines),
+                    KeyCode::Char('u') => tab.viewport.scroll_up(tab.viewport.half_page()),
+                    KeyCode::PageDown => tab.viewport.scroll_down(tab.viewport.height, num_lines),
+                    KeyCode::PageUp => tab.viewport.scroll_up(tab.viewport.height),
+                    KeyCode::Char('g') => tab.viewport.position = 0,
+                    KeyCode::Char('G') => {
+                        tab.viewport.position = tab.viewport.max_scroll(num_lines)
+                    }
+                    // The input is the tab before the first and after the last
+                    KeyCode::Char(']') if *index + 1 < tabs.len() => *index += 1,
+                    KeyCode::Char('[') if *index > 0 => *index -= 1,
+                    KeyCode::Char('[' | ']') | KeyCode::Esc => state = State::Pager { count: None },
+                    KeyCode::Char('q') => {
+                        tabs.remove(*index);
+                        state = match tabs.len() {
+                            0 => State::Pager { count: None },
+                            len => State::Tab {
+                                index: std::cmp::min(*index, len - 1),
+                            },
+                        };
+                    }
+                    _ => (),
+                }
+            }
+            State::Option => {
+                state = State::Pager { count: None };
+                let (toggled, description) = match key.code {
+                    KeyCode::Char('i') => {
+                        (CaseMode::Smart, "Ignoring case unless searching capitals")
+                    }
+                    KeyCode::Char('I') => (CaseMode::Insensitive, "Ignoring case"),
+                    KeyCode::Char('u') => {
+                        core.search_options.unicode = !core.search_options.unicode;
+                        message = Some(if core.search_options.unicode {
+                            "Ignoring the case of all letters, not just ASCII".to_string()
+                        } else {
+                            "Ignoring the case of ASCII letters only".to_string()
+                        });
+                        (core.search_options.case, "")
+                    }
+                    KeyCode::Char('d') => {
+                        core.search_options.fold_diacritics = !core.search_options.fold_diacritics;
+                        message = Some(if core.search_options.fold_diacritics {
+                            "Ignoring accents and other diacritics".to_string()
+                        } else {
+                            "Matching diacritics".to_string()
+                        });
+                        (core.search_options.case, "")
+                    }
+                    KeyCode::Char(c) => {
+                        message = Some(format!("Unknown option -{c}"));
+                        continue;
+                    }
+                    _ => continue,
+                };
+                if let KeyCode::Char('i' | 'I') = key.code {
+                    core.search_options.case = if core.search_options.case == toggled {
+                        message = Some("Searching case sensitively".to_string());
+                        CaseMode::Sensitive
+                    } else {
+                        message = Some(description.to_string());
+                        toggled
+                    };
+                }
+                // Count the matches again, jumping to the first one on screen
+                if let Some(previous) = &core.search {
+                    core.search = Search::new(previous.term(), &core.lines, core.search_options)
+                        .map(|search| search.within(previous.scope()));
+                    if let Some(search) = &mut core.search {
+                        search.first_from(top_line(
+                            &core.view,
+                            core.viewport.position,
+                            core.lines.len(),
+                        ));
+                    }
+                }
+            }
+            State::Sources { selected } => match key.code {
+                KeyCode::Char('j') | KeyCode::Down => {
+                    *selected = std::cmp::min(*selected + 1, source_names.len() - 1)
+                }
+                KeyCode::Char('k') | KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Char(' ') | KeyCode::Enter => {
+                    hidden_sources[*selected] = !hidden_sources[*selected];
+                    refilter(
+                        &mut core.view,
+                        &mut core.viewport,
+                        &core.lines,
+                        &line_sources,
+                        &hidden_sources,
+                        line_filter.as_ref(),
+                    );
+                }
+                KeyCode::Char('q') | KeyCode::Char('M') | KeyCode::Esc => {
+                    state = State::Pager { count: None }
+                }
+                _ => (),
+            },
+            State::Help | State::Overlay { .. } => state = State::Pager { count: None },
+            State::Yank { choices, selected } => match key.code {
+                KeyCode::Char('j') | KeyCode::Down => {
+                    *selected = std::cmp::min(*selected + 1, choices.len() - 1)
+                }
+                KeyCode::Char('k') | KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Enter => {
+                    let (format, text) = &choices[*selected];
+                    clipboard::copy(&mut io::stdout(), text)?;
+                    message = Some(copied_message(*format, text));
+                    state = State::Pager { count: None };
+                }
+                KeyCode::Char('q') | KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Esc => {
+                    state = State::Pager { count: None }
+                }
+                _ => (),
+            },
+            State::Links { items, selected } => match key.code {
+                KeyCode::Char('j') | KeyCode::Down => {
+                    *selected = std::cmp::min(*selected + 1, items.len() - 1)
+                }
+                KeyCode::Char('k') | KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Enter => {
+                    message = Some(open(&items[*selected]));
+                    state = State::Pager { count: None };
+                }
+                KeyCode::Char('y') => {
+                    clipboard::copy(&mut io::stdout(), &items[*selected])?;
+                    message = Some(format!("Copied {}", items[*selected]));
+                    state = State::Pager { count: None };
+                }
+                KeyCode::Char('q') | KeyCode::Char('o') | KeyCode::Char('O') | KeyCode::Esc => {
+                    state = State::Pager { count: None }
+                }
+                _ => (),
+            },
+            State::Header { items, selected } => match key.code {
+                KeyCode::Tab | KeyCode::Right | KeyCode::Char('l') => {
+                    *selected = (*selected + 1) % items.len()
+                }
+                KeyCode::BackTab | KeyCode::Left | KeyCode::Char('h') => {
+                    *selected = (*selected + items.len() - 1) % items.len()
+                }
+                KeyCode::Enter => {
+                    message = match &items[*selected] {
+                        HeaderItem::Hash(text) | HeaderItem::Author(text) => {
+                            clipboard::copy(&mut io::stdout(), text)?;
+                            Some(format!("Copied {text}"))
+                        }
+                        HeaderItem::Path(path) => {
+                            // At the line on screen if it is in that file
+                            let line = core
+                                .view
+                                .lines(
+                                    core.viewport.position..core.viewport.position + 1,
+                                    core.lines.len(),
+                                )
+                                .first()
+                                .and_then(|line| core.finder.diff_location(&core.lines, *line))
+                                .filter(|(location, _)| location == path)
+                                .map_or(1, |(_, line)| line);
+                            open_in_editor(terminal, path, line)?
+                        }
+                        HeaderItem::Link(link) => Some(open(link)),
+                        HeaderItem::More(_) => {
+                            state = context_overlay(
+                                &*core.finder,
+                                &core.lines,
+                                &core.view,
+                                core.viewport.position,
+                            );
+                            continue;
+                        }
+                    };
+                    state = State::Pager { count: None };
+                }
+                KeyCode::Char('y') => {
+                    let text = items[*selected].text();
+                    clipboard::copy(&mut io::stdout(), text)?;
+                    message = Some(format!("Copied {text}"));
+                    state = State::Pager { count: None };
+                }
+                KeyCode::Char('q') | KeyCode::Esc => state = State::Pager { count: None },
+                _ => (),
+            },
+            State::Actions { hash, selected } => match key.code {
+                KeyCode::Char('j') | KeyCode::Down => {
+                    *selected = std::cmp::min(*selected + 1, COMMIT_ACTIONS.len() - 1)
+                }
+                KeyCode::Char('k') | KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Enter => {
+                    let (action, changes) = COMMIT_ACTIONS[*selected];
+                    if changes && config.confirm_actions {
+                        state = State::Confirm {
+                            action: Confirmable::CommitAction {
+                                action,
+                                hash: std::mem::take(hash),
+                            },
+                        };
+                    } else {
+                        let (tab, run_message) =
+                            run_git(action, hash, tab_height(terminal.size()?.height));
+                        message = run_message;
+                        state = match tab {
+                            Some(tab) => {
+                                tabs.push(tab);
+                                State::Tab {
+                                    index: tabs.len() - 1,
+                                }
+                            }
+                            None => State::Pager { count: None },
+                        };
+                    }
+                }
+                KeyCode::Char('q') | KeyCode::Char('c') | KeyCode::Esc => {
+                    state = State::Pager { count: None }
+                }
+                _ => (),
+            },
+        }
+    }
+    let summary: Vec<String> = [
+        review
+            .as_mut()
+            .map(|review| review.summary(marks.len(), Instant::now())),
+        args.pick_hunks
+            .as_ref()
+            .filter(|_| !hunk_picker.is_empty())
+            .map(|output| emit_hunks(hunk_picker.patch(&core.lines), output.as_deref())),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    Ok((!summary.is_empty()).then(|| summary.join("\n")))
+}
+
+/// What is shown besides the lines, toggled with keys, and back to how cag
+/// started with `H`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Toggles {
+    show_context: bool,
+    /// Bytes of each line as a hex dump
+    hex_view: bool,
+    /// Removed and added lines of diffs side by side
+    side_by_side: bool,
+    show_gutter: bool,
+    /// The column with the time since the previous line
+    show_time_delta: bool,
+    /// The columns selected with `:columns` instead of whole lines
+    show_columns: bool,
+    /// The panel of what the command of `--exec` wrote to stderr
+    show_stderr: bool,
+    /// Long lines wrapped onto the next rows
+    wrap: bool,
+}
+
+impl Toggles {
+    fn new(args: &Args, config: &Config) -> Self {
+        Toggles {
+            show_context: true,
+            hex_view: false,
+            side_by_side: args.side_by_side,
+            show_gutter: args.gutter || config.gutter,
+            show_time_delta: false,
+            show_columns: false,
+            show_stderr: false,
+            wrap: args.wrap,
+        }
+    }
+}
+
+/// Everything `pager` needs to draw one frame.
+struct Screen<'a> {
+    lines: &'a dyn Storage,
+    /// Buffer line numbers of the rows on screen.
+    visible: &'a [usize],
+    /// Buffer line numbers of the last screen of rows when wrapping, to find
+    /// how far scrolling goes.
+    tail: &'a [usize],
+    line_sources: &'a [usize],
+    source_names: &'a [String],
+    hidden_sources: &'a [bool],
+    /// Pretty-printed JSON of the lines it has been expanded on.
+    expanded_json: &'a HashMap<usize, Vec<String>>,
+    /// Original bytes of the lines that weren't valid UTF-8.
+    raw_lines: &'a HashMap<usize, Vec<u8>>,
+    /// Whether to show the bytes of each line as a hex dump.
+    hex_view: bool,
+    /// Whether to show removed and added lines of diffs side by side.
+    side_by_side: bool,
+    /// Whether to wrap long lines instead of cutting them.
+    wrap: bool,
+    /// Filter whose output is shown instead of the lines it has got to.
+    preprocessor: Option<&'a Preprocessor>,
+    /// Lines of each context level, outermost first.
+    context: &'a [Cow<'a, [String]>],
+    /// Buffer line numbers of the lines starting and ending the outermost
+    /// context, highlighted when on screen.
+    boundaries: Option<(usize, Option<usize>)>,
+    /// Whether to show the context panel at all.
+    show_context: bool,
+    /// Lines of context shown at most.
+    context_height: usize,
+    /// Lines the context cut to fit is scrolled by.
+    context_scroll: usize,
+    state: &'a State,
+    /// Feedback shown on the bottom line until the next key press.
+    message: Option<&'a str>,
+    /// Shown at the right end of the bottom line, like the input rate.
+    status: Option<String>,
+    /// Last lines the command of `--exec` wrote to stderr, shown in a panel
+    /// under the lines unless empty, and how many it wrote in all.
+    stderr: &'a [String],
+    stderr_written: usize,
+    /// Whether nothing has been read yet of an input still open.
+    waiting: bool,
+    /// Highlighted in the lines on screen.
+    search: Option<&'a Search>,
+    tabs: &'a [Tab],
+    /// Commits listed in the commit list pane.
+    commits: &'a [Commit],
+    /// Whether to flash the status because a line raised an alert.
+    alerting: bool,
+    rewriter: &'a Rewriter,
+    /// Columns to display instead of whole lines.
+    columns: Option<&'a ColumnSelection>,
+    /// Fields to display instead of whole JSON lines.
+    json_log: Option<&'a JsonLog>,
+    /// Width and an annotation for each visible line of the gutter columns
+    /// shown.
+    annotations: Vec<(usize, Vec<Option<String>>)>,
+    theme: &'a Theme,
+    /// Searches and commands listed while typing a new one.
+    recent: &'a History,
+    /// Whether to color lines by their part of a diff.
+    color_diff: bool,
+    /// Whether to dim everything because the terminal lost focus.
+    dim: bool,
+    /// Whether to draw borders and separators with ASCII only.
+    ascii: bool,
+    input_type: InputType,
+}
+
+fn pager<B: Backend>(f: &mut Frame<B>, screen: &Screen, viewport: &mut Viewport) {
+    trace!("Rendering screen");
+    let more;
+    let (mut context, hidden, _) = if screen.show_context {
+        stack_context(screen.context, screen.context_height, screen.context_scroll)
+    } else {
+        (Vec::new(), 0, 0)
+    };
+    if hidden > 0 {
+        more = header::more_lines(hidden);
+        context.push(&more);
+    }
+    let commit_len = if context.is_empty() {
+        0
+    } else {
+        context.len() + 1
+    };
+    let focused = match screen.state {
+        State::Header { items, selected } => Some(items[*selected].text()),
+        _ => None,
+    };
+    let commit = context_text(&context, screen.rewriter, focused);
+    let stderr_len = match screen.stderr.len() {
+        0 => 0,
+        len => len + 1,
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Max(commit_len as u16),
+                Constraint::Min(8),
+                Constraint::Length(stderr_len as u16),
+            ]
+            .as_ref(),
+        )
+        .margin(1)
+        .split(f.size());
+
+    let commit_paragraph = Paragraph::new(commit).style(screen.theme.context).block(
+        Block::default()
+            .borders(Borders::BOTTOM)
+            .border_type(BorderType::Double)
+            .border_style(screen.theme.border),
+    );
+    f.render_widget(commit_paragraph, chunks[0]);
+
+    let text_area = match screen.state {
+        State::Commits { selected } => {
+            let panes = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(
+                    [
+                        Constraint::Length(std::cmp::min(48, chunks[1].width / 3)),
+                        Constraint::Min(1),
+                    ]
+                    .as_ref(),
+                )
+                .split(chunks[1]);
+            commits_pane(f, screen, *selected, panes[0]);
+            panes[1]
+        }
+        _ => chunks[1],
+    };
+    // Hex dumps are never side by side
+    let side_by_side = screen.side_by_side && !screen.hex_view;
+    let paragraph = if screen.waiting {
+        Paragraph::new("Waiting for input...").style(screen.theme.gutter)
+    } else if side_by_side {
+        Paragraph::new(side_by_side_text(screen, text_area.width as usize))
+    } else {
+        Paragraph::new(visible_text(screen, text_area.width as usize))
+    };
+    f.render_widget(paragraph, text_area);
+    viewport.height = chunks[1].height as usize;
+    viewport.wrapped = (screen.wrap && !screen.waiting && !side_by_side).then(|| {
+        let (width, height) = (text_area.width as usize, viewport.height);
+        (
+            fitting(screen, screen.visible.iter(), width, height),
+            fitting(screen, screen.tail.iter().rev(), width, height),
+        )
+    });
+    if !screen.stderr.is_empty() {
+        let title = format!(" stderr: {} lines, E to hide ", screen.stderr_written);
+        let stderr = Paragraph::new(screen.stderr.join("\n"))
+            .style(screen.theme.gutter)
+            .block(
+                Block::default()
+                    .borders(Borders::TOP)
+                    .border_style(screen.theme.border)
+                    .title(title),
+            );
+        f.render_widget(stderr, chunks[2]);
+    }
+
+    match screen.state {
+        State::Sources { selected } => sources_panel(f, screen, *selected),
+        State::Help => help_panel(f, screen),
+        State::Overlay { title, lines } => overlay_panel(f, title, lines),
+        State::Actions { hash, selected } => actions_panel(f, hash, *selected),
+        State::Links { items, selected } => links_panel(f, items, *selected),
+        State::Yank { choices, selected } => yank_panel(f, choices, *selected),
+        State::Tab { index } => tab_panel(f, screen.tabs, *index, screen.theme),
+        _ => (),
+    }
+    bottom_line(f, screen);
+    if screen.ascii {
+        f.render_widget(Asciify, f.size());
+    }
+    if screen.dim {
+        f.render_widget(Dim, f.size());
+    }
+}
+
+/// The context lines as displayed, with the first occurrence of `focused`
+/// highlighted.
+fn context_text<'a>(lines: &[&'a str], rewriter: &Rewriter, focused: Option<&str>) -> Text<'a> {
+    let mut focused = focused;
+    let lines: Vec<Spans> = lines
+        .iter()
+        .map(|line| {
+            let line = rewriter.apply(line);
+            let Some((start, end)) = focused.and_then(|text| {
+                let start = line.find(text)?;
+                Some((start, start + text.len()))
+            }) else {
+                return Spans::from(line.into_owned());
+            };
+            focused = None;
+            Spans::from(vec![
+                Span::raw(line[..start].to_string()),
+                Span::styled(
+                    line[start..end].to_string(),
+                    Style::default().add_modifier(Modifier::REVERSED),
+                ),
+                Span::raw(line[end..].to_string()),
+            ])
+        })
+        .collect();
+    Text::from(lines)
+}
+
+/// Dims everything already drawn in its area.
+struct Dim;
+
+impl Widget for Dim {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        buf.set_style(area, Style::default().add_modifier(Modifier::DIM));
+    }
+}
+
+/// Fits the context levels into `max_lines`, innermost at the bottom. Inner
+/// levels are kept whole and outer levels are cut short when space runs out,
+/// leaving the last line for an indicator of the number of lines cut, which
+/// is returned too. The level cut short is shown from `scroll` lines down,
+/// up to the last of the returned number of lines it can be scrolled by.
+fn stack_context<'a>(
+    levels: &'a [Cow<'a, [String]>],
+    max_lines: usize,
+    scroll: usize,
+) -> (Vec<&'a str>, usize, usize) {
+    let total: usize = levels.iter().map(|level| level.len()).sum();
+    let max_lines = if total > max_lines {
+        max_lines.saturating_sub(1)
+    } else {
+        max_lines
+    };
+    let mut stacked: Vec<&str> = Vec::new();
+    let mut max_scroll = 0;
+    for level in levels.iter().rev() {
+        let room = max_lines.saturating_sub(stacked.len());
+        let skip = if room > 0 && room < level.len() {
+            max_scroll = level.len() - room;
+            std::cmp::min(scroll, max_scroll)
+        } else {
+            0
+        };
+        stacked.splice(
+            0..0,
+            level.iter().skip(skip).take(room).map(|line| line.as_str()),
+        );
+    }
+    let hidden = total - stacked.len();
+    (stacked, hidden, max_scroll)
+}
+
+/// The context shown in the panel while the row at `position` is at the top.
+fn panel_context<'a>(
+    cf: &dyn ContextFinder,
+    lines: &'a dyn Storage,
+    view: &FilterView,
+    position: usize,
+) -> Vec<Cow<'a, [String]>> {
+    view.lines(position..position + 1, lines.len())
+        .first()
+        .map(|line| cf.get_context(lines, *line))
+        .unwrap_or_default()
+}
+
+/// Overlay with the whole context shown in the panel, for when it was cut to
+/// fit.
+fn context_overlay(
+    cf: &dyn ContextFinder,
+    lines: &dyn Storage,
+    view: &FilterView,
+    position: usize,
+) -> State {
+    let context = panel_context(cf, lines, view, position);
+    State::Overlay {
+        title: "Context".to_string(),
+        lines: context
+            .iter()
+            .flat_map(|level| level.iter().cloned())
+            .collect(),
+    }
+}
+
+/// Draws the command prompt or the last message on the bottom margin row.
+fn bottom_line<B: Backend>(f: &mut Frame<B>, screen: &Screen) {
+    let size = f.size();
+    if size.height == 0 {
+        return;
+    }
+    let area = Rect::new(size.x, size.y + size.height - 1, size.width, 1);
+    if let Some(status) = &screen.status {
+        f.render_widget(
+            Paragraph::new(status.as_str())
+                .style(if screen.alerting {
+                    screen.theme.alert
+                } else {
+                    screen.theme.status
+                })
+                .alignment(Alignment::Right),
+            area,
+        );
+    }
+    if let State::Command { input }
+    | State::Search { input, .. }
+    | State::Filter { input }
+    | State::Pipe { input, .. } = screen.state
+    {
+        let prompt = match screen.state {
+            State::Search { scope: None, .. } => "/",
+            State::Search { .. } => "s/",
+            State::Filter { .. } => "&",
+            State::Pipe { .. } => "|",
+            _ => ":",
+        };
+        recent_panel(f, screen.recent, area);
+        f.render_widget(Paragraph::new(format!("{prompt}{input}")), area);
+        f.set_cursor(
+            area.x + (prompt.len() + input.chars().count()) as u16,
+            area.y,
+        );
+    } else if let State::Write { input, whole, .. } = screen.state {
+        let prompt = if *whole {
+            "Write all lines to: "
+        } else {
+            "Write the context to: "
+        };
+        f.render_widget(Paragraph::new(format!("{prompt}{input}")), area);
+        f.set_cursor(
+            area.x + (prompt.len() + input.chars().count()) as u16,
+            area.y,
+        );
+    } else if let State::Option | State::FilterKey | State::Mark { .. } = screen.state {
+        let prompt = match screen.state {
+            State::FilterKey => "!",
+            State::Mark { goto: false } => "m",
+            State::Mark { goto: true } => "'",
+            _ => "-",
+        };
+        f.render_widget(Paragraph::new(prompt), area);
+        f.set_cursor(area.x + 1, area.y);
+    } else if let State::Header { items, selected } = screen.state {
+        let item = &items[*selected];
+        f.render_widget(
+            Paragraph::new(Span::styled(
+                format!(
+                    "Enter: {} {}  y: copy  Tab: next  Esc: back",
+                    item.action(),
+                    item.text()
+                ),
+                screen.theme.status,
+            )),
+            area,
+        );
+    } else if let State::Confirm { action } = screen.state {
+        f.render_widget(
+            Paragraph::new(Span::styled(action.prompt(), screen.theme.status)),
+            area,
+        );
+    } else if let Some(message) = screen.message {
+        f.render_widget(
+            Paragraph::new(Span::styled(message, screen.theme.status)),
+            area,
+        );
+    }
+}
+
+/// Borrows the visible lines into a `Text` without copying them into a new
+/// buffer on every frame. Lines from merged inputs are tagged and colored by
+/// their source.
+fn visible_text<'a>(screen: &Screen<'a>, width: usize) -> Text<'a> {
+    Text::from(
+        screen
+            .visible
+            .iter()
+            .enumerate()
+            .flat_map(|(row, &num)| line_rows(screen, Some(row), num, width))
+            .collect::<Vec<Spans>>(),
+    )
+}
+
+/// Rows showing buffer line `num`, the `row`th of the visible lines if it is
+/// one, which are several for expanded JSON and hex dumps, and for lines
+/// longer than `width` when wrapping.
+fn line_rows<'a>(
+    screen: &Screen<'a>,
+    row: Option<usize>,
+    num: usize,
+    width: usize,
+) -> Vec<Spans<'a>> {
+    let tag_width = screen
+        .source_names
+        .iter()
+        .map(|name| name.len())
+        .max()
+        .unwrap_or(0);
+    // Expanded JSON and hex dumps are shown as is, as selecting columns from
+    // them wouldn't make sense
+    let expanded_json = screen.expanded_json.get(&num);
+    let filtered = screen
+        .preprocessor
+        .and_then(|preprocessor| preprocessor.rows(num));
+    let line = screen.lines.line(num);
+    // The filter colors lines itself
+    let kind = DiffLine::of(&line).filter(|_| filtered.is_none());
+    let contents: Vec<Cow<str>> = match (expanded_json, filtered) {
+        _ if screen.hex_view => {
+            let bytes = screen
+                .raw_lines
+                .get(&num)
+                .map_or(line.as_bytes(), Vec::as_slice);
+            hex::dump(bytes).into_iter().map(Cow::Owned).collect()
+        }
+        (Some(pretty), _) => pretty
+            .iter()
+            .map(|line| screen.rewriter.apply(line))
+            .collect(),
+        (None, Some(filtered)) => filtered
+            .iter()
+            .map(|line| Cow::Borrowed(line.as_str()))
+            .collect(),
+        (None, None) => vec![match (
+            screen.json_log.and_then(|json_log| json_log.render(&line)),
+            screen.columns,
+        ) {
+            (Some(rendered), columns) => {
+                let line = match columns {
+                    Some(columns) => columns.apply(&rendered),
+                    None => rendered,
+                };
+                Cow::Owned(screen.rewriter.apply(&line).into_owned())
+            }
+            (None, Some(columns)) => {
+                Cow::Owned(screen.rewriter.apply(&columns.apply(&line)).into_owned())
+            }
+            // Lines read back from a spilled buffer are owned
+            (None, None) => match line {
+                Cow::Borrowed(line) => screen.rewriter.apply(line),
+                Cow::Owned(line) => Cow::Owned(screen.rewriter.apply(&line).into_owned()),
+            },
+        }],
+    };
+    contents
+        .into_iter()
+        .enumerate()
+        .flat_map(|(index, line)| {
+            let mut spans = gutter_spans(screen, row.filter(|_| index == 0));
+            let style = match screen.line_sources.get(num) {
+                Some(&source) => {
+                    let style = screen.theme.source(source);
+                    spans.push(Span::styled(
+                        format!("{:tag_width$} │ ", screen.source_names[source]),
+                        style.add_modifier(Modifier::BOLD),
+                    ));
+                    style
+                }
+                None if screen.color_diff && !screen.hex_view => {
+                    kind.map_or_else(Style::default, |kind| screen.theme.diff(kind))
+                }
+                None => Style::default(),
+            };
+            let style = match screen.boundaries {
+                Some((start, end)) if index == 0 && (num == start || end == Some(num)) => {
+                    style.patch(screen.theme.boundary)
+                }
+                _ => style,
+            };
+            let search = screen.search.filter(|search| search.covers(num));
+            let highlighted = highlight(line, search, style, screen.theme.search);
+            if !screen.wrap {
+                spans.extend(highlighted);
+                return vec![Spans::from(spans)];
+            }
+            // The rows continuing the line are indented past the gutter and
+            // the tag
+            let indent: usize = spans.iter().map(Span::width).sum();
+            wrap::wrap(highlighted, width.saturating_sub(indent))
+                .into_iter()
+                .enumerate()
+                .map(|(wrapped, row)| {
+                    let mut row_spans = match wrapped {
+                        0 => std::mem::take(&mut spans),
+                        _ => vec![Span::raw(" ".repeat(indent))],
+                    };
+                    row_spans.extend(row);
+                    Spans::from(row_spans)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Number of `lines`, taken in order, whose rows fit on `height` screen rows
+/// of `width` columns, at least one so that a line taller than the screen
+/// still shows.
+fn fitting<'a>(
+    screen: &Screen,
+    lines: impl Iterator<Item = &'a usize>,
+    width: usize,
+    height: usize,
+) -> usize {
+    let mut rows = 0;
+    let mut fit = 0;
+    for &num in lines {
+        rows += line_rows(screen, None, num, width).len();
+        if rows > height {
+            break;
+        }
+        fit += 1;
+    }
+    std::cmp::max(fit, 1)
+}
+
+/// Gutter columns of the `row`th of the visible lines, or blank ones for the
+/// rows continuing a line.
+fn gutter_spans<'a>(screen: &Screen, row: Option<usize>) -> Vec<Span<'a>> {
+    screen
+        .annotations
+        .iter()
+        .map(|(width, annotations)| {
+            let annotation = row
+                .and_then(|row| annotations[row].as_deref())
+                .unwrap_or("");
+            Span::styled(format!("{annotation:width$} "), screen.theme.gutter)
+        })
+        .collect()
+}
+
+/// The visible lines with the removed and added lines of diff hunks side by
+/// side in `width` columns, paired up only on screen so that large patches
+/// cost nothing more. Other lines are shown as usual across the whole width.
+fn side_by_side_text<'a>(screen: &Screen<'a>, width: usize) -> Text<'a> {
+    let gutter_width: usize = screen.annotations.iter().map(|(width, _)| width + 1).sum();
+    // Each side without the separator between them
+    let half = width.saturating_sub(gutter_width + 1) / 2;
+    let texts: Vec<Cow<str>> = screen
+        .visible
+        .iter()
+        .map(|num| screen.lines.line(*num))
+        .collect();
+    let in_hunk = screen
+        .visible
+        .first()
+        .is_some_and(|num| hunks::hunk(screen.lines, *num).is_some());
+    let side = |row: Option<usize>, kind: DiffLine| -> Vec<Span<'a>> {
+        let Some(row) = row else {
+            return vec![Span::raw(" ".repeat(half))];
+        };
+        let num = screen.visible[row];
+        let line = screen.rewriter.apply(&texts[row]);
+        let line: String = line.chars().take(half).collect();
+        // Context lines are on both sides
+        let style = match (screen.color_diff, line.starts_with(' ')) {
+            (true, false) => screen.theme.diff(kind),
+            _ => Style::default(),
+        };
+        let search = screen.search.filter(|search| search.covers(num));
+        highlight(
+            Cow::Owned(format!("{line:half$}")),
+            search,
+            style,
+            screen.theme.search,
+        )
+    };
+    let rows = side_by_side::rows(texts.iter().map(|text| text.as_ref()), in_hunk);
+    Text::from(
+        rows.into_iter()
+            .flat_map(|row| match row {
+                Row::Full(row) => line_rows(screen, Some(row), screen.visible[row], width),
+                Row::Split(left, right) => {
+                    let mut spans = gutter_spans(screen, left.or(right));
+                    spans.extend(side(left, DiffLine::Removed));
+                    spans.push(Span::styled("│", screen.theme.border));
+                    spans.extend(side(right, DiffLine::Added));
+                    vec![Spans::from(spans)]
+                }
+            })
+            .collect::<Vec<Spans>>(),
+    )
+}
+
+/// Splits `line` into spans with the matches of `search` highlighted. When
+/// wrapping, `wrap::wrap` lays the spans out on rows afterwards, carrying the
+/// highlight of a match over to the next row.
+fn highlight<'a>(
+    line: Cow<'a, str>,
+    search: Option<&Search>,
+    style: Style,
+    match_style: Style,
+) -> Vec<Span<'a>> {
+    let ranges = search.map_or_else(Vec::new, |search| search.highlights(&line));
+    if ranges.is_empty() {
+        return vec![Span::styled(line, style)];
+    }
+    let part = |range: Range<usize>| -> Cow<'a, str> {
+        match &line {
+            Cow::Borrowed(text) => {
+                let text: &'a str = text;
+                Cow::Borrowed(&text[range])
+            }
+            Cow::Owned(text) => Cow::Owned(text[range].to_string()),
+        }
+    };
+    let mut spans = Vec::new();
+    let mut end = 0;
+    for range in ranges {
+        if range.start > end {
+            spans.push(Span::styled(part(end..range.start), style));
+        }
+        end = range.end;
+        spans.push(Span::styled(part(range), match_style));
+    }
+    if end < line.len() {
+        spans.push(Span::styled(part(end..line.len()), style));
+    }
+    spans
+}
+
+/// Popup listing the merged inputs with a checkbox for showing each one.
+fn sources_panel<B: Backend>(f: &mut Frame<B>, screen: &Screen, selected: usize) {
+    let items: Vec<Spans> = screen
+        .source_names
+        .iter()
+        .zip(screen.hidden_sources)
+        .enumerate()
+        .map(|(source, (name, hidden))| {
+            let checkbox = if *hidden { "[ ] " } else { "[x] " };
+            let mut style = screen.theme.source(source);
+            if source == selected {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            Spans::from(Span::styled(format!("{checkbox}{name}"), style))
+        })
+        .collect();
+    let width = screen
+        .source_names
+        .iter()
+        .map(|name| name.len() as u16 + 6)
+        .max()
+        .unwrap_or(0)
+        .max(20);
+    let area = centered_rect(width, items.len() as u16 + 2, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(items).block(Block::default().borders(Borders::ALL).title("Sources")),
+        area,
+    );
+}
+
+/// Popup listing the git commands that can be run on the current commit.
+fn actions_panel<B: Backend>(f: &mut Frame<B>, hash: &str, selected: usize) {
+    let short_hash = &hash[..std::cmp::min(hash.len(), 7)];
+    let items: Vec<Spans> = COMMIT_ACTIONS
+        .iter()
+        .enumerate()
+        .map(|(index, (action, _))| {
+            let style = if index == selected {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            Spans::from(Span::styled(format!("git {action} {short_hash}"), style))
+        })
+        .collect();
+    let area = centered_rect(32, items.len() as u16 + 2, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(items).block(Block::default().borders(Borders::ALL).title("Run")),
+        area,
+    );
+}
+
+/// Popup listing the URLs and paths found, scrolled to keep the selected one
+/// visible.
+fn links_panel<B: Backend>(f: &mut Frame<B>, items: &[String], selected: usize) {
+    let width = items
+        .iter()
+        .map(|item| item.chars().count() as u16 + 4)
+        .max()
+        .unwrap_or(0)
+        .max(40);
+    let area = centered_rect(width, items.len() as u16 + 2, f.size());
+    let rows = area.height.saturating_sub(2).max(1) as usize;
+    let offset = (selected + 1).saturating_sub(rows);
+    let lines: Vec<Spans> = items
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .map(|(index, item)| {
+            let style = if index == selected {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            Spans::from(Span::styled(item.as_str(), style))
+        })
+        .collect();
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Enter to open, y to copy"),
+        ),
+        area,
+    );
+}
+
+/// Popup listing what can be copied of a commit, with the first line of
+/// each.
+fn yank_panel<B: Backend>(f: &mut Frame<B>, choices: &[(YankFormat, String)], selected: usize) {
+    let label_width = choices
+        .iter()
+        .map(|(format, _)| format.label().len())
+        .max()
+        .unwrap_or(0);
+    let lines: Vec<Spans> = choices
+        .iter()
+        .enumerate()
+        .map(|(index, (format, text))| {
+            let style = if index == selected {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            let first_line = text.lines().next().unwrap_or("");
+            Spans::from(Span::styled(
+                format!("{:label_width$}  {first_line}", format.label()),
+                style,
+            ))
+        })
+        .collect();
+    let area = centered_rect(72, lines.len() as u16 + 2, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Copy")),
+        area,
+    );
+}
+
+/// Pane listing the commits read so far, keeping `selected` in view.
+fn commits_pane<B: Backend>(f: &mut Frame<B>, screen: &Screen, selected: usize, area: Rect) {
+    let rows = area.height.saturating_sub(2).max(1) as usize;
+    let offset = (selected + 1).saturating_sub(rows);
+    let lines: Vec<Spans> = screen
+        .commits
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(rows)
+        .map(|(index, commit)| {
+            let style = if index == selected {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            Spans::from(vec![
+                Span::styled(&commit.hash[..7], screen.theme.gutter.patch(style)),
+                Span::styled(format!(" {}", commit.subject), style),
+            ])
+        })
+        .collect();
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::RIGHT)
+                .border_style(screen.theme.border)
+                .title(format!("Commits ({})", screen.commits.len())),
+        ),
+        area,
+    );
+}
+
+/// Popup listing the key bindings and the current input type.
+fn help_panel<B: Backend>(f: &mut Frame<B>, screen: &Screen) {
+    let key_width = KEY_BINDINGS
+        .iter()
+        .map(|(keys, _)| keys.len())
+        .max()
+        .unwrap_or(0);
+    let input_type = screen
+        .input_type
+        .to_possible_value()
+        .map_or_else(String::new, |value| value.get_name().to_string());
+    let mut lines = vec![
+        Spans::from(format!("Input type: {input_type}")),
+        Spans::from(""),
+    ];
+    lines.extend(KEY_BINDINGS.iter().map(|(keys, action)| {
+        Spans::from(vec![
+            Span::styled(
+                format!("{keys:key_width$}  "),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(*action),
+        ])
+    }));
+    let width = lines.iter().map(|line| line.width()).max().unwrap_or(0) as u16 + 4;
+    let area = centered_rect(width, lines.len() as u16 + 2, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Help (press any key to close)"),
+        ),
+        area,
+    );
+}
+
+/// Popup above the prompt in `prompt_area` listing the recent searches and
+/// commands with the keys re-running them.
+fn recent_panel<B: Backend>(f: &mut Frame<B>, recent: &History, prompt_area: Rect) {
+    let lines: Vec<Spans> = recent
+        .entries()
+        .enumerate()
+        .map(|(index, entry)| {
+            Spans::from(vec![
+                Span::styled(
+                    format!("Alt-{}  ", index + 1),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(entry),
+            ])
+        })
+        .collect();
+    if lines.is_empty() {
+        return;
+    }
+    let width = lines.iter().map(|line| line.width()).max().unwrap_or(0) as u16 + 2;
+    let height = std::cmp::min(lines.len() as u16 + 2, prompt_area.y);
+    let area = Rect::new(
+        prompt_area.x,
+        prompt_area.y - height,
+        std::cmp::min(width, prompt_area.width),
+        height,
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Recent")),
+        area,
+    );
+}
+
+/// Popup showing `lines`, like the result of `:topk`.
+fn overlay_panel<B: Backend>(f: &mut Frame<B>, title: &str, lines: &[String]) {
+    let title = format!("{title} (press any key to close)");
+    let lines: Vec<Spans> = if lines.is_empty() {
+        vec![Spans::from("Nothing found")]
+    } else {
+        lines
+            .iter()
+            .map(|line| Spans::from(line.as_str()))
+            .collect()
+    };
+    let width = lines
+        .iter()
+        .map(|line| line.width())
+        .chain([title.chars().count()])
+        .max()
+        .unwrap_or(0) as u16
+        + 4;
+    let area = centered_rect(width, lines.len() as u16 + 2, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)),
+        area,
+    );
+}
+
+/// Covers the pager with a derived buffer, under a bar listing the tabs.
+fn tab_panel<B: Backend>(f: &mut Frame<B>, tabs: &[Tab], index: usize, theme: &Theme) {
+    let size = f.size();
+    let area = Rect::new(size.x, size.y, size.width, size.height.saturating_sub(1));
+    f.render_widget(Clear, area);
+    let area = area.inner(&Margin {
+        horizontal: 1,
+        vertical: 1,
+    });
+    let mut bar = vec![Span::raw(" input ")];
+    for (tab_index, tab) in tabs.iter().enumerate() {
+        bar.push(Span::styled("│", theme.border));
+        let title = format!(" {} ", tab.title);
+        bar.push(if tab_index == index {
+            Span::styled(title, Style::default().add_modifier(Modifier::REVERSED))
+        } else {
+            Span::raw(title)
+        });
+    }
+    let tab = &tabs[index];
+    let height = tab_height(size.height);
+    let mut lines = vec![Spans::from(bar)];
+    lines.extend(
+        tab.lines
+            .iter()
+            .skip(tab.viewport.position)
+            .take(height)
+            .map(|line| Spans::from(line.as_str())),
+    );
+    f.render_widget(Paragraph::new(lines), area);
+}
+
+/// A `width` x `height` rectangle centered in `area`, clamped to fit.
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = std::cmp::min(width, area.width);
+    let height = std::cmp::min(height, area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Draws `core` as cag would on a `width` by `height` terminal, with the
+/// search prompt open on `search` and `message` on the bottom line if given,
+/// and returns the text of each row, for golden file tests.
+#[cfg(any(test, feature = "test-backend"))]
+pub fn render(
+    core: &mut PagerState,
+    width: u16,
+    height: u16,
+    search: Option<&str>,
+    message: Option<&str>,
+) -> Result<String, Error> {
+    let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(width, height))?;
+    let visible = core.view.lines(
+        core.viewport.position..core.viewport.position + height as usize,
+        core.lines.len(),
+    );
+    let context = panel_context(
+        &*core.finder,
+        &core.lines,
+        &core.view,
+        core.viewport.position,
+    );
+    let state = match search {
+        Some(input) => State::Search {
+            input: input.to_string(),
+            origin: core.viewport.position,
+            scope: None,
+            recalled: None,
+        },
+        None => State::Pager { count: None },
+    };
+    let status: Vec<String> = [
+        core.search.as_ref().map(Search::status),
+        Some(match core.lines.len() {
+            1 => "1 line".to_string(),
+            lines => format!("{lines} lines"),
+        }),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let screen = Screen {
+        lines: &core.lines,
+        visible: &visible,
+        tail: &[],
+        line_sources: &[],
+        source_names: &[],
+        hidden_sources: &[],
+        expanded_json: &HashMap::new(),
+        raw_lines: &HashMap::new(),
+        hex_view: false,
+        side_by_side: false,
+        wrap: false,
+        preprocessor: None,
+        context: &context,
+        boundaries: visible
+            .first()
+            .and_then(|line| core.finder.boundaries(&core.lines, *line)),
+        show_context: true,
+        context_height: <Args as clap::Parser>::parse_from(["cag"]).context_height,
+        context_scroll: 0,
+        state: &state,
+        message,
+        status: Some(status.join(" │ ")),
+        stderr: &[],
+        stderr_written: 0,
+        waiting: false,
+        search: core.search.as_ref(),
+        tabs: &[],
+        commits: &[],
+        alerting: false,
+        rewriter: &Rewriter::new(&[])?,
+        columns: None,
+        json_log: None,
+        annotations: Vec::new(),
+        theme: &Theme::dark(),
+        recent: &History::default(),
+        color_diff: true,
+        dim: false,
+        ascii: false,
+        input_type: InputType::Git,
+    };
+    terminal.draw(|frame| pager(frame, &screen, &mut core.viewport))?;
+    Ok(buffer_text(&terminal))
+}
+
+/// The text on the screen of a test terminal, without trailing spaces.
+#[cfg(any(test, feature = "test-backend"))]
+fn buffer_text(terminal: &Terminal<ratatui::backend::TestBackend>) -> String {
+    let width = terminal.backend().buffer().area.width;
+    let rows: Vec<String> = terminal
+        .backend()
+        .buffer()
+        .content
+        .chunks(width as usize)
+        .map(|row| {
+            let text: String = row.iter().map(|cell| cell.symbol.as_str()).collect();
+            text.trim_end().to_string()
+        })
+        .collect();
+    rows.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::VecDeque,
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use clap::CommandFactory;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui::{backend::TestBackend, style::Color, Terminal};
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::{buffer_text, current_context, render, run_app, Input};
+    use crate::{
+        alert::Alerts,
+        args::Args,
+        config::Config,
+        context_finder::{builtin, InputType},
+        diff::ColorMode,
+        extract::Extractor,
+        files::FileList,
+        input::{Chunk, Pause},
+        pager_state::{PagerEvent, PagerState},
+        rewrite::Rewriter,
+        theme::Theme,
+    };
+
+    const GIT_LOG: &str = include_str!("../tests/data/git_patch");
+
+    fn git_pager() -> PagerState {
+        let mut pager = PagerState::new(builtin(InputType::Git, None), 20);
+        let lines = GIT_LOG.lines().map(String::from).collect();
+        pager.handle(PagerEvent::NewLines(lines)).unwrap();
+        pager
+    }
+
+    /// Pages `GIT_LOG` as if read from `files`, presses `keys` and quits,
+    /// returning the last screen drawn and whether the pager was closed to
+    /// read the input again.
+    fn press_keys(keys: &str, files: &mut FileList) -> (String, bool) {
+        let args = <Args as clap::Parser>::parse_from(["cag"]);
+        let buffered = Chunk::from(GIT_LOG.lines().map(String::from).collect::<Vec<_>>());
+        let terminal = page(keys, args, &Config::default(), buffered, Vec::new(), files);
+        (buffer_text(&terminal), files.take_switch())
+    }
+
+    /// Pages `buffered`, the lines of `source_names` if merging, presses
+    /// `keys`, with a newline for Enter and `\x03` for Ctrl-C, and quits.
+    fn page(
+        keys: &str,
+        args: Args,
+        config: &Config,
+        buffered: Chunk,
+        source_names: Vec<String>,
+        files: &mut FileList,
+    ) -> Terminal<TestBackend> {
+        let mut terminal = Terminal::new(TestBackend::new(80, 20)).unwrap();
+        let (_, rx) = unbounded_channel();
+        let input = Input {
+            rx,
+            pause: Pause::default(),
+            source_names,
+            buffered,
+            files,
+            background: None,
+            finder: None,
+            exec: None,
+            keys: keys
+                .chars()
+                .chain(['q'])
+                .map(|key| match key {
+                    '\n' => KeyEvent::from(KeyCode::Enter),
+                    '\x03' => KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+                    key => KeyEvent::from(KeyCode::Char(key)),
+                })
+                .collect::<VecDeque<_>>(),
+        };
+        run_app(
+            &mut terminal,
+            args,
+            input,
+            config,
+            &Rewriter::new(&config.rewrite).unwrap(),
+            &Extractor::new(&config.extract).unwrap(),
+            &Alerts::new(&config.alerts).unwrap(),
+        )
+        .unwrap();
+        terminal
+    }
+
+    /// Compares `rendered` with the golden file `name` in
+    /// `tests/data/render`, writing it instead with `UPDATE_GOLDEN=1`.
+    fn assert_golden(name: &str, rendered: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/data/render")
+            .join(name);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            fs::write(&path, rendered).unwrap();
+        }
+        assert_eq!(rendered, fs::read_to_string(&path).unwrap());
+    }
+
+    #[test]
+    fn render_context_panel() {
+        let mut pager = git_pager();
+        pager.handle(PagerEvent::ScrollDown(30)).unwrap();
+        let rendered = render(&mut pager, 80, 20, None, None).unwrap();
+        assert_golden("context_panel", &rendered);
+    }
+
+    #[test]
+    fn render_search_box() {
+        let mut pager = git_pager();
+        pager
+            .handle(PagerEvent::Search("thiserror".to_string()))
+            .unwrap();
+        let rendered = render(&mut pager, 80, 20, Some("thiserror"), None).unwrap();
+        assert_golden("search_box", &rendered);
+    }
+
+    #[test]
+    fn render_status_bar() {
+        let mut pager = git_pager();
+        pager
+            .handle(PagerEvent::Search("Cargo".to_string()))
+            .unwrap();
+        pager.handle(PagerEvent::NextMatch).unwrap();
+        let message = Some("Resumed reading input");
+        let rendered = render(&mut pager, 80, 12, None, message).unwrap();
+        assert_golden("status_bar", &rendered);
+    }
+
+    #[test]
+    fn reset_and_reload_a_file() {
+        let mut files = FileList::new(vec![PathBuf::from("git.log")]);
+        // `H` goes back to the start rather than reading the file again
+        let (screen, reloaded) = press_keys("GH", &mut files);
+        assert!(!reloaded);
+        assert!(screen.contains("Back to the initial view"));
+        assert!(screen.lines().nth(1).unwrap().contains("commit "));
+        // `r` only runs `--exec` again
+        let (_, reloaded) = press_keys("Gr", &mut files);
+        assert!(!reloaded);
+        let (_, reloaded) = press_keys("GR", &mut files);
+        assert!(reloaded);
+        assert!(files.place().is_some_and(|place| place.line > 0));
+    }
+
+    #[test]
+    fn quit_with_ctrl_c_from_a_prompt() {
+        let mut files = FileList::default();
+        for keys in ["/xqz\x03w", ":xqz\x03w", "&xqz\x03w"] {
+            let (screen, _) = press_keys(keys, &mut files);
+            assert!(screen.contains("xqz"), "{keys:?}");
+            assert!(!screen.contains("xqzc"), "{keys:?}");
+        }
+    }
+
+    #[test]
+    fn reset_toggles() {
+        let mut files = FileList::default();
+        let (initial, _) = press_keys("", &mut files);
+        // Side by side, context hidden, time column and hex dump, scrolled
+        let (toggled, _) = press_keys("StDxjjj", &mut files);
+        assert_ne!(toggled, initial);
+        let (screen, _) = press_keys("StDxjjjH", &mut files);
+        let rows = |screen: &str| screen.lines().take(18).collect::<Vec<_>>().join("\n");
+        assert_eq!(rows(&screen), rows(&initial));
+        assert!(screen.contains("Back to the initial view"));
+    }
+
+    #[test]
+    fn same_context_as_the_keys_acting_on_it() {
+        let mut pager = git_pager();
+        // The header of the first commit is on top, and counts
+        assert_eq!(pager.context()[0][0], pager.lines().line(0));
+        for _ in 0..pager.lines().len() {
+            let top = pager.top_line();
+            let line = pager
+                .view
+                .lines(
+                    pager.viewport.position..pager.viewport.position + 1,
+                    pager.lines().len(),
+                )
+                .first()
+                .copied()
+                .unwrap();
+            assert_eq!(top, line);
+            assert_eq!(
+                pager.context(),
+                current_context(&*pager.finder, &pager.lines, line)
+            );
+            pager.handle(PagerEvent::ScrollDown(1)).unwrap();
+        }
+    }
+
+    #[test]
+    fn highlight_matches_across_wrapped_rows() {
+        let line = format!("{}needle and more", "x".repeat(76));
+        let draw = |keys: &str| {
+            let args = <Args as clap::Parser>::parse_from(["cag", "--type", "syslog"]);
+            let buffered = Chunk::from(vec![line.clone(), "next".to_string()]);
+            let mut files = FileList::default();
+            page(
+                keys,
+                args,
+                &Config::default(),
+                buffered,
+                Vec::new(),
+                &mut files,
+            )
+        };
+        let terminal = draw("/needle\n");
+        assert!(!buffer_text(&terminal).contains("and more"));
+
+        let terminal = draw("W/needle\n");
+        let screen = buffer_text(&terminal);
+        let rows: Vec<&str> = screen.lines().map(str::trim).collect();
+        // The text is inset by a column on each side
+        let first = rows.iter().position(|row| row.starts_with("xxx")).unwrap();
+        assert!(rows[first].ends_with("xne"));
+        assert_eq!(rows[first + 1], "edle and more");
+        assert_eq!(rows[first + 2], "next");
+        // The match is highlighted on both rows
+        let buffer = terminal.backend().buffer();
+        let search = Theme::dark().search;
+        let highlighted: Vec<(u16, &str)> = buffer
+            .content
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| cell.style() == search)
+            .map(|(index, cell)| (index as u16 / buffer.area.width, cell.symbol.as_str()))
+            .collect();
+        let first = first as u16;
+        assert_eq!(
+            highlighted,
+            [
+                (first, "n"),
+                (first, "e"),
+                (first + 1, "e"),
+                (first + 1, "d"),
+                (first + 1, "l"),
+                (first + 1, "e"),
+            ]
+        );
+    }
+
+    #[test]
+    fn page_to_the_bottom_of_wrapped_lines() {
+        // Each line takes three rows when wrapped
+        let lines: Vec<String> = (0..40)
+            .map(|num| format!("line {num:02} {}", "x".repeat(150)))
+            .chain(["line 40 needle".to_string()])
+            .collect();
+        let draw = |keys: &str| {
+            let args = <Args as clap::Parser>::parse_from(["cag", "--type", "syslog"]);
+            let mut files = FileList::default();
+            let buffered = Chunk::from(lines.clone());
+            buffer_text(&page(
+                keys,
+                args,
+                &Config::default(),
+                buffered,
+                Vec::new(),
+                &mut files,
+            ))
+        };
+        for keys in ["WG", "WF", "W/needle\n", "W/line 39\n"] {
+            let screen = draw(keys);
+            assert!(screen.contains("line 40"), "{keys:?}");
+        }
+        // The match is at the top when it can be
+        let screen = draw("W/line 20\n");
+        let first = screen.lines().find(|row| row.contains("line ")).unwrap();
+        assert!(first.contains("line 20"));
+    }
+
+    #[test]
+    fn draw_without_colors() {
+        let no_color = |args: &[&str], value: &str| {
+            let matches = Args::command().get_matches_from(args);
+            super::no_color(&matches, |_| Some(value.into()))
+        };
+        assert!(no_color(&["cag"], "1"));
+        assert!(!no_color(&["cag"], ""));
+        assert!(!no_color(&["cag", "--color", "always"], "1"));
+
+        let source_names = vec!["a.log".to_string(), "b.log".to_string()];
+        let draw = |monochrome: bool| {
+            let mut args = <Args as clap::Parser>::parse_from(["cag"]);
+            let mut config = Config::default();
+            if monochrome {
+                args.color = ColorMode::Never;
+                config.theme.monochrome = true;
+            }
+            // Merged lines of a patch, tagged with their input
+            let lines: Vec<String> = GIT_LOG.lines().map(String::from).collect();
+            let buffered = Chunk {
+                sources: (0..lines.len()).map(|line| line % 2).collect(),
+                lines,
+                ..Default::default()
+            };
+            let mut files = FileList::default();
+            let terminal = page(
+                "",
+                args,
+                &config,
+                buffered,
+                source_names.clone(),
+                &mut files,
+            );
+            let colored = terminal
+                .backend()
+                .buffer()
+                .content
+                .iter()
+                .any(|cell| cell.fg != Color::Reset || cell.bg != Color::Reset);
+            (buffer_text(&terminal), colored)
+        };
+        let (screen, colored) = draw(false);
+        assert!(screen.contains("b.log"));
+        assert!(colored);
+        let (screen, colored) = draw(true);
+        assert!(screen.contains("b.log"));
+        assert!(!colored);
+    }
+}
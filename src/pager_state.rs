@@ -0,0 +1,271 @@
+use std::borrow::Cow;
+
+use crate::{
+    context_finder::ContextFinder,
+    error::Error,
+    search::{Search, SearchOptions},
+    storage::{LineBuffer, Storage},
+    view::FilterView,
+    viewport::Viewport,
+};
+
+/// Something happening to the pager, whether the user did it or the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PagerEvent {
+    /// Scroll down this many rows, no further than the last screen.
+    ScrollDown(usize),
+    ScrollUp(usize),
+    Top,
+    Bottom,
+    /// The screen now has room for this many rows.
+    Resize(usize),
+    /// Lines read from the input. A filtered view is extended with the ones
+    /// it shows by whoever filters it.
+    NewLines(Vec<String>),
+    /// Search for a term from the top of the screen, or stop searching with
+    /// an empty one.
+    Search(String),
+    NextMatch,
+    PreviousMatch,
+    /// Keep the last lines on screen as more are read, or stop.
+    Follow(bool),
+}
+
+/// The lines read, what part of them is on screen and where the search is,
+/// without a terminal: `handle` is driven by the terminal in cag, and can be
+/// by tests or any other frontend.
+pub struct PagerState {
+    pub(crate) lines: LineBuffer,
+    pub(crate) view: FilterView,
+    pub(crate) viewport: Viewport,
+    pub(crate) finder: Box<dyn ContextFinder>,
+    pub(crate) search: Option<Search>,
+    pub(crate) search_options: SearchOptions,
+    /// Whether the last screen is kept in view as lines are read.
+    pub(crate) following: bool,
+}
+
+impl PagerState {
+    /// Pages nothing yet on a screen of `height` rows, finding contexts with
+    /// `finder`.
+    pub fn new(finder: Box<dyn ContextFinder>, height: usize) -> Self {
+        PagerState::with_lines(finder, height, LineBuffer::new(None))
+    }
+
+    pub(crate) fn with_lines(
+        finder: Box<dyn ContextFinder>,
+        height: usize,
+        lines: LineBuffer,
+    ) -> Self {
+        PagerState {
+            lines,
+            view: FilterView::default(),
+            viewport: Viewport::new(height),
+            finder,
+            search: None,
+            search_options: SearchOptions::default(),
+            following: false,
+        }
+    }
+
+    /// Updates the state for `event`, returning a message for the user, like
+    /// that the search wrapped around.
+    pub fn handle(&mut self, event: PagerEvent) -> Result<Option<String>, Error> {
+        let num_rows = self.num_rows();
+        match event {
+            PagerEvent::ScrollDown(rows) => self.viewport.scroll_down(rows, num_rows),
+            PagerEvent::ScrollUp(rows) => self.viewport.scroll_up(rows),
+            PagerEvent::Top => self.viewport.position = 0,
+            PagerEvent::Bottom => self.viewport.position = self.viewport.max_scroll(num_rows),
+            PagerEvent::Resize(height) => {
+                self.viewport.height = height;
+                // Fill a taller screen rather than leave it empty at the end
+                self.viewport.position = if self.following {
+                    self.viewport.max_scroll(num_rows)
+                } else {
+                    self.viewport.clamp(self.viewport.position, num_rows)
+                };
+            }
+            PagerEvent::NewLines(lines) => {
+                self.lines.extend(lines)?;
+                self.finder.extend(&self.lines);
+                if let Some(search) = &mut self.search {
+                    search.extend(&self.lines);
+                }
+                if self.following {
+                    self.viewport.position = self.viewport.max_scroll(self.num_rows());
+                }
+            }
+            PagerEvent::Search(term) => {
+                self.search = Search::new(&term, &self.lines, self.search_options);
+                let top = self.top_line();
+                // Staying put if there is no match
+                if let Some(line) = self
+                    .search
+                    .as_mut()
+                    .and_then(|search| search.first_from(top))
+                {
+                    self.viewport.position = self.show_line(line);
+                }
+            }
+            PagerEvent::NextMatch | PagerEvent::PreviousMatch => {
+                let Some(search) = &mut self.search else {
+                    return Ok(Some("No search term, / to search".to_string()));
+                };
+                let found = match event {
+                    PagerEvent::NextMatch => search.next(),
+                    _ => search.previous(),
+                };
+                let Some((line, wrapped)) = found else {
+                    return Ok(None);
+                };
+                self.following = false;
+                self.viewport.position = self.show_line(line);
+                if wrapped {
+                    return Ok(Some(match event {
+                        PagerEvent::NextMatch => "Search wrapped to the top".to_string(),
+                        _ => "Search wrapped to the bottom".to_string(),
+                    }));
+                }
+            }
+            PagerEvent::Follow(following) => {
+                self.following = following;
+                if following {
+                    self.viewport.position = self.viewport.max_scroll(num_rows);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// The lines read so far.
+    pub fn lines(&self) -> &dyn Storage {
+        &self.lines
+    }
+
+    /// Buffer lines on screen, top to bottom.
+    pub fn visible(&self) -> Vec<usize> {
+        self.view.lines(self.viewport.rows(), self.lines.len())
+    }
+
+    /// The lines of each level of context of the top line, outermost first.
+    /// Unlike the panel above it, the top line itself counts in case it
+    /// starts a context, like the header of a commit, as it does for the
+    /// keys acting on the current commit.
+    pub fn context(&self) -> Vec<Cow<'_, [String]>> {
+        current_context(&*self.finder, &self.lines, self.top_line())
+    }
+
+    pub fn following(&self) -> bool {
+        self.following
+    }
+
+    /// Number of rows that can be scrolled through.
+    pub(crate) fn num_rows(&self) -> usize {
+        self.view.len(self.lines.len())
+    }
+
+    /// Buffer line at the top of the screen, or the number of lines past the
+    /// last one.
+    pub(crate) fn top_line(&self) -> usize {
+        let position = self.viewport.position;
+        self.view
+            .lines(position..position + 1, self.lines.len())
+            .first()
+            .copied()
+            .unwrap_or(self.lines.len())
+    }
+
+    /// Position showing buffer line `line` at the top, or as close to it as
+    /// scrolling allows.
+    pub(crate) fn show_line(&self, line: usize) -> usize {
+        self.viewport.clamp(self.view.row(line), self.num_rows())
+    }
+}
+
+/// Context of buffer line `line` for acting on it, which unlike the context
+/// shown includes the line itself in case it is a header.
+pub(crate) fn current_context<'a>(
+    cf: &dyn ContextFinder,
+    lines: &'a dyn Storage,
+    line: usize,
+) -> Vec<Cow<'a, [String]>> {
+    cf.get_context(lines, std::cmp::min(line + 1, lines.len()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PagerEvent, PagerState};
+    use crate::context_finder::{builtin, InputType};
+
+    const GIT_LOG: &str = include_str!("../tests/data/git_patch");
+
+    fn git_pager(height: usize) -> PagerState {
+        let mut pager = PagerState::new(builtin(InputType::Git, None), height);
+        let lines = GIT_LOG.lines().map(String::from).collect();
+        pager.handle(PagerEvent::NewLines(lines)).unwrap();
+        pager
+    }
+
+    #[test]
+    fn scroll_and_resize() {
+        let mut pager = git_pager(10);
+        let num_lines = pager.lines().len();
+        assert_eq!(pager.visible(), (0..10).collect::<Vec<_>>());
+        pager.handle(PagerEvent::ScrollDown(5)).unwrap();
+        pager.handle(PagerEvent::ScrollUp(2)).unwrap();
+        assert_eq!(pager.visible()[0], 3);
+        pager.handle(PagerEvent::Bottom).unwrap();
+        assert_eq!(pager.visible().last(), Some(&(num_lines - 1)));
+        // A taller screen shows more of the end rather than empty rows
+        pager.handle(PagerEvent::Resize(20)).unwrap();
+        assert_eq!(pager.visible().len(), 20);
+        assert_eq!(pager.visible().last(), Some(&(num_lines - 1)));
+        pager.handle(PagerEvent::Top).unwrap();
+        assert_eq!(pager.visible()[0], 0);
+    }
+
+    #[test]
+    fn search_and_show_context() {
+        let mut pager = git_pager(10);
+        pager
+            .handle(PagerEvent::Search("Cargo.toml".to_string()))
+            .unwrap();
+        let first = pager.visible()[0];
+        assert!(pager.lines().line(first).contains("Cargo.toml"));
+        assert!(pager.context()[0][0].starts_with("commit "));
+        // Going back from the first match wraps around to the last
+        assert_eq!(
+            pager.handle(PagerEvent::PreviousMatch).unwrap().as_deref(),
+            Some("Search wrapped to the bottom")
+        );
+        assert!(pager.visible()[0] > first);
+        assert_eq!(
+            pager.handle(PagerEvent::NextMatch).unwrap().as_deref(),
+            Some("Search wrapped to the top")
+        );
+        assert_eq!(pager.visible()[0], first);
+        pager
+            .handle(PagerEvent::Search("no such term".to_string()))
+            .unwrap();
+        assert_eq!(pager.visible()[0], first);
+        pager.handle(PagerEvent::Search(String::new())).unwrap();
+        assert_eq!(
+            pager.handle(PagerEvent::NextMatch).unwrap().as_deref(),
+            Some("No search term, / to search")
+        );
+    }
+
+    #[test]
+    fn follow_new_lines() {
+        let mut pager = git_pager(10);
+        pager.handle(PagerEvent::Follow(true)).unwrap();
+        let num_lines = pager.lines().len();
+        pager
+            .handle(PagerEvent::NewLines(vec!["commit 0123abc".to_string()]))
+            .unwrap();
+        assert_eq!(pager.visible().last(), Some(&num_lines));
+        pager.handle(PagerEvent::Follow(false)).unwrap();
+        assert!(!pager.following());
+    }
+}
@@ -0,0 +1,257 @@
+use std::{
+    borrow::Cow,
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, Command, Stdio},
+    sync::{
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        OnceLock,
+    },
+    thread,
+};
+
+use regex::Regex;
+
+use crate::storage::Storage;
+
+/// Lines of the input looked through for the next line of output, past lines
+/// the filter left out.
+const ALIGN_WINDOW: usize = 256;
+
+/// Shortest part of an input line found within a line of output, as filters
+/// like `delta --line-numbers` decorate the lines they show.
+const MIN_CONTAINED: usize = 8;
+
+/// An external filter like `delta` or `diff-so-fancy` the input is piped
+/// through for display. Its output is aligned back onto the lines of the
+/// input, so contexts are still found in the input as it was.
+pub struct Preprocessor {
+    child: Child,
+    /// Sends lines of the input to the filter, until the input ends.
+    input: Option<Sender<Vec<String>>>,
+    output: Receiver<String>,
+    alignment: Alignment,
+}
+
+impl Preprocessor {
+    /// Runs `command` with the shell, fed and read by threads of its own so
+    /// that a slow filter never holds up the pager.
+    pub fn spawn(command: &str) -> io::Result<Self> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let (Some(mut stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(io::Error::other("no pipes to the filter"));
+        };
+        let (input, lines) = mpsc::channel::<Vec<String>>();
+        thread::spawn(move || {
+            for lines in lines {
+                for line in lines {
+                    if writeln!(stdin, "{line}").is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        let (sender, output) = mpsc::channel();
+        thread::spawn(move || {
+            let mut stdout = BufReader::new(stdout);
+            let mut line = Vec::new();
+            while let Ok(1..) = stdout.read_until(b'\n', &mut line) {
+                let text = String::from_utf8_lossy(&line);
+                let text = text.strip_suffix('\n').unwrap_or(&text);
+                let text = text.strip_suffix('\r').unwrap_or(text);
+                if sender.send(text.to_string()).is_err() {
+                    return;
+                }
+                line.clear();
+            }
+        });
+        Ok(Preprocessor {
+            child,
+            input: Some(input),
+            output,
+            alignment: Alignment::default(),
+        })
+    }
+
+    /// Passes lines just read to the filter.
+    pub fn feed(&self, lines: &[String]) {
+        if let Some(input) = &self.input {
+            let _ = input.send(lines.to_vec());
+        }
+    }
+
+    /// Ends the input of the filter, which many only print all of their
+    /// output on.
+    pub fn finish(&mut self) {
+        self.input = None;
+    }
+
+    /// Aligns the output of the filter received so far with the input
+    /// `lines`, returning whether there was any.
+    pub fn receive(&mut self, lines: &dyn Storage) -> bool {
+        let mut received = false;
+        loop {
+            match self.output.try_recv() {
+                Ok(output) => {
+                    self.alignment.push(lines, output);
+                    received = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    received |= !self.alignment.done;
+                    self.alignment.done = true;
+                    break;
+                }
+            }
+        }
+        received
+    }
+
+    /// Lines of output shown for input line `line`, or `None` to show the line
+    /// itself as the filter hasn't got to it yet.
+    pub fn rows(&self, line: usize) -> Option<&[String]> {
+        self.alignment.rows(line)
+    }
+}
+
+impl Drop for Preprocessor {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Lines of output of a filter, grouped by the line of input each shows.
+#[derive(Debug, Default)]
+struct Alignment {
+    rows: Vec<Vec<String>>,
+    /// Line of the input the next line of output is looked for from.
+    next: usize,
+    /// Whether the filter has exited, leaving the lines it didn't get to
+    /// out.
+    done: bool,
+}
+
+impl Alignment {
+    /// Adds a line of output to the first of the next lines of input it
+    /// shows, or to the line before if it shows none of them, like a
+    /// decoration or a header reformatted by the filter.
+    fn push(&mut self, lines: &dyn Storage, output: String) {
+        let plain = strip_escapes(&output);
+        let plain = plain.trim();
+        let end = std::cmp::min(lines.len(), self.next + ALIGN_WINDOW);
+        let found = (!plain.is_empty())
+            .then(|| (self.next..end).find(|line| shows(plain, &lines.line(*line))))
+            .flatten();
+        match found {
+            Some(line) => {
+                // The lines skipped over are left out
+                if self.rows.len() <= line {
+                    self.rows.resize(line + 1, Vec::new());
+                }
+                self.rows[line].push(output);
+                self.next = line + 1;
+            }
+            None => match self.rows.last_mut() {
+                Some(rows) => rows.push(output),
+                None => self.rows.push(vec![output]),
+            },
+        }
+    }
+
+    fn rows(&self, line: usize) -> Option<&[String]> {
+        match self.rows.get(line) {
+            Some(rows) => Some(rows),
+            None if self.done => Some(&[]),
+            None => None,
+        }
+    }
+}
+
+/// Whether the line of output `plain`, without colors and trimmed, shows the
+/// line of input `line`, with or without its diff marker.
+fn shows(plain: &str, line: &str) -> bool {
+    let line = strip_escapes(line);
+    let content = line.strip_prefix(['+', '-', ' ']).unwrap_or(&line).trim();
+    plain == line.trim()
+        || plain == content
+        || (content.len() >= MIN_CONTAINED && plain.contains(content))
+}
+
+/// `text` without ANSI escape sequences, like colors and hyperlinks.
+fn strip_escapes(text: &str) -> Cow<'_, str> {
+    static ESCAPES: OnceLock<Regex> = OnceLock::new();
+    if !text.contains('\x1b') {
+        return text.into();
+    }
+    ESCAPES
+        .get_or_init(|| {
+            Regex::new(r"\x1b(\[[0-9;?]*[ -/]*[@-~]|\][^\x07\x1b]*(\x07|\x1b\\))").unwrap()
+        })
+        .replace_all(text, "")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{strip_escapes, Alignment};
+
+    #[test]
+    fn align_filter_output() {
+        let input: Vec<String> = [
+            "diff --git a/src/main.rs b/src/main.rs",
+            "index 61ff04b..203c02a 100644",
+            "--- a/src/main.rs",
+            "+++ b/src/main.rs",
+            "@@ -3,3 +3,3 @@ use crossterm::{",
+            " };",
+            "-use std::io;",
+            "+use ratatui::{",
+        ]
+        .map(String::from)
+        .to_vec();
+        // Like delta: a boxed file name instead of the file header, and
+        // colored lines without their diff markers
+        let output = [
+            "",
+            "src/main.rs",
+            "───────────",
+            "\x1b[34m3\x1b[0m: use crossterm::{",
+            "};",
+            "\x1b[31muse std::io;\x1b[0m",
+            "\x1b[32muse ratatui::{\x1b[0m",
+        ];
+        let mut alignment = Alignment::default();
+        for line in &output[..5] {
+            alignment.push(&input, line.to_string());
+        }
+        // Decorations go with the line before, and the lines left out show
+        // nothing
+        let decorated: Vec<String> = output[..4].iter().map(|line| line.to_string()).collect();
+        assert_eq!(alignment.rows(0), Some(&decorated[..]));
+        assert_eq!(alignment.rows(4), Some(&[][..]));
+        assert_eq!(alignment.rows(5), Some(&["};".to_string()][..]));
+        // Not yet filtered
+        assert_eq!(alignment.rows(6), None);
+
+        for line in &output[5..] {
+            alignment.push(&input, line.to_string());
+        }
+        assert_eq!(alignment.rows(7), Some(&[output[6].to_string()][..]));
+        alignment.done = true;
+        assert_eq!(alignment.rows(8), Some(&[][..]));
+    }
+
+    #[test]
+    fn strip_colors_and_links() {
+        assert_eq!(strip_escapes("\x1b[1;31m-old\x1b[m"), "-old");
+        assert_eq!(
+            strip_escapes("\x1b]8;;file:///a.rs\x1b\\a.rs\x1b]8;;\x1b\\"),
+            "a.rs"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Length of one throughput sample.
+const BUCKET: Duration = Duration::from_secs(1);
+/// Number of samples kept for the sparkline.
+const HISTORY: usize = 20;
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Tracks how fast lines are read, per second, to show whether a followed
+/// input is still producing anything.
+#[derive(Debug)]
+pub struct Throughput {
+    /// Lines and bytes read in each second, oldest first. The last one is
+    /// still being filled.
+    buckets: VecDeque<(usize, usize)>,
+    bucket_start: Instant,
+}
+
+impl Throughput {
+    pub fn new(now: Instant) -> Self {
+        Throughput {
+            buckets: VecDeque::from([(0, 0)]),
+            bucket_start: now,
+        }
+    }
+
+    pub fn record(&mut self, now: Instant, lines: usize, bytes: usize) {
+        self.advance(now);
+        let current = self.buckets.back_mut().expect("there is always a bucket");
+        current.0 += lines;
+        current.1 += bytes;
+    }
+
+    /// Starts new buckets for the seconds passed since the last one, returning
+    /// whether there were any.
+    pub fn advance(&mut self, now: Instant) -> bool {
+        let mut advanced = false;
+        while now.duration_since(self.bucket_start) >= BUCKET {
+            self.bucket_start += BUCKET;
+            self.buckets.push_back((0, 0));
+            if self.buckets.len() > HISTORY + 1 {
+                self.buckets.pop_front();
+            }
+            advanced = true;
+        }
+        advanced
+    }
+
+    /// Lines and bytes read during the last full second.
+    pub fn rate(&self) -> (usize, usize) {
+        self.complete().last().copied().unwrap_or((0, 0))
+    }
+
+    /// Lines read per second over the last seconds, oldest first, scaled to
+    /// the busiest one. Seconds without any lines are blank.
+    pub fn sparkline(&self) -> String {
+        let complete = self.complete();
+        let max = complete.iter().map(|(lines, _)| *lines).max().unwrap_or(0);
+        complete
+            .iter()
+            .map(|(lines, _)| match lines {
+                0 => ' ',
+                lines => BARS[(lines * (BARS.len() - 1)).div_ceil(max)],
+            })
+            .collect()
+    }
+
+    /// Status line summary like `120 lines/s 9.4 KiB/s ▁▃█▅`.
+    pub fn summary(&self) -> String {
+        let (lines, bytes) = self.rate();
+        format!(
+            "{lines} lines/s {}/s {}",
+            format_bytes(bytes),
+            self.sparkline()
+        )
+    }
+
+    fn complete(&self) -> Vec<(usize, usize)> {
+        self.buckets
+            .iter()
+            .take(self.buckets.len() - 1)
+            .copied()
+            .collect()
+    }
+}
+
+/// Size like `9.4 KiB`.
+pub fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        0..=1023 => format!("{bytes} B"),
+        1024..=1_048_575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        1_048_576..=1_073_741_823 => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+        _ => format!("{:.1} GiB", bytes as f64 / 1_073_741_824.0),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::Throughput;
+
+    #[test]
+    fn rate_of_last_full_second() {
+        let start = Instant::now();
+        let mut throughput = Throughput::new(start);
+        throughput.record(start, 10, 1000);
+        assert_eq!(throughput.rate(), (0, 0));
+        throughput.record(start + Duration::from_millis(1500), 4, 2048);
+        assert_eq!(throughput.rate(), (10, 1000));
+        assert!(throughput.advance(start + Duration::from_secs(2)));
+        assert_eq!(throughput.summary(), "4 lines/s 2.0 KiB/s █▄");
+        // A quiet input shows up as blanks
+        throughput.advance(start + Duration::from_secs(4));
+        assert_eq!(throughput.sparkline(), "█▄  ");
+        assert_eq!(throughput.rate(), (0, 0));
+    }
+}
@@ -0,0 +1,105 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+/// Time spent on each commit during a review, to print a summary of the
+/// effort on exit.
+#[derive(Debug, Default)]
+pub struct ReviewTimer {
+    /// Hash, subject and time spent of each commit viewed, in the order they
+    /// were first viewed.
+    commits: Vec<(String, String, Duration)>,
+    by_hash: HashMap<String, usize>,
+    /// Commit being viewed and since when.
+    current: Option<(usize, Instant)>,
+}
+
+impl ReviewTimer {
+    /// Starts timing the commit with `hash`, stopping the one viewed before.
+    /// `None` stops timing, e.g. while the terminal is not focused.
+    pub fn view(&mut self, commit: Option<(&str, &str)>, now: Instant) {
+        let index = commit.map(|(hash, subject)| {
+            *self.by_hash.entry(hash.to_string()).or_insert_with(|| {
+                self.commits
+                    .push((hash.to_string(), subject.to_string(), Duration::ZERO));
+                self.commits.len() - 1
+            })
+        });
+        if let Some((current, since)) = self.current {
+            if Some(current) == index {
+                return;
+            }
+            self.commits[current].2 += now.duration_since(since);
+        }
+        self.current = index.map(|index| (index, now));
+    }
+
+    /// Summary like `Reviewed 2 commits in 3m10s, 1 mark set` followed by a
+    /// line for each commit.
+    pub fn summary(&mut self, marks: usize, now: Instant) -> String {
+        self.view(None, now);
+        let total: Duration = self.commits.iter().map(|(_, _, time)| *time).sum();
+        let mut summary = format!(
+            "Reviewed {} commit{} in {}, {marks} mark{} set",
+            self.commits.len(),
+            plural(self.commits.len()),
+            format_duration(total),
+            plural(marks),
+        );
+        for (hash, subject, time) in &self.commits {
+            let short_hash = &hash[..std::cmp::min(hash.len(), 7)];
+            let _ = write!(
+                summary,
+                "\n  {short_hash} {:>6}  {subject}",
+                format_duration(*time)
+            );
+        }
+        summary
+    }
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/// Duration like `45s`, `3m10s` or `1h05m`.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..=59 => format!("{seconds}s"),
+        60..=3599 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{:02}m", seconds / 3600, seconds / 60 % 60),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::ReviewTimer;
+
+    #[test]
+    fn time_per_commit() {
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+        let mut timer = ReviewTimer::default();
+        timer.view(Some(("b8e882d50a8e", "Read stdin")), at(0));
+        timer.view(Some(("96f57fbcd5a1", "WIP")), at(70));
+        // Unfocused time doesn't count
+        timer.view(None, at(80));
+        timer.view(Some(("96f57fbcd5a1", "WIP")), at(500));
+        timer.view(Some(("b8e882d50a8e", "Read stdin")), at(505));
+        assert_eq!(
+            timer.summary(1, at(510)),
+            "Reviewed 2 commits in 1m30s, 1 mark set\n  \
+             b8e882d  1m15s  Read stdin\n  \
+             96f57fb    15s  WIP"
+        );
+    }
+}
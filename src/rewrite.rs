@@ -0,0 +1,71 @@
+use std::borrow::Cow;
+
+use regex::Regex;
+
+use crate::{config::RewriteRule, error::Error};
+
+/// Applies the configured rewrite rules to lines as they are displayed. The
+/// buffer itself keeps the original text.
+#[derive(Debug, Default)]
+pub struct Rewriter {
+    rules: Vec<(Regex, String)>,
+}
+
+impl Rewriter {
+    pub fn new(rules: &[RewriteRule]) -> Result<Self, Error> {
+        let rules = rules
+            .iter()
+            .map(|rule| Ok((Regex::new(&rule.pattern)?, rule.replacement.clone())))
+            .collect::<Result<_, Error>>()?;
+        Ok(Rewriter { rules })
+    }
+
+    pub fn apply<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        let mut line = Cow::Borrowed(line);
+        for (pattern, replacement) in &self.rules {
+            if let Cow::Owned(rewritten) = pattern.replace_all(&line, replacement.as_str()) {
+                line = Cow::Owned(rewritten);
+            }
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use super::Rewriter;
+    use crate::config::RewriteRule;
+
+    fn rule(pattern: &str, replacement: &str) -> RewriteRule {
+        RewriteRule {
+            pattern: pattern.to_string(),
+            replacement: replacement.to_string(),
+        }
+    }
+
+    #[test]
+    fn rewrite_with_capture_groups() {
+        let rewriter = Rewriter::new(&[
+            rule(r"/home/(\w+)", "~$1"),
+            rule(r"[0-9a-f]{8}(-[0-9a-f]{4}){3}-[0-9a-f]{12}", "<uuid>"),
+        ])
+        .unwrap();
+        assert_eq!(
+            rewriter.apply("open /home/me/a 0b6c1a52-7d3e-4f0e-9d6a-2f2f3e8c9a10"),
+            "open ~me/a <uuid>"
+        );
+    }
+
+    #[test]
+    fn borrow_unchanged_lines() {
+        let rewriter = Rewriter::new(&[rule("x+", "y")]).unwrap();
+        assert!(matches!(rewriter.apply("abc"), Cow::Borrowed("abc")));
+    }
+
+    #[test]
+    fn reject_invalid_pattern() {
+        assert!(Rewriter::new(&[rule("(", "")]).is_err());
+    }
+}
@@ -0,0 +1,453 @@
+use std::ops::Range;
+
+use aho_corasick::AhoCorasick;
+use serde::Deserialize;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::storage::Storage;
+
+/// How letter case is treated when searching.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaseMode {
+    /// Ignore case unless the term has an uppercase letter.
+    #[default]
+    Smart,
+    Insensitive,
+    Sensitive,
+}
+
+impl CaseMode {
+    fn ignores_case(self, term: &str) -> bool {
+        match self {
+            CaseMode::Smart => !term.chars().any(char::is_uppercase),
+            CaseMode::Insensitive => true,
+            CaseMode::Sensitive => false,
+        }
+    }
+}
+
+/// Unicode normalization applied to the term and the lines before matching.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Normalization {
+    /// Match text as it is.
+    #[default]
+    None,
+    /// Match composed and decomposed letters alike, like `é` and `e` with a
+    /// combining accent.
+    Nfc,
+    /// Also match compatibility characters like `ﬁ` with `fi`.
+    Nfkc,
+}
+
+/// How a search matches text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub case: CaseMode,
+    /// Ignore the case of any letter rather than just ASCII ones, which is
+    /// slower.
+    pub unicode: bool,
+    pub normalization: Normalization,
+    /// Ignore accents and other diacritics, so `cafe` matches `café`.
+    pub fold_diacritics: bool,
+}
+
+/// How lines are transformed before matching, keeping track of where each
+/// byte of the result came from.
+#[derive(Debug, Clone, Copy)]
+struct Folding {
+    /// Lowercase letters, only ASCII ones unless `unicode` is set.
+    lowercase: bool,
+    unicode: bool,
+    normalization: Normalization,
+    fold_diacritics: bool,
+}
+
+/// Every match of a search term in the buffer, found once per term and
+/// extended as more lines are read.
+pub struct Search {
+    term: String,
+    matcher: AhoCorasick,
+    /// Buffer line number and byte range of each match, in order.
+    matches: Vec<(usize, Range<usize>)>,
+    /// Number of buffer lines searched so far.
+    searched: usize,
+    /// Index of the match last jumped to.
+    current: Option<usize>,
+    /// How lines are transformed before matching, if they are.
+    folding: Option<Folding>,
+    /// Buffer lines searched, all of them if `None`.
+    scope: Option<Range<usize>>,
+}
+
+impl Search {
+    /// Searches `lines` for `term` as `options` say. Returns `None` for an
+    /// empty term.
+    pub fn new(term: &str, lines: &dyn Storage, options: SearchOptions) -> Option<Self> {
+        let mut search = Self::pending(term, options)?;
+        search.extend(lines);
+        Some(search)
+    }
+
+    /// A search for `term` that hasn't scanned any lines yet, for scanning
+    /// a few at a time with `scan`.
+    pub fn pending(term: &str, options: SearchOptions) -> Option<Self> {
+        if term.is_empty() {
+            return None;
+        }
+        let ignore_case = options.case.ignores_case(term);
+        // Plain ASCII case insensitive matching needs no copy of the lines
+        let folding = (ignore_case && options.unicode
+            || options.normalization != Normalization::None
+            || options.fold_diacritics)
+            .then_some(Folding {
+                lowercase: ignore_case,
+                unicode: options.unicode,
+                normalization: options.normalization,
+                fold_diacritics: options.fold_diacritics,
+            });
+        let matcher = match folding {
+            Some(folding) => AhoCorasick::new([folding.apply(term).0]),
+            None => AhoCorasick::builder()
+                .ascii_case_insensitive(ignore_case)
+                .build([term]),
+        }
+        .ok()?;
+        Some(Search {
+            term: term.to_string(),
+            matcher,
+            matches: Vec::new(),
+            searched: 0,
+            current: None,
+            folding,
+            scope: None,
+        })
+    }
+
+    /// Only searches the buffer lines in `scope`, if given, forgetting the
+    /// matches already found outside of it.
+    pub fn within(mut self, scope: Option<Range<usize>>) -> Self {
+        if let Some(scope) = &scope {
+            self.matches.retain(|(line, _)| scope.contains(line));
+            self.current = None;
+        }
+        self.scope = scope;
+        self
+    }
+
+    pub fn term(&self) -> &str {
+        &self.term
+    }
+
+    pub fn scope(&self) -> Option<Range<usize>> {
+        self.scope.clone()
+    }
+
+    /// Whether buffer line `line` is searched.
+    pub fn covers(&self, line: usize) -> bool {
+        self.scope
+            .as_ref()
+            .is_none_or(|scope| scope.contains(&line))
+    }
+
+    /// Searches the lines read since the last call.
+    pub fn extend(&mut self, lines: &dyn Storage) {
+        self.scan(lines, usize::MAX);
+    }
+
+    /// Searches at most `count` of the lines not searched yet, returning
+    /// whether all of `lines` have been searched.
+    pub fn scan(&mut self, lines: &dyn Storage, count: usize) -> bool {
+        let end = std::cmp::min(lines.len(), self.searched.saturating_add(count));
+        // Lines out of scope are skipped without reading them
+        let scope = self.scope.clone().unwrap_or(0..usize::MAX);
+        for line_num in std::cmp::max(self.searched, scope.start)..std::cmp::min(end, scope.end) {
+            self.matches.extend(
+                self.highlights(&lines.line(line_num))
+                    .into_iter()
+                    .map(|range| (line_num, range)),
+            );
+        }
+        self.searched = std::cmp::max(self.searched, end);
+        self.searched == lines.len()
+    }
+
+    /// Forgets the matches in the first `lines` lines of the buffer,
+    /// numbering the rest from zero again.
+    pub fn discard(&mut self, lines: usize) {
+        let discarded = self.matches.partition_point(|(line, _)| *line < lines);
+        self.matches.drain(..discarded);
+        for (line, _) in &mut self.matches {
+            *line -= lines;
+        }
+        self.current = self
+            .current
+            .and_then(|current| current.checked_sub(discarded));
+        self.searched = self.searched.saturating_sub(lines);
+        if let Some(scope) = &mut self.scope {
+            *scope = scope.start.saturating_sub(lines)..scope.end.saturating_sub(lines);
+        }
+    }
+
+    /// Jumps to the first match on or after `line`, returning its line.
+    pub fn first_from(&mut self, line: usize) -> Option<usize> {
+        let index = self
+            .matches
+            .partition_point(|(match_line, _)| *match_line < line);
+        self.jump(index)
+    }
+
+    /// Jumps to the next match, returning its line and whether it wrapped
+    /// around from the last match to the first.
+    pub fn next(&mut self) -> Option<(usize, bool)> {
+        let index = self.current.map_or(0, |current| current + 1);
+        let wrapped = index >= self.matches.len();
+        Some((self.jump(if wrapped { 0 } else { index })?, wrapped))
+    }
+
+    /// Jumps to the previous match, returning its line and whether it
+    /// wrapped around from the first match to the last.
+    pub fn previous(&mut self) -> Option<(usize, bool)> {
+        let (index, wrapped) = match self.current {
+            Some(0) => (self.matches.len().saturating_sub(1), true),
+            current => (current.map_or(0, |current| current - 1), false),
+        };
+        Some((self.jump(index)?, wrapped))
+    }
+
+    fn jump(&mut self, index: usize) -> Option<usize> {
+        let (line, _) = self.matches.get(index)?;
+        self.current = Some(index);
+        Some(*line)
+    }
+
+    /// Byte ranges of the term in `text`, for highlighting it as displayed.
+    pub fn highlights(&self, text: &str) -> Vec<Range<usize>> {
+        let Some(folding) = self.folding else {
+            return self
+                .matcher
+                .find_iter(text)
+                .map(|found| found.range())
+                .collect();
+        };
+        let (folded, offsets) = folding.apply(text);
+        self.matcher
+            .find_iter(&folded)
+            .map(|found| offsets[found.start()]..offsets[found.end()])
+            .collect()
+    }
+
+    /// Number of matches found, and the bytes their index takes.
+    pub fn index_size(&self) -> (usize, usize) {
+        let entry = std::mem::size_of::<(usize, Range<usize>)>();
+        (self.matches.len(), self.matches.capacity() * entry)
+    }
+
+    /// Position among the matches, like `match 3/47`.
+    pub fn status(&self) -> String {
+        let term = match self.scope {
+            Some(_) => format!("/{} in context", self.term),
+            None => format!("/{}", self.term),
+        };
+        match (self.current, self.matches.len()) {
+            (_, 0) => format!("{term}: no matches"),
+            (Some(current), total) => format!("{term}: match {}/{total}", current + 1),
+            (None, 1) => format!("{term}: 1 match"),
+            (None, total) => format!("{term}: {total} matches"),
+        }
+    }
+}
+
+impl Folding {
+    /// Transforms `text`, returning the offset in `text` of each byte of the
+    /// result and of its end, as folding may change the length of letters.
+    /// Each letter is folded together with the combining marks following it,
+    /// so a match never starts or ends between them.
+    fn apply(&self, text: &str) -> (String, Vec<usize>) {
+        let mut folded = String::with_capacity(text.len());
+        let mut offsets = Vec::with_capacity(text.len() + 1);
+        let mut chars = text.char_indices().peekable();
+        while let Some((offset, first)) = chars.next() {
+            let mut end = offset + first.len_utf8();
+            while let Some((next, c)) = chars.peek() {
+                if !is_combining_mark(*c) {
+                    break;
+                }
+                end = next + c.len_utf8();
+                chars.next();
+            }
+            let cluster = &text[offset..end];
+            let normalized: String = match (self.normalization, self.fold_diacritics) {
+                (Normalization::Nfkc, true) => cluster.nfkd().collect(),
+                (_, true) => cluster.nfd().collect(),
+                (Normalization::Nfkc, false) => cluster.nfkc().collect(),
+                (Normalization::Nfc, false) => cluster.nfc().collect(),
+                (Normalization::None, false) => cluster.to_string(),
+            };
+            for c in normalized.chars() {
+                if self.fold_diacritics && is_combining_mark(c) {
+                    continue;
+                }
+                match (self.lowercase, self.unicode) {
+                    (true, true) => folded.extend(c.to_lowercase()),
+                    (true, false) => folded.push(c.to_ascii_lowercase()),
+                    (false, _) => folded.push(c),
+                }
+            }
+            offsets.resize(folded.len(), offset);
+        }
+        offsets.push(text.len());
+        (folded, offsets)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CaseMode, Normalization, Search, SearchOptions};
+
+    fn options(case: CaseMode, unicode: bool) -> SearchOptions {
+        SearchOptions {
+            case,
+            unicode,
+            ..SearchOptions::default()
+        }
+    }
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn count_and_step_through_matches() {
+        let mut lines = lines(&["Error: one", "fine", "error and ERROR", "fine"]);
+        let mut search = Search::new("error", &lines, options(CaseMode::Smart, false)).unwrap();
+        assert_eq!(search.status(), "/error: 3 matches");
+        assert_eq!(search.first_from(1), Some(2));
+        assert_eq!(search.status(), "/error: match 2/3");
+        assert_eq!(search.next(), Some((2, false)));
+        assert_eq!(search.status(), "/error: match 3/3");
+        // Wrapping around both ways
+        assert_eq!(search.next(), Some((0, true)));
+        assert_eq!(search.previous(), Some((2, true)));
+        assert_eq!(search.previous(), Some((2, false)));
+        assert_eq!(search.previous(), Some((0, false)));
+
+        lines.push("new error".to_string());
+        search.extend(&lines);
+        assert_eq!(search.status(), "/error: match 1/4");
+        assert_eq!(search.highlights("an Error or error"), vec![3..8, 12..17]);
+
+        lines.drain(..2);
+        search.discard(2);
+        assert_eq!(search.status(), "/error: 3 matches");
+        assert_eq!(search.first_from(0), Some(0));
+        assert_eq!(search.next(), Some((0, false)));
+        assert_eq!(search.next(), Some((2, false)));
+    }
+
+    #[test]
+    fn scan_a_few_lines_at_a_time() {
+        let lines = lines(&["error", "fine", "error", "error", "fine"]);
+        let mut search = Search::pending("error", SearchOptions::default()).unwrap();
+        assert_eq!(search.status(), "/error: no matches");
+        assert!(!search.scan(&lines, 2));
+        assert_eq!(search.status(), "/error: 1 match");
+        assert!(!search.scan(&lines, 2));
+        assert!(search.scan(&lines, 2));
+        assert!(search.scan(&lines, 2));
+        assert_eq!(search.status(), "/error: 3 matches");
+        assert!(Search::pending("", SearchOptions::default()).is_none());
+    }
+
+    #[test]
+    fn search_within_scope() {
+        let mut lines = lines(&["error", "error", "fine", "error", "error"]);
+        let mut search = Search::pending("error", SearchOptions::default())
+            .unwrap()
+            .within(Some(1..4));
+        search.extend(&lines);
+        assert_eq!(search.status(), "/error in context: 2 matches");
+        assert_eq!(search.first_from(0), Some(1));
+        assert_eq!(search.next(), Some((3, false)));
+        assert_eq!(search.next(), Some((1, true)));
+        assert!(!search.covers(0) && search.covers(3) && !search.covers(4));
+        // Lines read later are outside of the scope too
+        lines.push("error".to_string());
+        search.extend(&lines);
+        assert_eq!(search.status(), "/error in context: match 1/2");
+        // Matches found before scoping are kept only within the scope
+        let scoped = Search::new("error", &lines, SearchOptions::default())
+            .unwrap()
+            .within(Some(3..5));
+        assert_eq!(scoped.status(), "/error in context: 2 matches");
+        search.discard(2);
+        assert_eq!(search.scope(), Some(0..2));
+        assert_eq!(search.first_from(0), Some(1));
+    }
+
+    #[test]
+    fn case_modes() {
+        let lines = lines(&["Error", "error", "ERROR"]);
+        let count = |term, case| {
+            let mut search = Search::new(term, &lines, options(case, false)).unwrap();
+            search.first_from(0);
+            search.status()
+        };
+        assert_eq!(count("error", CaseMode::Smart), "/error: match 1/3");
+        assert_eq!(count("Error", CaseMode::Smart), "/Error: match 1/1");
+        assert_eq!(count("Error", CaseMode::Insensitive), "/Error: match 1/3");
+        assert_eq!(count("error", CaseMode::Sensitive), "/error: match 1/1");
+    }
+
+    #[test]
+    fn unicode_case() {
+        let lines = lines(&["Äpfel und äpfel", "ÄPFEL"]);
+        let ascii = Search::new("äpfel", &lines, options(CaseMode::Smart, false)).unwrap();
+        assert_eq!(ascii.status(), "/äpfel: 1 match");
+        let unicode = Search::new("äpfel", &lines, options(CaseMode::Smart, true)).unwrap();
+        assert_eq!(unicode.status(), "/äpfel: 3 matches");
+        // Ranges are in the original text even where lowercasing changes
+        // the length
+        assert_eq!(unicode.highlights("İ Äpfel"), vec![3..9]);
+    }
+
+    #[test]
+    fn no_matches() {
+        let mut search =
+            Search::new("missing", &lines(&["a", "b"]), SearchOptions::default()).unwrap();
+        assert_eq!(search.first_from(0), None);
+        assert_eq!(search.next(), None);
+        assert_eq!(search.status(), "/missing: no matches");
+        assert!(Search::new("", &Vec::new(), SearchOptions::default()).is_none());
+    }
+
+    #[test]
+    fn normalize_and_fold_diacritics() {
+        // Composed and decomposed é
+        let lines = lines(&["Café au lait", "cafe\u{301} noir", "cafe", "\u{fb01}le"]);
+        let count = |term, normalization, fold_diacritics| {
+            let options = SearchOptions {
+                normalization,
+                fold_diacritics,
+                ..SearchOptions::default()
+            };
+            Search::new(term, &lines, options).unwrap().status()
+        };
+        assert_eq!(count("café", Normalization::None, false), "/café: 1 match");
+        assert_eq!(count("café", Normalization::Nfc, false), "/café: 2 matches");
+        assert_eq!(count("cafe", Normalization::None, true), "/cafe: 3 matches");
+        assert_eq!(
+            count("file", Normalization::Nfc, false),
+            "/file: no matches"
+        );
+        assert_eq!(count("file", Normalization::Nfkc, false), "/file: 1 match");
+        // Highlights cover the accent too
+        let options = SearchOptions {
+            fold_diacritics: true,
+            ..SearchOptions::default()
+        };
+        let search = Search::new("cafe", &lines, options).unwrap();
+        assert_eq!(search.highlights("le cafe\u{301}!"), vec![3..9]);
+    }
+}
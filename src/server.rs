@@ -0,0 +1,263 @@
+use std::path::Path;
+
+use crate::{error::Error, input::InputReceiver};
+
+/// Reads `rx` to the end, keeping every line in memory, and serves them to
+/// pagers started with `cag --attach SOCKET` until killed.
+///
+/// Attached pagers get everything read so far and then each new line as it
+/// arrives, and page it independently like any other input. The socket is
+/// removed once the server ends, killed or not.
+#[cfg(unix)]
+pub fn serve(socket: &Path, rx: InputReceiver) -> Result<(), Error> {
+    let listener = bind(socket)?;
+    let _socket_file = SocketFile::new(socket);
+    eprintln!(
+        "Serving on {}, attach with: cag --attach {0}",
+        socket.display()
+    );
+    share(listener, rx)
+}
+
+#[cfg(not(unix))]
+pub fn serve(_socket: &Path, _rx: InputReceiver) -> Result<(), Error> {
+    Err(Error::Io(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "serving is only supported on unix",
+    )))
+}
+
+#[cfg(unix)]
+use unix::{bind, share, SocketFile};
+
+#[cfg(unix)]
+mod unix {
+    use std::{
+        ffi::CString,
+        io::{ErrorKind, Write},
+        net::Shutdown,
+        os::unix::{
+            ffi::OsStrExt,
+            net::{UnixListener, UnixStream},
+        },
+        path::{Path, PathBuf},
+        sync::{
+            mpsc::{sync_channel, SyncSender, TrySendError},
+            Arc, Mutex, OnceLock,
+        },
+        thread,
+    };
+
+    use tracing::{trace, warn};
+
+    use crate::{error::Error, input::InputReceiver};
+
+    /// Chunks of lines queued for a client before it counts as fallen behind
+    /// and is dropped, so one stuck pager doesn't hold up the others.
+    const CLIENT_QUEUE: usize = 1024;
+
+    #[derive(Default)]
+    struct Shared {
+        lines: Vec<String>,
+        clients: Vec<Client>,
+    }
+
+    /// An attached pager, written to by a thread of its own.
+    pub(super) struct Client {
+        queue: SyncSender<Arc<String>>,
+        stream: UnixStream,
+    }
+
+    impl Client {
+        /// Starts writing to `stream`, first the `lines` read so far.
+        pub(super) fn spawn(stream: UnixStream, lines: &[String]) -> Option<Client> {
+            let mut writer = match stream.try_clone() {
+                Ok(writer) => writer,
+                Err(err) => {
+                    warn!("Error attaching client: {err}");
+                    return None;
+                }
+            };
+            let (queue, rx) = sync_channel::<Arc<String>>(CLIENT_QUEUE);
+            thread::spawn(move || {
+                for buf in rx {
+                    if let Err(err) = writer.write_all(buf.as_bytes()) {
+                        trace!("Client detached: {err}");
+                        return;
+                    }
+                }
+            });
+            let client = Client { queue, stream };
+            client.send(Arc::new(text(lines))).then_some(client)
+        }
+
+        /// Queues `buf` for the client, returning `false` if it has gone away
+        /// or fallen too far behind, in which case it is disconnected.
+        pub(super) fn send(&self, buf: Arc<String>) -> bool {
+            match self.queue.try_send(buf) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    warn!("Dropping a client that fell behind");
+                    let _ = self.stream.shutdown(Shutdown::Write);
+                    false
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            }
+        }
+    }
+
+    /// Path of the socket served, for the signal handler to remove it.
+    static SOCKET: OnceLock<CString> = OnceLock::new();
+
+    /// Removes the socket when dropped, or when SIGINT, SIGTERM or SIGHUP end
+    /// the server, so the next one doesn't have to find it stale.
+    pub struct SocketFile(PathBuf);
+
+    impl SocketFile {
+        pub fn new(socket: &Path) -> Self {
+            extern "C" fn remove_and_exit(signal: libc::c_int) {
+                // SAFETY: unlink and _exit are async-signal-safe, and the
+                // path is set before the handler is installed.
+                unsafe {
+                    if let Some(socket) = SOCKET.get() {
+                        libc::unlink(socket.as_ptr());
+                    }
+                    libc::_exit(128 + signal);
+                }
+            }
+            if let Ok(path) = CString::new(socket.as_os_str().as_bytes()) {
+                if SOCKET.set(path).is_ok() {
+                    for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+                        // SAFETY: see the handler.
+                        unsafe {
+                            libc::signal(
+                                signal,
+                                remove_and_exit as *const () as libc::sighandler_t,
+                            );
+                        }
+                    }
+                }
+            }
+            SocketFile(socket.to_path_buf())
+        }
+    }
+
+    impl Drop for SocketFile {
+        fn drop(&mut self) {
+            trace!("Removing socket {:?}", self.0);
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// Listens on `socket`, replacing a stale socket left behind by a server
+    /// that is no longer running.
+    pub fn bind(socket: &Path) -> Result<UnixListener, Error> {
+        match UnixListener::bind(socket) {
+            Err(err)
+                if err.kind() == ErrorKind::AddrInUse && UnixStream::connect(socket).is_err() =>
+            {
+                trace!("Removing stale socket {socket:?}");
+                std::fs::remove_file(socket)?;
+                Ok(UnixListener::bind(socket)?)
+            }
+            listener => Ok(listener?),
+        }
+    }
+
+    pub fn share(listener: UnixListener, mut rx: InputReceiver) -> Result<(), Error> {
+        let shared = Arc::new(Mutex::new(Shared::default()));
+
+        let accepting = Arc::clone(&shared);
+        let accept_handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        warn!("Error accepting client: {err}");
+                        continue;
+                    }
+                };
+                trace!("Client attached");
+                // Hold the lock while queueing the catch up so no line is sent
+                // twice or missed
+                let mut shared = accepting.lock().unwrap();
+                if let Some(client) = Client::spawn(stream, &shared.lines) {
+                    shared.clients.push(client);
+                }
+            }
+        });
+
+        while let Some(chunk) = rx.blocking_recv() {
+            let chunk = chunk?;
+            let buf = Arc::new(text(&chunk.lines));
+            let mut shared = shared.lock().unwrap();
+            shared
+                .clients
+                .retain(|client| client.send(Arc::clone(&buf)));
+            shared.lines.extend(chunk.lines);
+        }
+        trace!("Input ended, serving what was read");
+        let _ = accept_handle.join();
+        Ok(())
+    }
+
+    /// `lines` as written to a client, each ended with a newline.
+    fn text(lines: &[String]) -> String {
+        let mut buf = String::new();
+        for line in lines {
+            buf.push_str(line);
+            buf.push('\n');
+        }
+        buf
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use std::{
+        io::{BufRead, BufReader, Read},
+        os::unix::net::UnixStream,
+        sync::Arc,
+        thread,
+    };
+
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::{bind, share, unix::Client};
+
+    #[test]
+    fn attached_clients_get_old_and_new_lines() {
+        let socket = std::env::temp_dir().join(format!("cag-test-{}.sock", std::process::id()));
+        let listener = bind(&socket).unwrap();
+        let (tx, rx) = unbounded_channel();
+        tx.send(Ok(vec!["a".to_string(), "b".to_string()].into()))
+            .unwrap();
+        thread::spawn(move || share(listener, rx));
+
+        let mut client = BufReader::new(UnixStream::connect(&socket).unwrap());
+        tx.send(Ok(vec!["c".to_string()].into())).unwrap();
+        let mut lines = Vec::new();
+        for _ in 0..3 {
+            let mut line = String::new();
+            client.read_line(&mut line).unwrap();
+            lines.push(line);
+        }
+        assert_eq!(lines, vec!["a\n", "b\n", "c\n"]);
+        let _ = std::fs::remove_file(&socket);
+    }
+    #[test]
+    fn drop_clients_falling_behind() {
+        let (stream, mut pager) = UnixStream::pair().unwrap();
+        let client = Client::spawn(stream, &["a".to_string()]).unwrap();
+        let mut caught_up = [0; 2];
+        pager.read_exact(&mut caught_up).unwrap();
+        assert_eq!(&caught_up, b"a\n");
+        // The pager stops reading, so its socket and then its queue fill up
+        let buf = Arc::new("x".repeat(64 * 1024));
+        assert!((0..100_000).any(|_| !client.send(Arc::clone(&buf))));
+        // And it is disconnected after what was written already
+        let mut read = Vec::new();
+        pager.read_to_end(&mut read).unwrap();
+        assert!(read.iter().all(|byte| *byte == b'x'));
+    }
+}
@@ -0,0 +1,101 @@
+/// A row of a diff shown side by side, with lines given by their index among
+/// the lines on screen.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Row {
+    /// A line shown across the whole width, like a file or hunk header.
+    Full(usize),
+    /// A line of the old file on the left and one of the new file on the
+    /// right, context lines being on both sides.
+    Split(Option<usize>, Option<usize>),
+}
+
+/// Rows of the lines on screen, pairing each run of removed lines of a hunk
+/// with the added lines following it. Only the lines on screen are looked
+/// at, so `in_hunk` tells whether the first one is inside a hunk.
+pub fn rows<'a>(lines: impl IntoIterator<Item = &'a str>, mut in_hunk: bool) -> Vec<Row> {
+    let mut rows = Vec::new();
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let flush = |rows: &mut Vec<Row>, removed: &mut Vec<usize>, added: &mut Vec<usize>| {
+        let pairs = std::cmp::max(removed.len(), added.len());
+        rows.extend(
+            (0..pairs).map(|pair| Row::Split(removed.get(pair).copied(), added.get(pair).copied())),
+        );
+        removed.clear();
+        added.clear();
+    };
+    for (index, line) in lines.into_iter().enumerate() {
+        match line.chars().next() {
+            Some('-') if in_hunk => {
+                // Removed lines after added ones start a new change
+                if !added.is_empty() {
+                    flush(&mut rows, &mut removed, &mut added);
+                }
+                removed.push(index);
+            }
+            Some('+') if in_hunk => added.push(index),
+            Some(' ') if in_hunk => {
+                flush(&mut rows, &mut removed, &mut added);
+                rows.push(Row::Split(Some(index), Some(index)));
+            }
+            _ => {
+                flush(&mut rows, &mut removed, &mut added);
+                rows.push(Row::Full(index));
+                // `\ No newline at end of file` is still part of the hunk
+                in_hunk = line.starts_with("@@") || (in_hunk && line.starts_with('\\'));
+            }
+        }
+    }
+    flush(&mut rows, &mut removed, &mut added);
+    rows
+}
+
+#[cfg(test)]
+mod test {
+    use super::{rows, Row};
+
+    const GIT_LOG: &str = include_str!("../tests/data/git_patch");
+
+    #[test]
+    fn pair_removed_and_added_lines() {
+        let lines: Vec<&str> = GIT_LOG.lines().skip(3).take(16).collect();
+        assert_eq!(
+            rows(lines, false),
+            [
+                Row::Full(0),
+                // The commit message is indented like context lines
+                Row::Full(1),
+                Row::Full(2),
+                Row::Full(3),
+                Row::Full(4),
+                Row::Full(5),
+                Row::Full(6),
+                Row::Full(7),
+                Row::Split(Some(8), Some(8)),
+                Row::Split(Some(9), Some(9)),
+                Row::Split(Some(10), Some(10)),
+                Row::Split(None, Some(11)),
+                Row::Split(None, Some(12)),
+                Row::Split(Some(13), Some(13)),
+                Row::Split(Some(14), None),
+                Row::Full(15),
+            ]
+        );
+    }
+
+    #[test]
+    fn start_inside_a_hunk() {
+        let lines = ["-old", "-older", "+new", " same", "-gone"];
+        assert_eq!(
+            rows(lines, true),
+            [
+                Row::Split(Some(0), Some(2)),
+                Row::Split(Some(1), None),
+                Row::Split(Some(3), Some(3)),
+                Row::Split(Some(4), None),
+            ]
+        );
+        // Without knowing, the lines are shown as they are
+        assert_eq!(rows(["-old"], false), [Row::Full(0)]);
+    }
+}
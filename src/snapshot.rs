@@ -0,0 +1,211 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use tokio::{sync::mpsc::unbounded_channel, task::JoinHandle};
+use tracing::trace;
+
+use crate::{
+    context_finder::InputType,
+    error::Error,
+    input::{runtime, Chunk, InputReceiver},
+    storage::Storage,
+};
+
+/// Identifies a snapshot file and its format version.
+const MAGIC: &[u8; 8] = b"CAGSNAP1";
+
+/// Everything read from an input, saved with `:save` and reopened with
+/// `--resume` without having to read the input again.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub input_type: InputType,
+    /// Names of the merged inputs, empty unless merging.
+    pub source_names: Vec<String>,
+    pub lines: Vec<String>,
+    /// Index of the merged input each line came from, empty unless merging.
+    pub line_sources: Vec<usize>,
+}
+
+impl Snapshot {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        trace!("Loading snapshot {path:?}");
+        let file = File::open(path).map_err(|err| Error::SnapshotRead(path.into(), err))?;
+        Snapshot::read(&mut BufReader::new(file)).map_err(|err| match err.kind() {
+            io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => {
+                Error::InvalidSnapshot(path.into())
+            }
+            _ => Error::SnapshotRead(path.into(), err),
+        })
+    }
+
+    /// Sends the saved lines as if they had just been read from the input.
+    pub fn stream(self) -> (InputReceiver, JoinHandle<()>) {
+        let (tx, rx) = unbounded_channel();
+        let _ = tx.send(Ok(Chunk {
+            lines: self.lines,
+            sources: self.line_sources,
+            ..Default::default()
+        }));
+        (rx, runtime().spawn(async {}))
+    }
+
+    fn read(input: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a cag snapshot"));
+        }
+        let input_type = InputType::from_str(&read_string(input)?, false).map_err(invalid_data)?;
+        let source_names = (0..read_len(input)?)
+            .map(|_| read_string(input))
+            .collect::<io::Result<Vec<_>>>()?;
+        let lines = (0..read_len(input)?)
+            .map(|_| read_string(input))
+            .collect::<io::Result<Vec<_>>>()?;
+        let line_sources = (0..read_len(input)?)
+            .map(|_| read_len(input))
+            .collect::<io::Result<Vec<_>>>()?;
+        // The sources index the names, one for each line
+        if !line_sources.is_empty() && line_sources.len() != lines.len() {
+            return Err(invalid_data("not one source for each line"));
+        }
+        if line_sources
+            .iter()
+            .any(|source| *source >= source_names.len())
+        {
+            return Err(invalid_data("source out of range"));
+        }
+        Ok(Snapshot {
+            input_type,
+            source_names,
+            lines,
+            line_sources,
+        })
+    }
+}
+
+/// Saves the lines read so far, with the names and indexes of their merged
+/// inputs, to `path`.
+pub fn save(
+    path: &Path,
+    input_type: InputType,
+    source_names: &[String],
+    lines: &dyn Storage,
+    line_sources: &[usize],
+) -> Result<(), Error> {
+    trace!("Saving snapshot {path:?}");
+    let write = || -> io::Result<()> {
+        let mut output = BufWriter::new(File::create(path)?);
+        output.write_all(MAGIC)?;
+        let input_type = input_type
+            .to_possible_value()
+            .expect("input types are never skipped");
+        write_string(&mut output, input_type.get_name())?;
+        write_len(&mut output, source_names.len())?;
+        for name in source_names {
+            write_string(&mut output, name)?;
+        }
+        write_len(&mut output, lines.len())?;
+        for line in lines.iter(0..lines.len()) {
+            write_string(&mut output, &line)?;
+        }
+        write_len(&mut output, line_sources.len())?;
+        for source in line_sources {
+            write_len(&mut output, *source)?;
+        }
+        output.flush()
+    };
+    write().map_err(|err| Error::SnapshotWrite(PathBuf::from(path), err))
+}
+
+fn invalid_data(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+fn write_len(output: &mut impl Write, len: usize) -> io::Result<()> {
+    output.write_all(&(len as u64).to_le_bytes())
+}
+
+fn write_string(output: &mut impl Write, string: &str) -> io::Result<()> {
+    write_len(output, string.len())?;
+    output.write_all(string.as_bytes())
+}
+
+fn read_len(input: &mut impl Read) -> io::Result<usize> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    usize::try_from(u64::from_le_bytes(bytes)).map_err(invalid_data)
+}
+
+fn read_string(input: &mut impl Read) -> io::Result<String> {
+    let len = read_len(input)?;
+    let mut bytes = Vec::new();
+    // Don't trust the length enough to allocate it up front
+    input.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(bytes).map_err(invalid_data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{save, Snapshot};
+    use crate::{context_finder::InputType, error::Error};
+
+    #[test]
+    fn save_and_load() {
+        let path = std::env::temp_dir().join(format!("cag-test-{}.cagsnap", std::process::id()));
+        let lines = vec!["first".to_string(), "ä second".to_string()];
+        let source_names = vec!["a.log".to_string(), "b.log".to_string()];
+        save(&path, InputType::Journald, &source_names, &lines, &[1, 0]).unwrap();
+        let snapshot = Snapshot::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            snapshot,
+            Snapshot {
+                input_type: InputType::Journald,
+                source_names,
+                lines,
+                line_sources: vec![1, 0],
+            }
+        );
+    }
+
+    #[test]
+    fn reject_sources_not_matching() {
+        let path =
+            std::env::temp_dir().join(format!("cag-test-sources-{}.cagsnap", std::process::id()));
+        let lines = vec!["first".to_string(), "second".to_string()];
+        let source_names = vec!["a.log".to_string(), "b.log".to_string()];
+        for line_sources in [&[0, 2][..], &[0], &[0, 1, 1]] {
+            save(
+                &path,
+                InputType::Journald,
+                &source_names,
+                &lines,
+                line_sources,
+            )
+            .unwrap();
+            assert!(matches!(
+                Snapshot::load(&path),
+                Err(Error::InvalidSnapshot(_))
+            ));
+        }
+        save(&path, InputType::Journald, &[], &lines, &[]).unwrap();
+        assert!(Snapshot::load(&path).is_ok());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reject_other_files() {
+        assert!(matches!(
+            Snapshot::load("tests/data/git_patch".as_ref()),
+            Err(Error::InvalidSnapshot(_))
+        ));
+    }
+}
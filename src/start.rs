@@ -0,0 +1,86 @@
+use std::ops::Range;
+
+use regex::Regex;
+
+use crate::{
+    context_finder::{diff_path, InputType},
+    error::Error,
+    storage::Storage,
+};
+
+/// Where to start paging instead of the top, like the first hunk of a diff
+/// when used as git's pager, skipping its preamble. Found as the lines
+/// stream in, until the first one matching.
+#[derive(Debug)]
+pub struct StartAt {
+    pattern: Regex,
+    /// Whether only the new paths of `diff --git` headers are matched,
+    /// without their prefixes.
+    files_only: bool,
+}
+
+impl StartAt {
+    /// Starts at the first file whose path matches `pattern` for a diff, or
+    /// the first line matching it for other input types. Without a
+    /// `pattern`, starts at the first line matching the `configured` one of
+    /// the input type, if any.
+    pub fn new(
+        input_type: InputType,
+        pattern: Option<&str>,
+        configured: Option<&str>,
+    ) -> Result<Option<Self>, Error> {
+        let (pattern, files_only) = match (pattern, configured) {
+            (Some(pattern), _) => (pattern, input_type == InputType::Git),
+            (None, Some(configured)) => (configured, false),
+            (None, None) => return Ok(None),
+        };
+        Ok(Some(StartAt {
+            pattern: Regex::new(pattern)?,
+            files_only,
+        }))
+    }
+
+    /// The first line in `range` of `lines` to start at.
+    pub fn find(&self, lines: &dyn Storage, range: Range<usize>) -> Option<usize> {
+        range.into_iter().find(|&line_num| {
+            let line = lines.line(line_num);
+            if self.files_only {
+                diff_path(&line).is_some_and(|path| self.pattern.is_match(path))
+            } else {
+                self.pattern.is_match(&line)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::StartAt;
+    use crate::context_finder::InputType;
+
+    const GIT_LOG: &str = include_str!("../tests/data/git_patch");
+
+    #[test]
+    fn start_at_file_or_hunk() {
+        let input: Vec<String> = GIT_LOG.lines().map(|l| l.to_string()).collect();
+        let start = StartAt::new(InputType::Git, Some("main"), Some("^@@ "))
+            .unwrap()
+            .unwrap();
+        let line = start.find(&input, 0..input.len()).unwrap();
+        assert_eq!(input[line], "diff --git a/src/main.rs b/src/main.rs");
+        // Not in the lines read so far
+        assert_eq!(start.find(&input, 0..line), None);
+        // Paths are matched without their prefixes
+        let start = StartAt::new(InputType::Git, Some("^src/"), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(start.find(&input, 0..input.len()), Some(line));
+
+        let start = StartAt::new(InputType::Git, None, Some("^@@ "))
+            .unwrap()
+            .unwrap();
+        let line = start.find(&input, 0..input.len()).unwrap();
+        assert!(input[line].starts_with("@@ -7,5 +7,6 @@"));
+        assert!(StartAt::new(InputType::Git, None, None).unwrap().is_none());
+    }
+}
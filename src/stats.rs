@@ -0,0 +1,104 @@
+use crate::rate::format_bytes;
+
+/// Size of the buffer, its indexes and the input, shown by `:stats` for
+/// users and for diagnosing slowness.
+pub struct Stats {
+    pub lines: usize,
+    /// Bytes of the text of the lines.
+    pub bytes: usize,
+    /// Whether the lines are kept in a temporary file rather than in memory.
+    pub spilled: bool,
+    /// Estimate of the memory taken by the lines.
+    pub buffer_memory: usize,
+    /// Entries and bytes of each index, by name, `None` if there is none.
+    pub indexes: Vec<(&'static str, Option<(usize, usize)>)>,
+    /// Lines dropped because of `--max-lines`.
+    pub dropped: usize,
+    /// Whether more input may still arrive.
+    pub streaming: bool,
+    /// What is being done with arriving lines, like following them.
+    pub stream_status: Option<String>,
+}
+
+impl Stats {
+    /// One `name: value` line each, for the overlay.
+    pub fn lines(&self) -> Vec<String> {
+        let index_memory: usize = self
+            .indexes
+            .iter()
+            .filter_map(|(_, size)| size.map(|(_, bytes)| bytes))
+            .sum();
+        let mut lines = vec![
+            format!("lines: {} ({})", self.lines, format_bytes(self.bytes)),
+            format!(
+                "stored in: {}",
+                if self.spilled {
+                    "temporary file"
+                } else {
+                    "memory"
+                }
+            ),
+            format!(
+                "memory: about {}",
+                format_bytes(self.buffer_memory + index_memory)
+            ),
+        ];
+        lines.extend(self.indexes.iter().map(|(name, size)| match size {
+            Some((entries, bytes)) => format!("{name}: {entries} ({})", format_bytes(*bytes)),
+            None => format!("{name}: none"),
+        }));
+        if self.dropped > 0 {
+            lines.push(format!("dropped: {} lines", self.dropped));
+        }
+        let stream = if self.streaming { "open" } else { "ended" };
+        lines.push(match &self.stream_status {
+            Some(status) => format!("input: {stream}, {status}"),
+            None => format!("input: {stream}"),
+        });
+        lines
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Stats;
+
+    #[test]
+    fn describe_buffer_and_indexes() {
+        let stats = Stats {
+            lines: 1200,
+            bytes: 3 * 1_048_576,
+            spilled: false,
+            buffer_memory: 3 * 1_048_576,
+            indexes: vec![("contexts", Some((40, 1024 * 1024))), ("filter", None)],
+            dropped: 0,
+            streaming: true,
+            stream_status: Some("following".to_string()),
+        };
+        assert_eq!(
+            stats.lines(),
+            [
+                "lines: 1200 (3.0 MiB)",
+                "stored in: memory",
+                "memory: about 4.0 MiB",
+                "contexts: 40 (1.0 MiB)",
+                "filter: none",
+                "input: open, following",
+            ]
+        );
+        let ended = Stats {
+            spilled: true,
+            dropped: 5,
+            streaming: false,
+            stream_status: None,
+            indexes: Vec::new(),
+            ..stats
+        };
+        let lines = ended.lines();
+        assert_eq!(lines[1], "stored in: temporary file");
+        assert_eq!(
+            lines[lines.len() - 2..],
+            ["dropped: 5 lines", "input: ended"]
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    ops::Range,
+};
+
+use tracing::trace;
+
+use crate::error::Error;
+
+/// Lines read around one that isn't cached, so scanning up or down a spilled
+/// buffer reads the file in blocks rather than line by line.
+const WINDOW: usize = 4096;
+
+/// Lines of the input wherever they are kept, read by the pager and the
+/// context finder alike.
+pub trait Storage {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Line at `index`, which must be below `len()`.
+    fn line(&self, index: usize) -> Cow<'_, str>;
+
+    /// Lines in `range`, which must be within `len()`.
+    fn lines(&self, range: Range<usize>) -> Cow<'_, [String]>;
+}
+
+impl dyn Storage + '_ {
+    /// Lines in `range`, one at a time.
+    pub fn iter(
+        &self,
+        range: Range<usize>,
+    ) -> impl DoubleEndedIterator<Item = Cow<'_, str>> + ExactSizeIterator {
+        range.map(|index| self.line(index))
+    }
+}
+
+impl Storage for [String] {
+    fn len(&self) -> usize {
+        <[String]>::len(self)
+    }
+
+    fn line(&self, index: usize) -> Cow<'_, str> {
+        Cow::Borrowed(&self[index])
+    }
+
+    fn lines(&self, range: Range<usize>) -> Cow<'_, [String]> {
+        Cow::Borrowed(&self[range])
+    }
+}
+
+impl Storage for Vec<String> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn line(&self, index: usize) -> Cow<'_, str> {
+        self.as_slice().line(index)
+    }
+
+    fn lines(&self, range: Range<usize>) -> Cow<'_, [String]> {
+        self.as_slice().lines(range)
+    }
+}
+
+/// The lines read so far, kept in memory until they take more than the
+/// `spill_over` bytes given, and in a temporary file after that so inputs
+/// of several gigabytes don't have to fit in RAM.
+pub struct LineBuffer {
+    memory: Vec<String>,
+    /// Bytes of the lines in `memory`.
+    bytes: usize,
+    spill_over: Option<usize>,
+    spilled: Option<SpillFile>,
+}
+
+impl LineBuffer {
+    pub fn new(spill_over: Option<usize>) -> Self {
+        LineBuffer {
+            memory: Vec::new(),
+            bytes: 0,
+            spill_over,
+            spilled: None,
+        }
+    }
+
+    /// Appends `lines`, moving everything to the temporary file once the
+    /// lines in memory grow too big.
+    pub fn extend(&mut self, lines: Vec<String>) -> Result<(), Error> {
+        if let Some(spilled) = &mut self.spilled {
+            return spilled.extend(lines).map_err(Error::Spill);
+        }
+        self.bytes += lines.iter().map(String::len).sum::<usize>();
+        self.memory.extend(lines);
+        if self.spill_over.is_some_and(|limit| self.bytes > limit) {
+            trace!("Spilling {} lines to a temporary file", self.memory.len());
+            let mut spilled = SpillFile::new().map_err(Error::Spill)?;
+            spilled
+                .extend(std::mem::take(&mut self.memory))
+                .map_err(Error::Spill)?;
+            self.bytes = 0;
+            self.spilled = Some(spilled);
+        }
+        Ok(())
+    }
+
+    /// Bytes of the text of the lines, wherever they are kept.
+    pub fn bytes(&self) -> usize {
+        match &self.spilled {
+            Some(spilled) => spilled.bytes(),
+            None => self.bytes,
+        }
+    }
+
+    /// Whether the lines have been moved to a temporary file.
+    pub fn is_spilled(&self) -> bool {
+        self.spilled.is_some()
+    }
+
+    /// Rough estimate of the memory taken by the lines and their index.
+    pub fn memory(&self) -> usize {
+        match &self.spilled {
+            Some(spilled) => spilled.memory(),
+            None => self.bytes + self.memory.capacity() * std::mem::size_of::<String>(),
+        }
+    }
+
+    /// Forgets the first `count` lines. Spilled lines are only forgotten by
+    /// the index, the temporary file is not shrunk.
+    pub fn drain_front(&mut self, count: usize) {
+        match &mut self.spilled {
+            Some(spilled) => spilled.drain_front(count),
+            None => {
+                self.bytes -= self.memory[..count].iter().map(String::len).sum::<usize>();
+                self.memory.drain(..count);
+            }
+        }
+    }
+}
+
+impl Storage for LineBuffer {
+    fn len(&self) -> usize {
+        match &self.spilled {
+            Some(spilled) => spilled.len(),
+            None => self.memory.len(),
+        }
+    }
+
+    fn line(&self, index: usize) -> Cow<'_, str> {
+        match &self.spilled {
+            Some(spilled) => Cow::Owned(spilled.line(index)),
+            None => self.memory.line(index),
+        }
+    }
+
+    fn lines(&self, range: Range<usize>) -> Cow<'_, [String]> {
+        match &self.spilled {
+            Some(spilled) => Cow::Owned(spilled.read(range)),
+            None => self.memory.lines(range),
+        }
+    }
+}
+
+/// Lines written one after another to a temporary file, deleted when done,
+/// with only the offset of each line kept in memory.
+struct SpillFile {
+    file: RefCell<File>,
+    /// Offset of the start of each line, followed by the end of the last.
+    offsets: Vec<u64>,
+    /// The lines read last, with the index of the first.
+    window: RefCell<(usize, Vec<String>)>,
+}
+
+impl SpillFile {
+    fn new() -> io::Result<Self> {
+        Ok(SpillFile {
+            file: RefCell::new(tempfile::tempfile()?),
+            offsets: vec![0],
+            window: RefCell::new((0, Vec::new())),
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Appends `lines`, keeping the last of them as the window, as they
+    /// are usually scanned right after for searches and commits.
+    fn extend(&mut self, mut lines: Vec<String>) -> io::Result<()> {
+        let start = self.len();
+        let mut end = *self.offsets.last().expect("offsets start with zero");
+        let mut bytes = Vec::new();
+        for line in &lines {
+            bytes.extend_from_slice(line.as_bytes());
+            end += line.len() as u64;
+            self.offsets.push(end);
+        }
+        let file = self.file.get_mut();
+        file.seek(SeekFrom::End(0))?;
+        file.write_all(&bytes)?;
+        let kept = lines.split_off(lines.len().saturating_sub(WINDOW));
+        *self.window.get_mut() = (start + lines.len(), kept);
+        Ok(())
+    }
+
+    fn bytes(&self) -> usize {
+        (self.offsets[self.len()] - self.offsets[0]) as usize
+    }
+
+    /// The offsets and the window, as the lines themselves are on disk.
+    fn memory(&self) -> usize {
+        let window = self.window.borrow();
+        self.offsets.capacity() * std::mem::size_of::<u64>()
+            + window
+                .1
+                .iter()
+                .map(|line| line.len() + std::mem::size_of::<String>())
+                .sum::<usize>()
+    }
+
+    fn drain_front(&mut self, count: usize) {
+        self.offsets.drain(..count);
+        *self.window.get_mut() = (0, Vec::new());
+    }
+
+    fn line(&self, index: usize) -> String {
+        let mut window = self.window.borrow_mut();
+        let (start, lines) = &*window;
+        if let Some(line) = index
+            .checked_sub(*start)
+            .and_then(|offset| lines.get(offset))
+        {
+            return line.clone();
+        }
+        let start = index.saturating_sub(WINDOW / 2);
+        let lines = self.read(start..std::cmp::min(start + WINDOW, self.len()));
+        let line = lines[index - start].clone();
+        *window = (start, lines);
+        line
+    }
+
+    /// Reads the lines in `range` from the file. Failing to read back what
+    /// was written is treated like running out of memory would be.
+    fn read(&self, range: Range<usize>) -> Vec<String> {
+        let (start, end) = (self.offsets[range.start], self.offsets[range.end]);
+        let mut bytes = vec![0; (end - start) as usize];
+        let mut file = self.file.borrow_mut();
+        file.seek(SeekFrom::Start(start))
+            .and_then(|_| file.read_exact(&mut bytes))
+            .expect("Could not read back lines spilled to a temporary file");
+        self.offsets[range.start..=range.end]
+            .windows(2)
+            .map(|line| {
+                let (line_start, line_end) =
+                    ((line[0] - start) as usize, (line[1] - start) as usize);
+                String::from_utf8_lossy(&bytes[line_start..line_end]).into_owned()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::{collection::vec, prelude::*};
+
+    use super::{LineBuffer, Storage};
+
+    #[test]
+    fn spill_to_file() {
+        let mut buffer = LineBuffer::new(Some(10));
+        buffer
+            .extend(vec!["first".to_string(), "second".to_string()])
+            .unwrap();
+        assert!(buffer.spilled.is_some());
+        buffer
+            .extend((0..10_000).map(|line| format!("line {line}")).collect())
+            .unwrap();
+        assert_eq!(buffer.len(), 10_002);
+        assert_eq!(buffer.bytes(), 11 + 88_890);
+        assert_eq!(buffer.line(1), "second");
+        assert_eq!(buffer.line(9_000), "line 8998");
+        // Scanning backwards from the far end refills the cached window
+        assert_eq!(buffer.line(10), "line 8");
+        assert_eq!(
+            buffer.lines(0..3).as_ref(),
+            ["first", "second", "line 0"].map(String::from)
+        );
+        buffer.drain_front(2);
+        assert_eq!(buffer.line(0), "line 0");
+        assert_eq!(buffer.len(), 10_000);
+        assert_eq!(buffer.bytes(), 88_890);
+
+        let mut buffer = LineBuffer::new(None);
+        buffer.extend(vec!["kept".to_string()]).unwrap();
+        assert!(!buffer.is_spilled());
+        assert_eq!(buffer.bytes(), 4);
+        assert_eq!(buffer.line(0), "kept");
+    }
+
+    proptest! {
+        #[test]
+        fn spilled_lines_read_back_unchanged(
+            chunks in vec(vec(".{0,30}", 0..20), 1..10),
+            spill_over in 0..200usize,
+            drained in 0..50usize,
+            index in any::<prop::sample::Index>(),
+        ) {
+            let mut buffer = LineBuffer::new(Some(spill_over));
+            let mut lines: Vec<String> = Vec::new();
+            for chunk in chunks {
+                lines.extend(chunk.iter().cloned());
+                buffer.extend(chunk).unwrap();
+            }
+            let drained = drained.min(lines.len());
+            buffer.drain_front(drained);
+            lines.drain(..drained);
+            prop_assert_eq!(buffer.len(), lines.len());
+            let read = buffer.lines(0..lines.len());
+            prop_assert_eq!(read.as_ref(), lines.as_slice());
+            if !lines.is_empty() {
+                let index = index.index(lines.len());
+                prop_assert_eq!(buffer.line(index), lines[index].as_str());
+            }
+        }
+    }
+}
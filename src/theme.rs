@@ -0,0 +1,390 @@
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+use crate::{background::Background, diff::DiffLine};
+
+/// Styles used to draw the pager.
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    /// The pinned context panel
+    pub context: Style,
+    /// The border between the context panel and the buffer
+    pub border: Style,
+    /// The annotation gutter
+    pub gutter: Style,
+    /// Messages and prompts on the bottom line
+    pub status: Style,
+    /// Matches of the search term
+    pub search: Style,
+    /// The status flashing because of an alert
+    pub alert: Style,
+    /// Lines starting and ending the current context, where the panel
+    /// comes from
+    pub boundary: Style,
+    /// `commit` lines of a diff
+    pub commit: Style,
+    /// Commit metadata like the author
+    pub metadata: Style,
+    /// File names and modes of a diff
+    pub file_header: Style,
+    /// Hunk headers of a diff
+    pub hunk: Style,
+    /// Added lines of a diff
+    pub added: Style,
+    /// Removed lines of a diff
+    pub removed: Style,
+    /// Lines of the merged inputs, each taking the next style
+    pub sources: [Style; 6],
+}
+
+/// Styles telling the merged inputs apart, one color each.
+fn sources(colors: [Color; 6]) -> [Style; 6] {
+    colors.map(|color| Style::default().fg(color))
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Theme {
+            context: Style::default().fg(Color::White),
+            border: Style::default().fg(Color::Gray),
+            gutter: Style::default().fg(Color::DarkGray),
+            status: Style::default().add_modifier(Modifier::REVERSED),
+            search: Style::default().fg(Color::Black).bg(Color::Yellow),
+            alert: Style::default()
+                .fg(Color::White)
+                .bg(Color::Red)
+                .add_modifier(Modifier::BOLD),
+            boundary: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            commit: Style::default().fg(Color::Yellow),
+            metadata: Style::default().fg(Color::Gray),
+            file_header: Style::default().add_modifier(Modifier::BOLD),
+            hunk: Style::default().fg(Color::Cyan),
+            added: Style::default().fg(Color::Green),
+            removed: Style::default().fg(Color::Red),
+            sources: sources([
+                Color::Cyan,
+                Color::Yellow,
+                Color::Magenta,
+                Color::Green,
+                Color::Blue,
+                Color::Red,
+            ]),
+        }
+    }
+
+    pub fn light() -> Self {
+        Theme {
+            context: Style::default().fg(Color::Black),
+            border: Style::default().fg(Color::DarkGray),
+            gutter: Style::default().fg(Color::DarkGray),
+            status: Style::default().add_modifier(Modifier::REVERSED),
+            search: Style::default().fg(Color::Black).bg(Color::LightYellow),
+            alert: Style::default()
+                .fg(Color::White)
+                .bg(Color::Red)
+                .add_modifier(Modifier::BOLD),
+            boundary: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            commit: Style::default().fg(Color::Rgb(0x80, 0x60, 0x00)),
+            metadata: Style::default().fg(Color::DarkGray),
+            file_header: Style::default().add_modifier(Modifier::BOLD),
+            hunk: Style::default().fg(Color::Blue),
+            added: Style::default().fg(Color::Rgb(0x00, 0x80, 0x00)),
+            removed: Style::default().fg(Color::Rgb(0xb0, 0x00, 0x00)),
+            sources: sources([
+                Color::Blue,
+                Color::Rgb(0x80, 0x60, 0x00),
+                Color::Magenta,
+                Color::Rgb(0x00, 0x80, 0x00),
+                Color::Cyan,
+                Color::Rgb(0xb0, 0x00, 0x00),
+            ]),
+        }
+    }
+
+    /// Solarized dark, with the accent colors of the palette.
+    pub fn solarized() -> Self {
+        let base01 = Color::Rgb(0x58, 0x6e, 0x75);
+        let base1 = Color::Rgb(0x93, 0xa1, 0xa1);
+        let yellow = Color::Rgb(0xb5, 0x89, 0x00);
+        let red = Color::Rgb(0xdc, 0x32, 0x2f);
+        Theme {
+            context: Style::default().fg(base1),
+            border: Style::default().fg(base01),
+            gutter: Style::default().fg(base01),
+            status: Style::default().add_modifier(Modifier::REVERSED),
+            search: Style::default().fg(Color::Rgb(0x00, 0x2b, 0x36)).bg(yellow),
+            alert: Style::default()
+                .fg(Color::Rgb(0xfd, 0xf6, 0xe3))
+                .bg(red)
+                .add_modifier(Modifier::BOLD),
+            boundary: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            commit: Style::default().fg(yellow),
+            metadata: Style::default().fg(Color::Rgb(0x83, 0x94, 0x96)),
+            file_header: Style::default().fg(base1).add_modifier(Modifier::BOLD),
+            hunk: Style::default().fg(Color::Rgb(0x2a, 0xa1, 0x98)),
+            added: Style::default().fg(Color::Rgb(0x85, 0x99, 0x00)),
+            removed: Style::default().fg(red),
+            sources: sources([
+                Color::Rgb(0x2a, 0xa1, 0x98),
+                yellow,
+                Color::Rgb(0xd3, 0x36, 0x82),
+                Color::Rgb(0x85, 0x99, 0x00),
+                Color::Rgb(0x26, 0x8b, 0xd2),
+                red,
+            ]),
+        }
+    }
+
+    /// The theme with its colors left out, and highlights reversed instead.
+    pub fn without_colors(self) -> Self {
+        let plain = |style: Style| Style::default().add_modifier(style.add_modifier);
+        Theme {
+            context: plain(self.context),
+            border: plain(self.border),
+            gutter: plain(self.gutter),
+            status: plain(self.status),
+            search: plain(self.search).add_modifier(Modifier::REVERSED),
+            alert: plain(self.alert).add_modifier(Modifier::REVERSED),
+            boundary: plain(self.boundary),
+            commit: plain(self.commit),
+            metadata: plain(self.metadata),
+            file_header: plain(self.file_header),
+            hunk: plain(self.hunk),
+            added: plain(self.added),
+            removed: plain(self.removed),
+            sources: self.sources.map(plain),
+        }
+    }
+
+    /// Style of the lines of merged input number `source`.
+    pub fn source(&self, source: usize) -> Style {
+        self.sources[source % self.sources.len()]
+    }
+
+    pub fn diff(&self, line: DiffLine) -> Style {
+        match line {
+            DiffLine::Commit => self.commit,
+            DiffLine::Metadata => self.metadata,
+            DiffLine::FileHeader => self.file_header,
+            DiffLine::Hunk => self.hunk,
+            DiffLine::Added => self.added,
+            DiffLine::Removed => self.removed,
+        }
+    }
+
+    pub fn for_background(background: Background) -> Self {
+        match background {
+            Background::Dark => Theme::dark(),
+            Background::Light => Theme::light(),
+        }
+    }
+}
+
+/// The `[theme]` section of the config: a preset to start from and styles
+/// replacing some of its ones.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// Preset to use whatever the terminal background
+    pub preset: Option<Preset>,
+    pub context: Option<StyleConfig>,
+    pub border: Option<StyleConfig>,
+    pub status: Option<StyleConfig>,
+    pub search: Option<StyleConfig>,
+    pub boundary: Option<StyleConfig>,
+    pub added: Option<StyleConfig>,
+    pub removed: Option<StyleConfig>,
+    /// Draw with bold, underlined and reversed text only, as when `NO_COLOR`
+    /// is set
+    pub monochrome: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    Dark,
+    Light,
+    Solarized,
+}
+
+/// A style like `{ fg = 'green', bold = true }`. Colors are names like
+/// `red` and `darkgray`, `#rrggbb` or a number of the 256 color palette.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StyleConfig {
+    pub fg: Option<ThemeColor>,
+    pub bg: Option<ThemeColor>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underlined: bool,
+    pub reversed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "toml::Value")]
+pub struct ThemeColor(pub Color);
+
+impl ThemeConfig {
+    /// The theme to draw with on a terminal with `background`.
+    pub fn theme(&self, background: Option<Background>) -> Theme {
+        let mut theme = match self.preset {
+            Some(Preset::Dark) => Theme::dark(),
+            Some(Preset::Light) => Theme::light(),
+            Some(Preset::Solarized) => Theme::solarized(),
+            None => background.map_or_else(Theme::dark, Theme::for_background),
+        };
+        let overrides = [
+            (&mut theme.context, self.context),
+            (&mut theme.border, self.border),
+            (&mut theme.status, self.status),
+            (&mut theme.search, self.search),
+            (&mut theme.boundary, self.boundary),
+            (&mut theme.added, self.added),
+            (&mut theme.removed, self.removed),
+        ];
+        for (style, config) in overrides {
+            if let Some(config) = config {
+                *style = config.style();
+            }
+        }
+        match self.monochrome {
+            true => theme.without_colors(),
+            false => theme,
+        }
+    }
+}
+
+impl StyleConfig {
+    fn style(&self) -> Style {
+        let mut style = Style::default();
+        if let Some(ThemeColor(fg)) = self.fg {
+            style = style.fg(fg);
+        }
+        if let Some(ThemeColor(bg)) = self.bg {
+            style = style.bg(bg);
+        }
+        let modifiers = [
+            (self.bold, Modifier::BOLD),
+            (self.italic, Modifier::ITALIC),
+            (self.underlined, Modifier::UNDERLINED),
+            (self.reversed, Modifier::REVERSED),
+        ];
+        for (enabled, modifier) in modifiers {
+            if enabled {
+                style = style.add_modifier(modifier);
+            }
+        }
+        style
+    }
+}
+
+impl TryFrom<toml::Value> for ThemeColor {
+    type Error = String;
+
+    fn try_from(value: toml::Value) -> Result<Self, Self::Error> {
+        let name = match value {
+            toml::Value::Integer(index) => {
+                return u8::try_from(index)
+                    .map(|index| ThemeColor(Color::Indexed(index)))
+                    .map_err(|_| format!("No color {index} in the 256 color palette"));
+            }
+            toml::Value::String(name) => name,
+            other => return Err(format!("Expected a color, got {other}")),
+        };
+        if let Some(hex) = name.strip_prefix('#') {
+            let channel = |range| {
+                hex.get(range)
+                    .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+            };
+            return match (hex.len(), channel(0..2), channel(2..4), channel(4..6)) {
+                (6, Some(r), Some(g), Some(b)) => Ok(ThemeColor(Color::Rgb(r, g, b))),
+                _ => Err(format!("Invalid color {name}, expected #rrggbb")),
+            };
+        }
+        let color = match name.to_lowercase().as_str() {
+            "reset" => Color::Reset,
+            "black" => Color::Black,
+            "red" => Color::Red,
+            "green" => Color::Green,
+            "yellow" => Color::Yellow,
+            "blue" => Color::Blue,
+            "magenta" => Color::Magenta,
+            "cyan" => Color::Cyan,
+            "gray" => Color::Gray,
+            "darkgray" => Color::DarkGray,
+            "lightred" => Color::LightRed,
+            "lightgreen" => Color::LightGreen,
+            "lightyellow" => Color::LightYellow,
+            "lightblue" => Color::LightBlue,
+            "lightmagenta" => Color::LightMagenta,
+            "lightcyan" => Color::LightCyan,
+            "white" => Color::White,
+            _ => return Err(format!("Unknown color {name}")),
+        };
+        Ok(ThemeColor(color))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::style::{Color, Modifier, Style};
+
+    use super::{Theme, ThemeColor, ThemeConfig};
+    use crate::background::Background;
+
+    fn color(value: toml::Value) -> Result<Color, String> {
+        ThemeColor::try_from(value).map(|ThemeColor(color)| color)
+    }
+
+    #[test]
+    fn parse_colors() {
+        assert_eq!(color("DarkGray".into()), Ok(Color::DarkGray));
+        assert_eq!(color("#93a1A1".into()), Ok(Color::Rgb(0x93, 0xa1, 0xa1)));
+        assert_eq!(color(toml::Value::Integer(208)), Ok(Color::Indexed(208)));
+        assert!(color("#93a1".into()).is_err());
+        assert!(color("#ééé".into()).is_err());
+        assert!(color("purple".into()).is_err());
+        assert!(color(toml::Value::Integer(256)).is_err());
+    }
+
+    #[test]
+    fn override_preset() {
+        let config: ThemeConfig = toml::from_str(
+            r#"
+            preset = 'solarized'
+            added = { fg = 'lightgreen', bold = true }
+            "#,
+        )
+        .unwrap();
+        let theme = config.theme(Some(Background::Light));
+        assert_eq!(
+            theme.added,
+            Style::default()
+                .fg(Color::LightGreen)
+                .add_modifier(Modifier::BOLD)
+        );
+        assert_eq!(theme.removed, Theme::solarized().removed);
+        // Without a preset the theme follows the background
+        let theme = ThemeConfig::default().theme(Some(Background::Light));
+        assert_eq!(theme.context, Theme::light().context);
+    }
+
+    #[test]
+    fn monochrome() {
+        let config: ThemeConfig = toml::from_str(
+            r#"
+            monochrome = true
+            added = { fg = 'lightgreen', bold = true }
+            "#,
+        )
+        .unwrap();
+        let theme = config.theme(None);
+        assert_eq!(theme.added, Style::default().add_modifier(Modifier::BOLD));
+        assert_eq!(theme.commit, Style::default());
+        assert_eq!(theme.source(7), Style::default());
+        assert_eq!(
+            theme.search,
+            Style::default().add_modifier(Modifier::REVERSED)
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+
+/// Ways to derive a new buffer from the lines shown, for quick analysis
+/// without leaving the pager. The original lines are left untouched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    /// Sort the lines.
+    Sort,
+    /// Keep the first of every distinct line. With `count`, prefix each with
+    /// how often it occurs, most frequent first, like `sort | uniq -c | sort
+    /// -rn`.
+    Uniq { count: bool },
+}
+
+impl Transform {
+    /// Parses the arguments of `:uniq`.
+    pub fn parse_uniq(args: &str) -> Result<Self, String> {
+        match args.trim() {
+            "" => Ok(Transform::Uniq { count: false }),
+            "-c" => Ok(Transform::Uniq { count: true }),
+            other => Err(format!("Unexpected argument: {other}")),
+        }
+    }
+
+    /// Title of the tab showing the result.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Transform::Sort => "sort",
+            Transform::Uniq { count: false } => "uniq",
+            Transform::Uniq { count: true } => "uniq -c",
+        }
+    }
+
+    pub fn apply<'a, S: AsRef<str> + 'a>(
+        &self,
+        lines: impl IntoIterator<Item = &'a S>,
+    ) -> Vec<String> {
+        match self {
+            Transform::Sort => {
+                let mut sorted: Vec<String> = lines
+                    .into_iter()
+                    .map(|line| line.as_ref().to_string())
+                    .collect();
+                sorted.sort();
+                sorted
+            }
+            Transform::Uniq { count } => {
+                let mut counts: HashMap<&str, usize> = HashMap::new();
+                let mut distinct = Vec::new();
+                for line in lines {
+                    let line = line.as_ref();
+                    *counts.entry(line).or_insert_with(|| {
+                        distinct.push(line);
+                        0
+                    }) += 1;
+                }
+                if !count {
+                    return distinct.into_iter().map(str::to_string).collect();
+                }
+                // Stable, so equally frequent lines keep their order
+                distinct.sort_by_key(|line| std::cmp::Reverse(counts[line]));
+                let width = counts.values().max().map_or(0, |max| max.to_string().len());
+                distinct
+                    .into_iter()
+                    .map(|line| format!("{:>width$} {line}", counts[line]))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// The `limit` most frequent values of `pattern` in `lines` with how often
+/// each occurs, most frequent first. The value is the first capture group,
+/// or the whole match if there is none.
+pub fn top_values<'a, S: AsRef<str> + 'a>(
+    pattern: &Regex,
+    lines: impl IntoIterator<Item = &'a S>,
+    limit: usize,
+) -> Vec<(String, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut values = Vec::new();
+    for line in lines {
+        for captures in pattern.captures_iter(line.as_ref()) {
+            let Some(value) = captures.get(1).or_else(|| captures.get(0)) else {
+                continue;
+            };
+            *counts.entry(value.as_str()).or_insert_with(|| {
+                values.push(value.as_str());
+                0
+            }) += 1;
+        }
+    }
+    // Stable, so equally frequent values keep the order they were found in
+    values.sort_by_key(|value| std::cmp::Reverse(counts[value]));
+    values
+        .into_iter()
+        .take(limit)
+        .map(|value| (value.to_string(), counts[value]))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use regex::Regex;
+
+    use super::{top_values, Transform};
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn sort_and_uniq() {
+        let input = lines(&["b", "a", "c", "a", "b", "a"]);
+        assert_eq!(
+            Transform::Sort.apply(&input),
+            lines(&["a", "a", "a", "b", "b", "c"])
+        );
+        assert_eq!(
+            Transform::Uniq { count: false }.apply(&input),
+            lines(&["b", "a", "c"])
+        );
+        let counted = Transform::parse_uniq("-c").unwrap().apply(&input);
+        assert_eq!(counted, lines(&["3 a", "2 b", "1 c"]));
+        assert!(Transform::parse_uniq("-x").is_err());
+    }
+
+    #[test]
+    fn count_top_values() {
+        let input = lines(&[
+            "login user=ann",
+            "login user=bob",
+            "logout user=ann",
+            "no user here",
+            "login user=cid user=ann",
+        ]);
+        let pattern = Regex::new(r"user=(\w+)").unwrap();
+        assert_eq!(
+            top_values(&pattern, &input, 2),
+            vec![("ann".to_string(), 3), ("bob".to_string(), 1)]
+        );
+    }
+}
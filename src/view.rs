@@ -0,0 +1,122 @@
+use std::ops::Range;
+
+/// Maps the rows shown on screen to line numbers in the full buffer when only
+/// some of the lines are shown.
+#[derive(Debug, Default)]
+pub struct FilterView {
+    /// Buffer line numbers of the shown lines, `None` when nothing is hidden.
+    rows: Option<Vec<usize>>,
+}
+
+impl FilterView {
+    /// Number of rows that can be shown out of a buffer of `num_lines`.
+    pub fn len(&self, num_lines: usize) -> usize {
+        self.rows.as_ref().map_or(num_lines, |rows| rows.len())
+    }
+
+    /// Number of rows shown and the bytes their index takes, if only some
+    /// lines are shown.
+    pub fn index_size(&self) -> Option<(usize, usize)> {
+        let rows = self.rows.as_ref()?;
+        Some((rows.len(), rows.capacity() * std::mem::size_of::<usize>()))
+    }
+
+    /// Buffer line numbers for the given range of rows, clamped to what is
+    /// available.
+    pub fn lines(&self, rows: Range<usize>, num_lines: usize) -> Vec<usize> {
+        let end = std::cmp::min(rows.end, self.len(num_lines));
+        let start = std::cmp::min(rows.start, end);
+        match &self.rows {
+            Some(shown) => shown[start..end].to_vec(),
+            None => (start..end).collect(),
+        }
+    }
+
+    /// Row showing buffer line `line`, or the next shown line if it is hidden.
+    pub fn row(&self, line: usize) -> usize {
+        match &self.rows {
+            Some(shown) => shown.partition_point(|shown| *shown < line),
+            None => line,
+        }
+    }
+
+    /// Row showing buffer line `line`, or the closest shown line if it is
+    /// hidden, the one below when both are as close.
+    pub fn nearest_row(&self, line: usize) -> usize {
+        let Some(shown) = &self.rows else {
+            return line;
+        };
+        let below = shown.partition_point(|shown| *shown < line);
+        match (below.checked_sub(1), shown.get(below)) {
+            (Some(above), Some(next)) if line - shown[above] < next - line => above,
+            (Some(above), None) => above,
+            _ => below,
+        }
+    }
+
+    /// Shows only the lines in `lines` for which `keep` is true.
+    pub fn filter(&mut self, lines: Range<usize>, keep: impl Fn(usize) -> bool) {
+        self.rows = Some(lines.filter(|line| keep(*line)).collect());
+    }
+
+    /// Shows every line again.
+    pub fn clear(&mut self) {
+        self.rows = None;
+    }
+
+    /// Forgets the first `lines` lines of the buffer, numbering the rest from
+    /// zero again. Returns how many rows they were shown on.
+    pub fn discard(&mut self, lines: usize) -> usize {
+        match &mut self.rows {
+            Some(rows) => {
+                let discarded = rows.partition_point(|line| *line < lines);
+                rows.drain(..discarded);
+                for line in rows {
+                    *line -= lines;
+                }
+                discarded
+            }
+            None => lines,
+        }
+    }
+
+    /// Adds newly read lines to a filtered view.
+    pub fn extend(&mut self, lines: Range<usize>, keep: impl Fn(usize) -> bool) {
+        if let Some(rows) = &mut self.rows {
+            rows.extend(lines.filter(|line| keep(*line)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FilterView;
+
+    #[test]
+    fn unfiltered_view_maps_rows_to_lines() {
+        let view = FilterView::default();
+        assert_eq!(view.len(10), 10);
+        assert_eq!(view.lines(8..12, 10), vec![8, 9]);
+    }
+
+    #[test]
+    fn filtered_view_skips_hidden_lines() {
+        let mut view = FilterView::default();
+        view.filter(0..10, |line| line % 3 == 0);
+        assert_eq!(view.len(10), 4);
+        assert_eq!(view.lines(2..5, 10), vec![6, 9]);
+        assert_eq!(view.row(6), 2);
+        assert_eq!(view.row(7), 3);
+        assert_eq!(view.nearest_row(7), 2);
+        assert_eq!(view.nearest_row(8), 3);
+        assert_eq!(view.nearest_row(9), 3);
+        assert_eq!(view.nearest_row(11), 3);
+        view.extend(10..13, |line| line % 3 == 0);
+        assert_eq!(view.lines(2..10, 13), vec![6, 9, 12]);
+        assert_eq!(view.discard(7), 3);
+        assert_eq!(view.lines(0..10, 6), vec![2, 5]);
+        view.clear();
+        assert_eq!(view.len(13), 13);
+        assert_eq!(view.discard(7), 7);
+    }
+}
@@ -0,0 +1,103 @@
+use std::ops::Range;
+
+/// The rows of the buffer shown on screen. Scrolling stays within the rows
+/// read so far, which keep growing while the input is still streaming.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    /// First row shown.
+    pub position: usize,
+    /// Number of rows that fit on screen.
+    pub height: usize,
+    /// Rows that fit on screen at `position` and on the last screen when
+    /// long lines are wrapped onto several screen rows, as measured when last
+    /// drawn, or `None` when every row takes one.
+    pub wrapped: Option<(usize, usize)>,
+}
+
+impl Viewport {
+    pub fn new(height: usize) -> Self {
+        Viewport {
+            position: 0,
+            height,
+            wrapped: None,
+        }
+    }
+
+    /// Rows shown on screen, some of which may not have been read yet.
+    pub fn rows(&self) -> Range<usize> {
+        self.position..self.position + self.shown()
+    }
+
+    /// Number of rows shown on screen.
+    pub fn shown(&self) -> usize {
+        self.wrapped.map_or(self.height, |(shown, _)| shown)
+    }
+
+    /// Furthest position showing the last screen of `num_rows`, or the top
+    /// while they all fit on one screen, like while a short input is still
+    /// streaming in.
+    pub fn max_scroll(&self, num_rows: usize) -> usize {
+        num_rows.saturating_sub(self.wrapped.map_or(self.height, |(_, last)| last))
+    }
+
+    /// `position` moved back within `num_rows` if scrolled past their last
+    /// screen.
+    pub fn clamp(&self, position: usize, num_rows: usize) -> usize {
+        std::cmp::min(position, self.max_scroll(num_rows))
+    }
+
+    /// Scrolls down `count` rows, but no further than the last screen of
+    /// `num_rows`. A position already past it, e.g. after the screen grew or
+    /// rows were filtered out, is kept rather than jumping back up.
+    pub fn scroll_down(&mut self, count: usize, num_rows: usize) {
+        let target = self.clamp(self.position.saturating_add(count), num_rows);
+        self.position = std::cmp::max(self.position, target);
+    }
+
+    pub fn scroll_up(&mut self, count: usize) {
+        self.position = self.position.saturating_sub(count);
+    }
+
+    /// Rows scrolled by half a screen, at least one.
+    pub fn half_page(&self) -> usize {
+        std::cmp::max(1, self.shown() / 2)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Viewport;
+
+    #[test]
+    fn scroll_within_rows_read() {
+        let mut viewport = Viewport::new(10);
+        // A streaming input shorter than a screen doesn't scroll
+        viewport.scroll_down(5, 4);
+        assert_eq!(viewport.position, 0);
+        assert_eq!(viewport.max_scroll(4), 0);
+        viewport.scroll_down(5, 100);
+        assert_eq!(viewport.rows(), 5..15);
+        viewport.scroll_down(1000, 100);
+        assert_eq!(viewport.position, 90);
+        viewport.scroll_up(viewport.half_page());
+        assert_eq!(viewport.position, 85);
+        assert_eq!(viewport.clamp(95, 100), 90);
+        // Scrolling down past the last screen doesn't jump back up
+        viewport.position = 95;
+        viewport.scroll_down(1, 100);
+        assert_eq!(viewport.position, 95);
+        assert_eq!(Viewport::new(1).half_page(), 1);
+    }
+
+    #[test]
+    fn scroll_through_wrapped_rows() {
+        let mut viewport = Viewport::new(10);
+        // Four rows fill the screen, and the last three the last screen
+        viewport.wrapped = Some((4, 3));
+        assert_eq!(viewport.rows(), 0..4);
+        assert_eq!(viewport.half_page(), 2);
+        assert_eq!(viewport.max_scroll(100), 97);
+        viewport.scroll_down(1000, 100);
+        assert_eq!(viewport.position, 97);
+    }
+}
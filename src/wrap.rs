@@ -0,0 +1,119 @@
+use std::borrow::Cow;
+
+use ratatui::text::Span;
+use unicode_width::UnicodeWidthChar;
+
+/// Cuts `spans` into rows of at most `width` columns. A span crossing the end
+/// of a row is split with both parts keeping its style, so a search match
+/// wrapping onto the next row stays highlighted on both. Wide characters move
+/// to the next row whole rather than being split.
+pub fn wrap(spans: Vec<Span<'_>>, width: usize) -> Vec<Vec<Span<'_>>> {
+    let mut rows = vec![Vec::new()];
+    let mut used = 0;
+    for span in spans {
+        let style = span.style;
+        let mut rest = span.content;
+        while !rest.is_empty() {
+            // Byte offset where the row fills up, if it does
+            let mut cut = None;
+            for (offset, c) in rest.char_indices() {
+                let columns = c.width().unwrap_or(0);
+                // A character wider than the row still takes one of its own
+                if used + columns > width && used > 0 {
+                    cut = Some(offset);
+                    break;
+                }
+                used += columns;
+            }
+            let Some(cut) = cut else {
+                rows.last_mut().unwrap().push(Span::styled(rest, style));
+                break;
+            };
+            let (head, tail) = split(rest, cut);
+            if !head.is_empty() {
+                rows.last_mut().unwrap().push(Span::styled(head, style));
+            }
+            rows.push(Vec::new());
+            used = 0;
+            rest = tail;
+        }
+    }
+    rows
+}
+
+/// `text` split at byte offset `at`, borrowing from it if it is borrowed.
+fn split(text: Cow<'_, str>, at: usize) -> (Cow<'_, str>, Cow<'_, str>) {
+    match text {
+        Cow::Borrowed(text) => (Cow::Borrowed(&text[..at]), Cow::Borrowed(&text[at..])),
+        Cow::Owned(text) => (
+            Cow::Owned(text[..at].to_string()),
+            Cow::Owned(text[at..].to_string()),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::{
+        style::{Color, Style},
+        text::Span,
+    };
+
+    use super::wrap;
+
+    fn rows(spans: Vec<Span>, width: usize) -> Vec<Vec<(String, Style)>> {
+        wrap(spans, width)
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|span| (span.content.into_owned(), span.style))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keep_highlights_across_rows() {
+        let plain = Style::default();
+        let found = Style::default().bg(Color::Yellow);
+        // The match starts at column 6 and ends on the next row
+        let spans = vec![
+            Span::styled("error ", plain),
+            Span::styled("connection", found),
+            Span::styled(" refused", plain),
+        ];
+        assert_eq!(
+            rows(spans, 10),
+            [
+                vec![("error ".to_string(), plain), ("conn".to_string(), found)],
+                vec![("ection".to_string(), found), (" ref".to_string(), plain)],
+                vec![("used".to_string(), plain)],
+            ]
+        );
+    }
+
+    #[test]
+    fn wrap_by_columns() {
+        let plain = Style::default();
+        // Each of these takes two columns
+        assert_eq!(
+            rows(vec![Span::raw("日本語")], 5),
+            [
+                vec![("日本".to_string(), plain)],
+                vec![("語".to_string(), plain)],
+            ]
+        );
+        assert_eq!(
+            rows(vec![Span::raw("日本")], 1),
+            [
+                vec![("日".to_string(), plain)],
+                vec![("本".to_string(), plain)],
+            ]
+        );
+        assert_eq!(rows(vec![Span::raw("")], 5), [vec![]]);
+        assert_eq!(
+            rows(vec![Span::raw("short")], 5),
+            [vec![("short".to_string(), plain)]]
+        );
+    }
+}
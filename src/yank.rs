@@ -0,0 +1,172 @@
+use serde::Deserialize;
+
+/// What yanking a commit copies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum YankFormat {
+    /// The full hash.
+    Hash,
+    /// The short hash and the subject, like `b8e882d Read stdin`.
+    HashSubject,
+    /// Every line of the commit, from its header to the end of its diff.
+    Patch,
+    /// A Markdown link to the commit on the forge, titled with the short hash
+    /// and the subject.
+    Markdown,
+    /// The lines of the context panel.
+    Context,
+    /// Ask which of the above to copy.
+    Choose,
+}
+
+/// The formats the chooser offers, in order.
+const CHOICES: [YankFormat; 5] = [
+    YankFormat::Context,
+    YankFormat::Hash,
+    YankFormat::HashSubject,
+    YankFormat::Patch,
+    YankFormat::Markdown,
+];
+
+/// The `[yank]` section of the config: what `y` and `Y` copy of a commit.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct YankConfig {
+    /// What `y` copies.
+    pub y: YankFormat,
+    /// What `Y` copies.
+    #[serde(rename = "Y")]
+    pub shift_y: YankFormat,
+    /// URL of a commit on the forge with `{hash}` in place of its hash, like
+    /// `https://github.com/owner/repo/commit/{hash}`, for Markdown links.
+    pub forge_url: Option<String>,
+}
+
+impl Default for YankConfig {
+    fn default() -> Self {
+        YankConfig {
+            y: YankFormat::Hash,
+            shift_y: YankFormat::Choose,
+            forge_url: None,
+        }
+    }
+}
+
+/// A commit as it can be copied.
+pub struct Commit<'a> {
+    pub hash: &'a str,
+    /// First line of the message, empty if it hasn't been read.
+    pub subject: &'a str,
+    pub patch: &'a [String],
+    pub context: &'a [String],
+}
+
+impl YankFormat {
+    /// Name of the format in the chooser.
+    pub fn label(self) -> &'static str {
+        match self {
+            YankFormat::Hash => "hash",
+            YankFormat::HashSubject => "hash and subject",
+            YankFormat::Patch => "patch",
+            YankFormat::Markdown => "Markdown link",
+            YankFormat::Context => "context",
+            YankFormat::Choose => "choose",
+        }
+    }
+
+    /// The text copied of `commit`, or `None` if the format asks for a choice
+    /// or needs a forge URL that isn't configured.
+    pub fn text(self, commit: &Commit, forge_url: Option<&str>) -> Option<String> {
+        let short_hash = &commit.hash[..std::cmp::min(commit.hash.len(), 7)];
+        let title = match commit.subject {
+            "" => short_hash.to_string(),
+            subject => format!("{short_hash} {subject}"),
+        };
+        Some(match self {
+            YankFormat::Hash => commit.hash.to_string(),
+            YankFormat::HashSubject => title,
+            YankFormat::Patch => commit.patch.join("\n"),
+            YankFormat::Markdown => {
+                let url = forge_url?.replace("{hash}", commit.hash);
+                // Brackets in the subject would end the link text early
+                let title = title.replace('[', "\\[").replace(']', "\\]");
+                format!("[{title}]({url})")
+            }
+            YankFormat::Context => commit.context.join("\n"),
+            YankFormat::Choose => return None,
+        })
+    }
+}
+
+/// The formats that can be copied of `commit`, with the text of each.
+pub fn choices(commit: &Commit, forge_url: Option<&str>) -> Vec<(YankFormat, String)> {
+    CHOICES
+        .iter()
+        .filter_map(|format| Some((*format, format.text(commit, forge_url)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{choices, Commit, YankConfig, YankFormat};
+
+    #[test]
+    fn format_commit() {
+        let patch = [
+            "commit b8e882d50a8e2f184e8803a18818da18dbbd1469",
+            "",
+            "    Read stdin [WIP]",
+        ]
+        .map(String::from);
+        let commit = Commit {
+            hash: "b8e882d50a8e2f184e8803a18818da18dbbd1469",
+            subject: "Read stdin [WIP]",
+            patch: &patch,
+            context: &patch[..1],
+        };
+        let forge = Some("https://example.com/cag/commit/{hash}");
+        assert_eq!(
+            YankFormat::HashSubject.text(&commit, None).unwrap(),
+            "b8e882d Read stdin [WIP]"
+        );
+        assert_eq!(
+            YankFormat::Markdown.text(&commit, forge).unwrap(),
+            "[b8e882d Read stdin \\[WIP\\]](https://example.com/cag/commit/b8e882d50a8e2f184e8803a18818da18dbbd1469)"
+        );
+        assert_eq!(
+            YankFormat::Patch.text(&commit, None).unwrap(),
+            patch.join("\n")
+        );
+        // Markdown links need to know where the commits are
+        let offered: Vec<YankFormat> = choices(&commit, None)
+            .into_iter()
+            .map(|(format, _)| format)
+            .collect();
+        assert_eq!(
+            offered,
+            [
+                YankFormat::Context,
+                YankFormat::Hash,
+                YankFormat::HashSubject,
+                YankFormat::Patch
+            ]
+        );
+        assert_eq!(choices(&commit, forge).len(), 5);
+        assert_eq!(YankFormat::Choose.text(&commit, forge), None);
+    }
+
+    #[test]
+    fn parse_config() {
+        let config: YankConfig = toml::from_str(
+            r#"
+            y = "hash-subject"
+            Y = "patch"
+            forge_url = "https://example.com/{hash}"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.y, YankFormat::HashSubject);
+        assert_eq!(config.shift_y, YankFormat::Patch);
+        assert_eq!(YankConfig::default().shift_y, YankFormat::Choose);
+    }
+}